@@ -0,0 +1,393 @@
+//! claude-workbench-cli: headless companion of the desktop app for scripts and CI.
+//!
+//! Shares the `commands` modules with the app, so it reads and writes the same
+//! `~/.claude` files and the same `agents.db` in the app data directory.
+//! Results go to stdout (JSON unless stated otherwise), errors to stderr with exit code 1.
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use claude_workbench_lib::claude_binary;
+use claude_workbench_lib::commands::mcp::{self, MCPServer, ServerStatus};
+use claude_workbench_lib::commands::prompt_tracker::{self, RewindMode};
+use claude_workbench_lib::commands::{claude, provider, slash_commands, storage, usage};
+use serde::Serialize;
+
+const USAGE: &str = "\
+Usage: claude-workbench-cli <command> [args]
+
+Commands:
+  projects                                  List projects in ~/.claude/projects
+  sessions <project-id>                     List sessions of a project
+  usage [--days N] [--format json|csv] [--by date|model|project]
+                                            Export usage statistics
+  provider list|current                     Show provider presets / active config
+  provider switch <id>                      Switch to a provider preset
+  provider clear                            Remove provider settings
+  mcp list                                  List MCP servers
+  mcp get <name>                            Show one MCP server
+  mcp add <name> [--transport stdio|sse|http] [--scope local|project|user]
+          [--env KEY=VALUE]... [--url URL] [-- <command> [args]...]
+  mcp add-json <name> <json> [--scope local|project|user]
+  mcp remove <name>
+  slash list [--project <path>]             List slash commands
+  slash save <name> --scope user|project [--namespace NS] [--description TEXT]
+          [--tools a,b] [--project <path>] [--file <path>]
+                                            Save a slash command (content from --file or stdin)
+  revert <session-id> <project-id> <project-path> <prompt-index>
+          [--mode conversation_only|code_only|both]
+                                            Rewind a session to a prompt
+";
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "help" || args[0] == "--help" || args[0] == "-h" {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("claude-workbench-cli: failed to start runtime: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = runtime.block_on(run(&args)) {
+        eprintln!("claude-workbench-cli: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: &[String]) -> Result<(), String> {
+    let cli = Args::parse(&args[1..]);
+
+    match args[0].as_str() {
+        "projects" => print_json(&claude::list_projects().await?),
+        "sessions" => {
+            let project_id = cli.positional(0, "project-id")?;
+            print_json(&claude::get_project_sessions(project_id.to_string()).await?)
+        }
+        "usage" => run_usage(&cli),
+        "provider" => run_provider(&cli).await,
+        "mcp" => run_mcp(&cli),
+        "slash" => run_slash(&cli).await,
+        "revert" => run_revert(&cli),
+        other => Err(format!("Unknown command '{}'\n\n{}", other, USAGE)),
+    }
+}
+
+fn app_data_dir() -> Result<PathBuf, String> {
+    claude_binary::headless_app_data_dir()
+        .ok_or_else(|| "Failed to get app data directory".to_string())
+}
+
+fn claude_path() -> Result<String, String> {
+    claude_binary::find_claude_binary_in(Some(&app_data_dir()?))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize output: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Minimal argument parser: `--name value` options (repeatable), positionals,
+/// and everything after a bare `--` kept verbatim
+struct Args {
+    positionals: Vec<String>,
+    options: Vec<(String, String)>,
+    trailing: Vec<String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Self {
+        let mut parsed = Args {
+            positionals: Vec::new(),
+            options: Vec::new(),
+            trailing: Vec::new(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                parsed.trailing = iter.cloned().collect();
+                break;
+            }
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = iter.next().cloned().unwrap_or_default();
+                    parsed.options.push((name.to_string(), value));
+                }
+                None => parsed.positionals.push(arg.clone()),
+            }
+        }
+
+        parsed
+    }
+
+    fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positionals
+            .get(index)
+            .map(|s| s.as_str())
+            .ok_or_else(|| format!("Missing <{}>\n\n{}", name, USAGE))
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn options(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+}
+
+fn run_usage(cli: &Args) -> Result<(), String> {
+    let days = cli
+        .option("days")
+        .map(|d| {
+            d.parse::<u32>()
+                .map_err(|_| format!("Invalid --days value: {}", d))
+        })
+        .transpose()?;
+
+    let mut conn = storage::open_database(&app_data_dir()?)
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    // No background ingester here: bring usage_entries up to date first
+    usage::refresh_usage_entries(&mut conn)?;
+    let stats = usage::usage_stats(&conn, days)?;
+
+    match cli.option("format").unwrap_or("json") {
+        "json" => print_json(&stats),
+        "csv" => {
+            let stats = serde_json::to_value(&stats).map_err(|e| e.to_string())?;
+            print!("{}", usage_csv(&stats, cli.option("by").unwrap_or("date"))?);
+            Ok(())
+        }
+        other => Err(format!("Unknown format '{}', expected json or csv", other)),
+    }
+}
+
+/// One CSV table of the usage breakdown selected by `--by`
+fn usage_csv(stats: &serde_json::Value, by: &str) -> Result<String, String> {
+    let columns: &[&str] = match by {
+        "date" => &["date", "total_cost", "total_tokens", "models_used"],
+        "model" => &[
+            "model",
+            "total_cost",
+            "total_tokens",
+            "input_tokens",
+            "output_tokens",
+            "cache_creation_tokens",
+            "cache_read_tokens",
+            "session_count",
+        ],
+        "project" => &[
+            "project_path",
+            "project_name",
+            "total_cost",
+            "total_tokens",
+            "session_count",
+            "last_used",
+        ],
+        other => {
+            return Err(format!(
+                "Unknown grouping '{}', expected date, model or project",
+                other
+            ))
+        }
+    };
+
+    let mut csv = columns.join(",");
+    csv.push('\n');
+    let rows = stats
+        .get(format!("by_{}", by))
+        .and_then(|rows| rows.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for row in rows {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|column| csv_field(row.get(*column).unwrap_or(&serde_json::Value::Null)))
+            .collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+
+    Ok(csv)
+}
+
+fn csv_field(value: &serde_json::Value) -> String {
+    let text = match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| item.to_string())
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
+        other => other.to_string(),
+    };
+
+    if text.contains(',') || text.contains('"') || text.contains('\n') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+async fn run_provider(cli: &Args) -> Result<(), String> {
+    match cli.positional(0, "provider command")? {
+        "list" => print_json(&provider::get_provider_presets()?),
+        "current" => print_json(&provider::get_current_provider_config()?),
+        "switch" => {
+            let id = cli.positional(1, "id")?;
+            let config = provider::get_provider_config(id.to_string())?;
+            println!("{}", provider::switch_provider_config(config).await?);
+            Ok(())
+        }
+        "clear" => {
+            println!("{}", provider::clear_provider_config().await?);
+            Ok(())
+        }
+        other => Err(format!("Unknown provider command '{}'", other)),
+    }
+}
+
+fn run_mcp(cli: &Args) -> Result<(), String> {
+    let subcommand = cli.positional(0, "mcp command")?;
+    let claude_path = claude_path()?;
+
+    let result = match subcommand {
+        "list" => return print_json(&mcp::list_servers(&claude_path)?),
+        "get" => {
+            let name = cli.positional(1, "name")?;
+            return print_json(&mcp::get_server(&claude_path, name.to_string())?);
+        }
+        "remove" => {
+            let name = cli.positional(1, "name")?;
+            println!("{}", mcp::remove_server(&claude_path, name)?);
+            return Ok(());
+        }
+        "add" => {
+            let mut env = std::collections::HashMap::new();
+            for pair in cli.options("env") {
+                let (key, value) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("Invalid --env value '{}', expected KEY=VALUE", pair))?;
+                env.insert(key.to_string(), value.to_string());
+            }
+
+            let server = MCPServer {
+                name: cli.positional(1, "name")?.to_string(),
+                transport: cli.option("transport").unwrap_or("stdio").to_string(),
+                command: cli.trailing.first().cloned(),
+                args: cli.trailing.iter().skip(1).cloned().collect(),
+                env,
+                url: cli.option("url").map(str::to_string),
+                scope: cli.option("scope").unwrap_or("local").to_string(),
+                is_active: false,
+                status: ServerStatus::default(),
+            };
+            mcp::add_server(&claude_path, &server)
+        }
+        "add-json" => mcp::add_server_json(
+            &claude_path,
+            cli.positional(1, "name")?.to_string(),
+            cli.positional(2, "json")?,
+            cli.option("scope").unwrap_or("local"),
+        ),
+        other => return Err(format!("Unknown mcp command '{}'", other)),
+    };
+
+    if !result.success {
+        return Err(result.message);
+    }
+    print_json(&result)
+}
+
+async fn run_slash(cli: &Args) -> Result<(), String> {
+    let project_path = cli.option("project").map(str::to_string);
+
+    match cli.positional(0, "slash command")? {
+        "list" => print_json(&slash_commands::slash_commands_list(project_path).await?),
+        "save" => {
+            let content = match cli.option("file") {
+                Some(path) => std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+                None => {
+                    let mut content = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut content)
+                        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+                    content
+                }
+            };
+            let allowed_tools = cli
+                .option("tools")
+                .map(|tools| {
+                    tools
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let command = slash_commands::slash_command_save(
+                cli.option("scope").unwrap_or("user").to_string(),
+                cli.positional(1, "name")?.to_string(),
+                cli.option("namespace").map(str::to_string),
+                content,
+                cli.option("description").map(str::to_string),
+                allowed_tools,
+                project_path,
+            )
+            .await?;
+            print_json(&command)
+        }
+        other => Err(format!("Unknown slash command '{}'", other)),
+    }
+}
+
+fn run_revert(cli: &Args) -> Result<(), String> {
+    let session_id = cli.positional(0, "session-id")?;
+    let project_id = cli.positional(1, "project-id")?;
+    let project_path = cli.positional(2, "project-path")?;
+    let prompt_index = cli
+        .positional(3, "prompt-index")?
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid prompt index: {}", e))?;
+    let mode: RewindMode = serde_json::from_value(serde_json::Value::String(
+        cli.option("mode").unwrap_or("both").to_string(),
+    ))
+    .map_err(|_| "Invalid --mode, expected conversation_only, code_only or both".to_string())?;
+
+    // The prompt text is printed so scripts can re-submit or edit it
+    let prompt = prompt_tracker::rewind_to_prompt(
+        &app_data_dir()?,
+        session_id,
+        project_id,
+        project_path,
+        prompt_index,
+        mode,
+    )?;
+    println!("{}", prompt);
+    Ok(())
+}
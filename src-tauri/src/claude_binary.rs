@@ -5,7 +5,7 @@ use std::cmp::Ordering;
 /// Shared module for detecting Claude Code binary installations
 /// Supports NVM installations, aliased paths, version-based selection, and bundled sidecars
 /// Cross-platform support for Windows and macOS
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use tauri::Manager;
 
@@ -45,13 +45,28 @@ pub struct ClaudeInstallation {
     pub installation_type: InstallationType,
 }
 
+/// Tauri app identifier (tauri.conf.json); the app data dir is named after it
+const APP_IDENTIFIER: &str = "claude.workbench.app";
+
+/// The app data dir as `app.path().app_data_dir()` resolves it, for entry points
+/// that run without a Tauri app (pre-commit shim, headless CLI)
+pub fn headless_app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
 /// Main function to find the Claude binary - Cross-platform version
 /// Supports Windows and macOS, only uses system-installed Claude CLI
 pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, String> {
+    find_claude_binary_in(app_handle.path().app_data_dir().ok().as_deref())
+}
+
+/// Same as `find_claude_binary`, reading and caching the selected path in
+/// `<app_data_dir>/agents.db` (headless callers pass the directory directly)
+pub fn find_claude_binary_in(app_data_dir: Option<&Path>) -> Result<String, String> {
     info!("Searching for system Claude CLI...");
 
     // First check if we have a stored path in the database
-    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
+    if let Some(app_data_dir) = app_data_dir {
         let db_path = app_data_dir.join("agents.db");
         if db_path.exists() {
             if let Ok(conn) = rusqlite::Connection::open(&db_path) {
@@ -114,7 +129,7 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
         );
 
         // Store the successful path in database for future use
-        if let Err(e) = store_claude_path(app_handle, &best.path) {
+        if let Err(e) = store_claude_path(app_data_dir, &best.path) {
             warn!("Failed to store claude path in database: {}", e);
         }
 
@@ -125,9 +140,9 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
 }
 
 /// Store Claude CLI path in database for future use
-fn store_claude_path(app_handle: &tauri::AppHandle, path: &str) -> Result<(), String> {
-    if let Ok(app_data_dir) = app_handle.path().app_data_dir() {
-        if let Err(e) = std::fs::create_dir_all(&app_data_dir) {
+fn store_claude_path(app_data_dir: Option<&Path>, path: &str) -> Result<(), String> {
+    if let Some(app_data_dir) = app_data_dir {
+        if let Err(e) = std::fs::create_dir_all(app_data_dir) {
             return Err(format!("Failed to create app data directory: {}", e));
         }
 
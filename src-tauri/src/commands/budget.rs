@@ -0,0 +1,413 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+use super::pricing::TokenUsage;
+use super::storage::AgentDb;
+
+/// Budgets live next to the other Claude config files
+const BUDGETS_FILE: &str = "budgets.json";
+
+/// Live usage older than this is dropped; the ingester has long caught up by then
+const PENDING_RETENTION_HOURS: i64 = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// First local date of the period containing `today`
+    fn start(&self, today: NaiveDate) -> NaiveDate {
+        match self {
+            BudgetPeriod::Daily => today,
+            BudgetPeriod::Weekly => {
+                today - Duration::days(today.weekday().num_days_from_monday() as i64)
+            }
+            BudgetPeriod::Monthly => today.with_day(1).unwrap_or(today),
+        }
+    }
+}
+
+/// A spending limit, either global (`project_path` unset) or for one project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Project the budget applies to; None means all projects
+    #[serde(default)]
+    pub project_path: Option<String>,
+    pub period: BudgetPeriod,
+    pub limit_usd: f64,
+    /// Fraction of the limit at which a warning is raised
+    #[serde(default = "default_soft_threshold")]
+    pub soft_threshold: f64,
+    /// Refuse new runs once the limit is reached (unless overridden)
+    #[serde(default = "default_true")]
+    pub hard_limit: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_soft_threshold() -> f64 {
+    0.8
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Budget {
+    fn applies_to(&self, project_path: &str) -> bool {
+        match &self.project_path {
+            Some(budget_path) => {
+                normalize_project_path(budget_path) == normalize_project_path(project_path)
+            }
+            None => true,
+        }
+    }
+
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| match &self.project_path {
+                Some(path) => format!("{:?} budget for {}", self.period, path),
+                None => format!("{:?} global budget", self.period),
+            })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetFile {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub budgets: Vec<Budget>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetLevel {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+/// Current spend against a budget, also the payload of `budget-warning`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    pub period_start: String,
+    pub spent_usd: f64,
+    pub remaining_usd: f64,
+    pub ratio: f64,
+    pub level: BudgetLevel,
+}
+
+/// Cost of an assistant message seen on a live stream but possibly not ingested yet
+struct PendingUsage {
+    project_path: String,
+    cost: f64,
+    seen_at: DateTime<Local>,
+}
+
+#[derive(Default)]
+struct BudgetTracker {
+    /// Keyed by Claude message ID; the same message is streamed once per content block
+    pending: HashMap<String, PendingUsage>,
+    /// "{budget_id}:{period_start}:{level}" already announced via `budget-warning`
+    notified: HashSet<String>,
+}
+
+static TRACKER: once_cell::sync::Lazy<Mutex<BudgetTracker>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(BudgetTracker::default()));
+
+fn normalize_project_path(path: &str) -> &str {
+    path.trim_end_matches(['/', '\\'])
+}
+
+fn get_budgets_path() -> Result<PathBuf, String> {
+    let claude_dir = super::claude::get_claude_dir().map_err(|e| e.to_string())?;
+    Ok(claude_dir.join(BUDGETS_FILE))
+}
+
+fn load_budgets() -> Result<BudgetFile, String> {
+    let path = get_budgets_path()?;
+    if !path.exists() {
+        return Ok(BudgetFile::default());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read budgets file: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse budgets file: {}", e))
+}
+
+fn save_budgets(file: &BudgetFile) -> Result<(), String> {
+    let path = get_budgets_path()?;
+    let content = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize budgets file: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write budgets file: {}", e))
+}
+
+/// Ingested spend since `start` (local date), optionally for a single project
+fn ingested_spend(
+    conn: &Connection,
+    start: NaiveDate,
+    project_path: Option<&str>,
+) -> Result<f64, String> {
+    let start = start.format("%Y-%m-%d").to_string();
+    let date_sql = super::usage::LOCAL_DATE_SQL;
+    let result = match project_path {
+        Some(path) => conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(cost), 0.0) FROM usage_entries
+                 WHERE {} >= ?1 AND RTRIM(project_path, '/\\') = ?2",
+                date_sql
+            ),
+            params![start, normalize_project_path(path)],
+            |row| row.get::<_, f64>(0),
+        ),
+        None => conn.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(cost), 0.0) FROM usage_entries WHERE {} >= ?1",
+                date_sql
+            ),
+            params![start],
+            |row| row.get::<_, f64>(0),
+        ),
+    };
+    result.map_err(|e| format!("Failed to query spend: {}", e))
+}
+
+/// Drops live usage that has reached `usage_entries` (or is too old to matter)
+fn prune_pending(conn: &Connection, tracker: &mut BudgetTracker) {
+    let cutoff = Local::now() - Duration::hours(PENDING_RETENTION_HOURS);
+    tracker.pending.retain(|message_id, usage| {
+        if usage.seen_at < cutoff {
+            return false;
+        }
+        // message_hash is "{message_id}:{request_id}"; ';' sorts right after ':'
+        let ingested = conn
+            .query_row(
+                "SELECT 1 FROM usage_entries WHERE message_hash >= ?1 AND message_hash < ?2 LIMIT 1",
+                params![format!("{}:", message_id), format!("{};", message_id)],
+                |_| Ok(()),
+            )
+            .is_ok();
+        !ingested
+    });
+}
+
+/// Evaluates every enabled budget that applies to `project_path` (all budgets when None).
+/// Spend counts ingested rows plus live stream usage that has not been ingested yet.
+fn evaluate_budgets(
+    conn: &Connection,
+    tracker: &mut BudgetTracker,
+    project_path: Option<&str>,
+) -> Result<Vec<BudgetStatus>, String> {
+    let file = load_budgets()?;
+    prune_pending(conn, tracker);
+
+    let today = Local::now().date_naive();
+    let mut statuses = Vec::new();
+    for budget in file.budgets {
+        if !budget.enabled || project_path.is_some_and(|path| !budget.applies_to(path)) {
+            continue;
+        }
+
+        let start = budget.period.start(today);
+        let mut spent = ingested_spend(conn, start, budget.project_path.as_deref())?;
+        spent += tracker
+            .pending
+            .values()
+            .filter(|usage| usage.seen_at.date_naive() >= start)
+            .filter(|usage| budget.applies_to(&usage.project_path))
+            .map(|usage| usage.cost)
+            .sum::<f64>();
+
+        let ratio = if budget.limit_usd > 0.0 {
+            spent / budget.limit_usd
+        } else {
+            0.0
+        };
+        let level = if budget.limit_usd > 0.0 && spent >= budget.limit_usd {
+            BudgetLevel::Exceeded
+        } else if budget.limit_usd > 0.0 && ratio >= budget.soft_threshold {
+            BudgetLevel::Warning
+        } else {
+            BudgetLevel::Ok
+        };
+
+        statuses.push(BudgetStatus {
+            period_start: start.format("%Y-%m-%d").to_string(),
+            spent_usd: spent,
+            remaining_usd: (budget.limit_usd - spent).max(0.0),
+            ratio,
+            level,
+            budget,
+        });
+    }
+    Ok(statuses)
+}
+
+/// Emits `budget-warning` once per budget, period and level
+fn notify_thresholds(app: &AppHandle, tracker: &mut BudgetTracker, statuses: &[BudgetStatus]) {
+    for status in statuses {
+        if status.level == BudgetLevel::Ok {
+            continue;
+        }
+        let key = format!(
+            "{}:{}:{:?}",
+            status.budget.id, status.period_start, status.level
+        );
+        if tracker.notified.insert(key) {
+            log::warn!(
+                "{} at {:.0}% (${:.2} of ${:.2})",
+                status.budget.label(),
+                status.ratio * 100.0,
+                status.spent_usd,
+                status.budget.limit_usd
+            );
+            let _ = app.emit("budget-warning", status);
+        }
+    }
+}
+
+/// Records an assistant message from a running Claude stream and re-evaluates
+/// the budgets of its project. Repeated chunks of the same message are ignored.
+pub fn record_stream_usage(app: &AppHandle, project_path: &str, msg: &serde_json::Value) {
+    if msg["type"] != "assistant" {
+        return;
+    }
+    let message = &msg["message"];
+    let (message_id, usage) = match (message["id"].as_str(), message.get("usage")) {
+        (Some(id), Some(usage)) => (id, usage),
+        _ => return,
+    };
+    let tokens = |key: &str| usage.get(key).and_then(|t| t.as_u64()).unwrap_or(0);
+    let token_usage = TokenUsage {
+        input_tokens: tokens("input_tokens"),
+        output_tokens: tokens("output_tokens"),
+        cache_creation_tokens: tokens("cache_creation_input_tokens"),
+        cache_read_tokens: tokens("cache_read_input_tokens"),
+    };
+
+    let model = message["model"].as_str().unwrap_or("unknown");
+
+    // Lock order is always database first, then tracker
+    let db = app.state::<AgentDb>();
+    let conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(_) => return,
+    };
+    let mut tracker = match TRACKER.lock() {
+        Ok(tracker) => tracker,
+        Err(_) => return,
+    };
+    if tracker.pending.contains_key(message_id) {
+        return;
+    }
+
+    let provider = super::pricing::current_provider();
+    let cost = super::pricing::calculate_cost(model, provider.as_deref(), &token_usage);
+    tracker.pending.insert(
+        message_id.to_string(),
+        PendingUsage {
+            project_path: project_path.to_string(),
+            cost,
+            seen_at: Local::now(),
+        },
+    );
+
+    match evaluate_budgets(&conn, &mut tracker, Some(project_path)) {
+        Ok(statuses) => notify_thresholds(app, &mut tracker, &statuses),
+        Err(e) => log::warn!("Failed to evaluate budgets: {}", e),
+    }
+}
+
+/// Refuses a new run when a hard budget covering `project_path` is exhausted.
+/// Called by execute/continue/resume unless the user chose to override.
+pub fn check_budget_before_run(app: &AppHandle, project_path: &str) -> Result<(), String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut tracker = TRACKER.lock().map_err(|e| e.to_string())?;
+    let statuses = evaluate_budgets(&conn, &mut tracker, Some(project_path))?;
+    notify_thresholds(app, &mut tracker, &statuses);
+
+    match statuses
+        .iter()
+        .find(|s| s.budget.hard_limit && s.level == BudgetLevel::Exceeded)
+    {
+        Some(status) => Err(format!(
+            "Budget exceeded: {} has spent ${:.2} of ${:.2} since {}. Override the budget to run anyway.",
+            status.budget.label(),
+            status.spent_usd,
+            status.budget.limit_usd,
+            status.period_start
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Lists all configured budgets
+#[command]
+pub fn list_budgets() -> Result<Vec<Budget>, String> {
+    Ok(load_budgets()?.budgets)
+}
+
+/// Adds or replaces a budget (matched by id)
+#[command]
+pub fn save_budget(budget: Budget) -> Result<(), String> {
+    if budget.id.trim().is_empty() {
+        return Err("Budget id cannot be empty".to_string());
+    }
+    if !(budget.limit_usd.is_finite() && budget.limit_usd >= 0.0) {
+        return Err("Budget limit must be a non-negative number".to_string());
+    }
+    if !(budget.soft_threshold > 0.0 && budget.soft_threshold <= 1.0) {
+        return Err("Soft threshold must be between 0 and 1".to_string());
+    }
+
+    let mut file = load_budgets()?;
+    match file.budgets.iter_mut().find(|b| b.id == budget.id) {
+        Some(existing) => *existing = budget,
+        None => file.budgets.push(budget),
+    }
+    if file.version == 0 {
+        file.version = 1;
+    }
+    save_budgets(&file)
+}
+
+/// Removes a budget by id
+#[command]
+pub fn delete_budget(id: String) -> Result<(), String> {
+    let mut file = load_budgets()?;
+    let before = file.budgets.len();
+    file.budgets.retain(|b| b.id != id);
+    if file.budgets.len() == before {
+        return Err(format!("No budget with id '{}'", id));
+    }
+    save_budgets(&file)
+}
+
+/// Current spend for the budgets covering `project_path`, or all budgets when omitted
+#[command]
+pub fn get_budget_status(
+    db: State<'_, AgentDb>,
+    project_path: Option<String>,
+) -> Result<Vec<BudgetStatus>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut tracker = TRACKER.lock().map_err(|e| e.to_string())?;
+    evaluate_budgets(&conn, &mut tracker, project_path.as_deref())
+}
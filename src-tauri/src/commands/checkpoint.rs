@@ -0,0 +1,231 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use super::simple_git::{self, GitRepo};
+
+/// app_settings key holding the selected backend
+const BACKEND_SETTING_KEY: &str = "checkpoint_backend";
+
+/// Where prompt checkpoints (commit_before / commit_after) are stored
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointBackend {
+    /// Commit into the project's own git repository (initializing it if needed)
+    #[default]
+    ProjectGit,
+    /// Commit into a bare shadow repository under the app data dir that uses the
+    /// project as its work tree; the project's `.git`, config and history are never touched
+    Shadow,
+}
+
+impl CheckpointBackend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CheckpointBackend::ProjectGit => "project_git",
+            CheckpointBackend::Shadow => "shadow",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "project_git" => Some(CheckpointBackend::ProjectGit),
+            "shadow" => Some(CheckpointBackend::Shadow),
+            _ => None,
+        }
+    }
+}
+
+/// App data directory holding agents.db and the shadow repositories
+pub fn app_data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+fn open_settings_db(app_data_dir: &Path) -> Result<Connection, String> {
+    std::fs::create_dir_all(app_data_dir)
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+
+    let conn = Connection::open(app_data_dir.join("agents.db"))
+        .map_err(|e| format!("Failed to open database: {}", e))?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create settings table: {}", e))?;
+
+    Ok(conn)
+}
+
+/// Currently selected backend (defaults to the project repository)
+pub fn load_backend(app: &AppHandle) -> CheckpointBackend {
+    match app_data_dir(app) {
+        Ok(dir) => load_backend_at(&dir),
+        Err(e) => {
+            log::warn!("Failed to read checkpoint backend setting: {}", e);
+            CheckpointBackend::default()
+        }
+    }
+}
+
+/// Currently selected backend, read from the database in `app_data_dir`
+pub fn load_backend_at(app_data_dir: &Path) -> CheckpointBackend {
+    let stored = open_settings_db(app_data_dir).and_then(|conn| {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![BACKEND_SETTING_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    });
+
+    match stored {
+        Ok(Some(value)) => CheckpointBackend::parse(&value).unwrap_or_default(),
+        Ok(None) => CheckpointBackend::default(),
+        Err(e) => {
+            log::warn!("Failed to read checkpoint backend setting: {}", e);
+            CheckpointBackend::default()
+        }
+    }
+}
+
+/// Shadow repository for a project: <app_data>/checkpoints/<name>-<hash>.git
+fn shadow_git_dir(app_data_dir: &Path, project_path: &str) -> PathBuf {
+    let canonical = std::fs::canonicalize(project_path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| project_path.to_string());
+    let hash = format!("{:x}", Sha256::digest(canonical.as_bytes()));
+    let name = Path::new(project_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "project".to_string());
+
+    app_data_dir
+        .join("checkpoints")
+        .join(format!("{}-{}.git", name, &hash[..16]))
+}
+
+/// Repository holding checkpoints of `project_path` for the given backend
+pub fn open_repo(
+    app: &AppHandle,
+    project_path: &str,
+    backend: CheckpointBackend,
+) -> Result<GitRepo, String> {
+    match backend {
+        CheckpointBackend::ProjectGit => Ok(GitRepo::project(project_path)),
+        CheckpointBackend::Shadow => open_repo_at(&app_data_dir(app)?, project_path, backend),
+    }
+}
+
+/// Same as `open_repo`, with shadow repositories resolved under `app_data_dir`
+pub fn open_repo_at(
+    app_data_dir: &Path,
+    project_path: &str,
+    backend: CheckpointBackend,
+) -> Result<GitRepo, String> {
+    match backend {
+        CheckpointBackend::ProjectGit => Ok(GitRepo::project(project_path)),
+        CheckpointBackend::Shadow => Ok(GitRepo::separate(
+            project_path,
+            shadow_git_dir(app_data_dir, project_path),
+        )),
+    }
+}
+
+/// Whether the backend's repository exists (without creating it)
+pub fn repo_exists(repo: &GitRepo) -> bool {
+    match &repo.git_dir {
+        Some(git_dir) => git_dir.join("HEAD").exists(),
+        None => simple_git::is_git_repo(&repo.work_tree),
+    }
+}
+
+/// Make sure the repository exists and has at least one commit.
+/// Returns true if it had to be created.
+pub fn ensure_repo(repo: &GitRepo) -> Result<bool, String> {
+    match &repo.git_dir {
+        None => {
+            let created = !simple_git::is_git_repo(&repo.work_tree);
+            simple_git::ensure_git_repo(&repo.work_tree)?;
+            Ok(created)
+        }
+        Some(git_dir) => {
+            let created = simple_git::git_init_bare(git_dir)?;
+            if simple_git::git_head(repo).is_none() {
+                simple_git::git_checkpoint_commit(
+                    repo,
+                    "[Claude Workbench] Initial checkpoint - preserving existing code",
+                )?;
+            }
+            Ok(created)
+        }
+    }
+}
+
+/// Current checkpoint commit
+pub fn current_commit(repo: &GitRepo) -> Result<String, String> {
+    match &repo.git_dir {
+        None => simple_git::git_current_commit(&repo.work_tree),
+        Some(_) => {
+            simple_git::git_head(repo).ok_or_else(|| "Shadow repository has no commits".to_string())
+        }
+    }
+}
+
+/// Record the current working tree as a checkpoint.
+/// Returns: Ok(true) if committed, Ok(false) if nothing changed
+pub fn commit_changes(repo: &GitRepo, message: &str) -> Result<bool, String> {
+    match &repo.git_dir {
+        None => simple_git::git_commit_changes(&repo.work_tree, message),
+        Some(_) => simple_git::git_checkpoint_commit(repo, message),
+    }
+}
+
+/// Checkpoint the working tree as it is right now, uncommitted edits included.
+/// The shadow repository commits it onto HEAD; the project repository only gets a
+/// snapshot commit (HEAD and the index are left alone) pinned under `pin_ref` so gc keeps it.
+pub fn snapshot_commit(repo: &GitRepo, message: &str, pin_ref: &str) -> Result<String, String> {
+    match &repo.git_dir {
+        None => {
+            let commit = simple_git::git_snapshot_if_changed(repo, message)?;
+            if simple_git::git_head(repo).as_deref() != Some(commit.as_str()) {
+                simple_git::git_update_ref(repo, pin_ref, &commit)?;
+            }
+            Ok(commit)
+        }
+        Some(_) => {
+            simple_git::git_checkpoint_commit(repo, message)?;
+            current_commit(repo)
+        }
+    }
+}
+
+/// Get the selected checkpoint backend
+#[tauri::command]
+pub async fn get_checkpoint_backend(app: AppHandle) -> Result<CheckpointBackend, String> {
+    Ok(load_backend(&app))
+}
+
+/// Select the checkpoint backend used for new prompts
+#[tauri::command]
+pub async fn set_checkpoint_backend(
+    app: AppHandle,
+    backend: CheckpointBackend,
+) -> Result<(), String> {
+    let conn = open_settings_db(&app_data_dir(&app)?)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+        params![BACKEND_SETTING_KEY, backend.as_str()],
+    )
+    .map_err(|e| format!("Failed to store checkpoint backend: {}", e))?;
+
+    log::info!("Checkpoint backend set to {}", backend.as_str());
+    Ok(())
+}
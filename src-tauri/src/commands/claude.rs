@@ -12,8 +12,7 @@ use std::process::Stdio;
 use std::sync::Arc;
 use std::time::SystemTime;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
+use tokio::process::Command;
 use tauri_plugin_shell::ShellExt;
 use regex;
 
@@ -21,64 +20,6 @@ use regex;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
-/// Global state to track current Claude process
-pub struct ClaudeProcessState {
-    pub current_process: Arc<Mutex<Option<Child>>>,
-}
-
-impl Default for ClaudeProcessState {
-    fn default() -> Self {
-        Self {
-            current_process: Arc::new(Mutex::new(None)),
-        }
-    }
-}
-
-impl Drop for ClaudeProcessState {
-    fn drop(&mut self) {
-        // When the application exits, clean up the current process
-        log::info!("ClaudeProcessState dropping, cleaning up current process...");
-        
-        // Use a runtime to execute the async cleanup
-        let process = self.current_process.clone();
-        if let Ok(handle) = tokio::runtime::Handle::try_current() {
-            // We're in a tokio runtime context
-            handle.block_on(async move {
-                let mut current_process = process.lock().await;
-                if let Some(mut child) = current_process.take() {
-                    log::info!("Cleanup on drop: Killing Claude process");
-                    match child.kill().await {
-                        Ok(_) => {
-                            log::info!("Cleanup on drop: Successfully killed Claude process");
-                        }
-                        Err(e) => {
-                            log::error!("Cleanup on drop: Failed to kill Claude process: {}", e);
-                        }
-                    }
-                }
-            });
-        } else {
-            // Create a temporary runtime for cleanup
-            if let Ok(rt) = tokio::runtime::Runtime::new() {
-                rt.block_on(async move {
-                    let mut current_process = process.lock().await;
-                    if let Some(mut child) = current_process.take() {
-                        log::info!("Cleanup on drop: Killing Claude process");
-                        match child.kill().await {
-                            Ok(_) => {
-                                log::info!("Cleanup on drop: Successfully killed Claude process");
-                            }
-                            Err(e) => {
-                                log::error!("Cleanup on drop: Failed to kill Claude process: {}", e);
-                            }
-                        }
-                    }
-                });
-            }
-        }
-    }
-}
-
 /// Maps frontend model IDs to Claude CLI model aliases
 /// Converts frontend-friendly model names to official Claude Code model identifiers
 /// Updated to use Claude 4.1 Opus (released August 2025) as the latest Opus model
@@ -267,7 +208,7 @@ fn get_project_path_from_sessions(project_dir: &PathBuf) -> Result<String, Strin
 
 /// Encodes a project path to match Claude CLI's encoding scheme
 /// Uses single hyphens to separate path components
-fn encode_project_path(path: &str) -> String {
+pub(crate) fn encode_project_path(path: &str) -> String {
     path.replace("\\", "-")
         .replace("/", "-")
         .replace(":", "")
@@ -1860,6 +1801,8 @@ pub async fn execute_claude_code(
     model: String,
     plan_mode: Option<bool>,
     max_thinking_tokens: Option<u32>,
+    tab_id: Option<String>,
+    budget_override: Option<bool>,
 ) -> Result<(), String> {
     let plan_mode = plan_mode.unwrap_or(false);
     log::info!(
@@ -1869,6 +1812,11 @@ pub async fn execute_claude_code(
         plan_mode
     );
 
+    // 预算硬上限检查（用户可选择覆盖）
+    if !budget_override.unwrap_or(false) {
+        crate::commands::budget::check_budget_before_run(&app, &project_path)?;
+    }
+
     let claude_path = find_claude_binary(&app)?;
     
     // 获取当前执行配置
@@ -1902,7 +1850,7 @@ pub async fn execute_claude_code(
 
     // Create command
     let cmd = create_system_command(&claude_path, args, &project_path, Some(&mapped_model), max_thinking_tokens)?;
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    spawn_claude_process(app, cmd, prompt, model, project_path, tab_id, None).await
 }
 
 /// Continue an existing Claude Code conversation with streaming output
@@ -1915,6 +1863,8 @@ pub async fn continue_claude_code(
     model: String,
     plan_mode: Option<bool>,
     max_thinking_tokens: Option<u32>,
+    tab_id: Option<String>,
+    budget_override: Option<bool>,
 ) -> Result<(), String> {
     let plan_mode = plan_mode.unwrap_or(false);
     log::info!(
@@ -1924,6 +1874,11 @@ pub async fn continue_claude_code(
         plan_mode
     );
 
+    // 预算硬上限检查（用户可选择覆盖）
+    if !budget_override.unwrap_or(false) {
+        crate::commands::budget::check_budget_before_run(&app, &project_path)?;
+    }
+
     let claude_path = find_claude_binary(&app)?;
     
     // 获取当前执行配置
@@ -1960,7 +1915,7 @@ pub async fn continue_claude_code(
 
     // Create command
     let cmd = create_system_command(&claude_path, args, &project_path, Some(&mapped_model), max_thinking_tokens)?;
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    spawn_claude_process(app, cmd, prompt, model, project_path, tab_id, None).await
 }
 
 /// Resume an existing Claude Code session by ID with streaming output
@@ -1974,6 +1929,8 @@ pub async fn resume_claude_code(
     model: String,
     plan_mode: Option<bool>,
     max_thinking_tokens: Option<u32>,
+    tab_id: Option<String>,
+    budget_override: Option<bool>,
 ) -> Result<(), String> {
     let plan_mode = plan_mode.unwrap_or(false);
     log::info!(
@@ -1993,6 +1950,11 @@ pub async fn resume_claude_code(
     log::info!("Expected session file directory: {}", session_dir);
     log::info!("Session ID to resume: {}", session_id);
 
+    // 预算硬上限检查（用户可选择覆盖）
+    if !budget_override.unwrap_or(false) {
+        crate::commands::budget::check_budget_before_run(&app, &project_path)?;
+    }
+
     let claude_path = find_claude_binary(&app)?;
     
     // 获取当前执行配置
@@ -2034,150 +1996,93 @@ pub async fn resume_claude_code(
     let cmd = create_system_command(&claude_path, args, &project_path, Some(&mapped_model), max_thinking_tokens)?;
     
     // Try to spawn the process - if it fails, fall back to continue mode
-    match spawn_claude_process(app.clone(), cmd, prompt.clone(), model.clone(), project_path.clone()).await {
+    match spawn_claude_process(app.clone(), cmd, prompt.clone(), model.clone(), project_path.clone(), tab_id.clone(), Some(session_id.clone())).await {
         Ok(_) => Ok(()),
         Err(resume_error) => {
             log::warn!("Resume failed: {}, trying continue mode as fallback", resume_error);
             // Fallback to continue mode
-            continue_claude_code(app, project_path, prompt, model, Some(plan_mode), max_thinking_tokens).await
+            // Budgets were already checked for this run
+            continue_claude_code(app, project_path, prompt, model, Some(plan_mode), max_thinking_tokens, tab_id, Some(true)).await
         }
     }
 }
 
-/// Cancel the currently running Claude Code execution
+/// Cancel a running Claude Code execution
+/// Targets the process owned by `session_id`, or by `tab_id` if Claude has not
+/// reported its session ID yet. Other sessions keep running.
 #[tauri::command]
 pub async fn cancel_claude_execution(
     app: AppHandle,
     session_id: Option<String>,
+    tab_id: Option<String>,
 ) -> Result<(), String> {
     log::info!(
-        "Cancelling Claude Code execution for session: {:?}",
-        session_id
+        "Cancelling Claude Code execution for session: {:?}, tab: {:?}",
+        session_id,
+        tab_id
     );
 
-    let mut killed = false;
-    let mut attempted_methods = Vec::new();
+    let registry = app.state::<crate::process::ProcessRegistryState>();
 
-    // Method 1: Try to find and kill via ProcessRegistry using session ID
+    // Resolve the target process: session ID first, then the owning tab
+    let mut target = None;
     if let Some(sid) = &session_id {
-        let registry = app.state::<crate::process::ProcessRegistryState>();
         match registry.0.get_claude_session_by_id(sid) {
-            Ok(Some(process_info)) => {
-                log::info!("Found process in registry for session {}: run_id={}, PID={}", 
-                    sid, process_info.run_id, process_info.pid);
-                match registry.0.kill_process(process_info.run_id).await {
-                    Ok(success) => {
-                        if success {
-                            log::info!("Successfully killed process via registry");
-                            killed = true;
-                        } else {
-                            log::warn!("Registry kill returned false");
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to kill via registry: {}", e);
-                    }
-                }
-                attempted_methods.push("registry");
-            }
-            Ok(None) => {
-                log::warn!("Session {} not found in ProcessRegistry", sid);
-            }
-            Err(e) => {
-                log::error!("Error querying ProcessRegistry: {}", e);
+            Ok(found) => target = found,
+            Err(e) => log::error!("Error querying ProcessRegistry: {}", e),
+        }
+    }
+    if target.is_none() {
+        if let Some(tid) = &tab_id {
+            match registry.0.get_claude_process_by_tab(tid) {
+                Ok(found) => target = found,
+                Err(e) => log::error!("Error querying ProcessRegistry: {}", e),
             }
         }
     }
 
-    // Method 2: Try the legacy approach via ClaudeProcessState
-    if !killed {
-        let claude_state = app.state::<ClaudeProcessState>();
-        let mut current_process = claude_state.current_process.lock().await;
-
-        if let Some(mut child) = current_process.take() {
-            // Try to get the PID before killing
-            let pid = child.id();
-            log::info!("Attempting to kill Claude process via ClaudeProcessState with PID: {:?}", pid);
-
-            // Kill the process
-            match child.kill().await {
-                Ok(_) => {
-                    log::info!("Successfully killed Claude process via ClaudeProcessState");
+    let mut killed = false;
+    match target {
+        Some(process_info) => {
+            log::info!(
+                "Found process in registry: run_id={}, PID={}",
+                process_info.run_id,
+                process_info.pid
+            );
+            match registry.0.kill_process(process_info.run_id).await {
+                Ok(true) => {
+                    log::info!("Successfully killed process via registry");
                     killed = true;
                 }
-                Err(e) => {
-                    log::error!("Failed to kill Claude process via ClaudeProcessState: {}", e);
-                    
-                    // Method 3: If we have a PID, try system kill as last resort
-                    if let Some(pid) = pid {
-                        log::info!("Attempting system kill as last resort for PID: {}", pid);
-                        let kill_result = if cfg!(target_os = "windows") {
-                            #[cfg(target_os = "windows")]
-                            {
-                                use std::os::windows::process::CommandExt;
-                                std::process::Command::new("taskkill")
-                                    .args(["/F", "/T", "/PID", &pid.to_string()]) // Added /T to kill process tree
-                                    .creation_flags(0x08000000) // CREATE_NO_WINDOW
-                                    .output()
-                            }
-                            #[cfg(not(target_os = "windows"))]
-                            {
-                                // This branch will never be reached due to the outer if condition
-                                // but is needed for compilation on non-Windows platforms
-                                std::process::Command::new("kill")
-                                    .args(["-KILL", &pid.to_string()])
-                                    .output()
-                            }
-                        } else {
-                            std::process::Command::new("kill")
-                                .args(["-KILL", &pid.to_string()])
-                                .output()
-                        };
-                        
-                        match kill_result {
-                            Ok(output) if output.status.success() => {
-                                log::info!("Successfully killed process via system command");
-                                killed = true;
-                            }
-                            Ok(output) => {
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                log::error!("System kill failed: {}", stderr);
-                            }
-                            Err(e) => {
-                                log::error!("Failed to execute system kill command: {}", e);
-                            }
-                        }
-                    }
-                }
+                Ok(false) => log::warn!("Registry kill returned false"),
+                Err(e) => log::warn!("Failed to kill via registry: {}", e),
             }
-            attempted_methods.push("claude_state");
-        } else {
-            log::warn!("No active Claude process in ClaudeProcessState");
         }
-    }
-
-    if !killed && attempted_methods.is_empty() {
-        log::warn!("No active Claude process found to cancel");
+        None => {
+            log::warn!("No active Claude process found to cancel");
+        }
     }
 
     // Always emit cancellation events for UI consistency
-    if let Some(sid) = session_id {
+    if let Some(sid) = &session_id {
         let _ = app.emit(&format!("claude-cancelled:{}", sid), true);
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         let _ = app.emit(&format!("claude-complete:{}", sid), false);
+    } else if let Some(tid) = &tab_id {
+        let _ = app.emit(&format!("claude-cancelled:tab:{}", tid), true);
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let _ = app.emit(&format!("claude-complete:tab:{}", tid), false);
+    } else {
+        // Legacy callers without a target only listen to the generic events
+        let _ = app.emit("claude-cancelled", true);
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let _ = app.emit("claude-complete", false);
     }
-    
-    // Also emit generic events for backward compatibility
-    let _ = app.emit("claude-cancelled", true);
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    let _ = app.emit("claude-complete", false);
-    
+
     if killed {
         log::info!("Claude process cancellation completed successfully");
-    } else if !attempted_methods.is_empty() {
-        log::warn!("Claude process cancellation attempted but process may have already exited. Attempted methods: {:?}", attempted_methods);
     }
-    
+
     Ok(())
 }
 
@@ -2203,8 +2108,43 @@ pub async fn get_claude_session_output(
     }
 }
 
+/// Emits a Claude stream event scoped to its session and owning tab.
+/// The session channel (`event:{session_id}`) is used for reconnects; the tab channel
+/// (`event:tab:{tab_id}`) carries the full stream including messages before init.
+/// Callers without a tab fall back to the generic event for backward compatibility.
+fn emit_scoped_event<S: Serialize + Clone>(
+    app: &AppHandle,
+    event: &str,
+    session_id: Option<&str>,
+    tab_id: Option<&str>,
+    payload: S,
+) {
+    if let Some(sid) = session_id {
+        let _ = app.emit(&format!("{}:{}", event, sid), payload.clone());
+    }
+    match tab_id {
+        Some(tid) => {
+            let _ = app.emit(&format!("{}:tab:{}", event, tid), payload);
+        }
+        None => {
+            let _ = app.emit(event, payload);
+        }
+    }
+}
+
 /// Helper function to spawn Claude process and handle streaming
-async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String, model: String, project_path: String) -> Result<(), String> {
+/// Each process is owned by its own ProcessRegistry entry, so several sessions
+/// (e.g. one per project tab) can run concurrently.
+/// `resumed_session_id` is the session passed to `--resume`; its JSONL seeds the context meter
+async fn spawn_claude_process(
+    app: AppHandle,
+    mut cmd: Command,
+    prompt: String,
+    model: String,
+    project_path: String,
+    tab_id: Option<String>,
+    resumed_session_id: Option<String>,
+) -> Result<(), String> {
     use tokio::io::{AsyncBufReadExt, BufReader};
     use std::sync::Mutex;
 
@@ -2220,29 +2160,30 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
     // Get the child PID for logging
     let pid = child.id().unwrap_or(0);
     log::info!(
-        "Spawned Claude process with PID: {:?}",
-        pid
+        "Spawned Claude process with PID: {:?} (tab: {:?})",
+        pid,
+        tab_id
     );
 
     // Create readers first (before moving child)
     let stdout_reader = BufReader::new(stdout);
     let stderr_reader = BufReader::new(stderr);
 
+    // Hand the child over to the ProcessRegistry right away so it can be cancelled
+    // before Claude reports its session ID
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let run_id = registry.0.register_claude_process(
+        tab_id.clone(),
+        pid,
+        project_path.clone(),
+        prompt.clone(),
+        model.clone(),
+        child,
+    )?;
+    log::info!("Registered Claude process with run_id: {}", run_id);
+
     // We'll extract the session ID from Claude's init message
     let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
-    let run_id_holder: Arc<Mutex<Option<i64>>> = Arc::new(Mutex::new(None));
-
-    // Store the child process in the global state (for backward compatibility)
-    let claude_state = app.state::<ClaudeProcessState>();
-    {
-        let mut current_process = claude_state.current_process.lock().await;
-        // If there's already a process running, kill it first
-        if let Some(mut existing_child) = current_process.take() {
-            log::warn!("Killing existing Claude process before starting new one");
-            let _ = existing_child.kill().await;
-        }
-        *current_process = Some(child);
-    }
 
     // Check if auto-compact state is available
     let auto_compact_available = app.try_state::<crate::commands::context_manager::AutoCompactState>().is_some();
@@ -2250,14 +2191,14 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
     let session_id_holder_clone = session_id_holder.clone();
-    let run_id_holder_clone = run_id_holder.clone();
-    let registry = app.state::<crate::process::ProcessRegistryState>();
     let registry_clone = registry.0.clone();
     let project_path_clone = project_path.clone();
-    let prompt_clone = prompt.clone();
     let model_clone = model.clone();
+    let tab_id_clone = tab_id.clone();
     let stdout_task = tokio::spawn(async move {
         let mut lines = stdout_reader.lines();
+        // Stream-json repeats the same usage on every content block of a message
+        let mut last_usage_message_id: Option<String> = None;
         while let Ok(Some(line)) = lines.next_line().await {
             log::debug!("Claude stdout: {}", line);
             
@@ -2274,28 +2215,44 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                             if auto_compact_available {
                                 if let Some(auto_compact_state) = app_handle.try_state::<crate::commands::context_manager::AutoCompactState>() {
                                     if let Err(e) = auto_compact_state.0.register_session(
-                                    claude_session_id.to_string(),
-                                    project_path_clone.clone(),
-                                    model_clone.clone(),
-                                ) {
-                                    log::warn!("Failed to register session with auto-compact manager: {}", e);
-                                }
+                                        claude_session_id.to_string(),
+                                        project_path_clone.clone(),
+                                        model_clone.clone(),
+                                    ) {
+                                        log::warn!("Failed to register session with auto-compact manager: {}", e);
+                                    }
+
+                                    // A resumed session starts with its whole history in context
+                                    if let Some(resumed_id) = &resumed_session_id {
+                                        let seed = crate::commands::context_meter::session_context_from_jsonl(&project_path_clone, claude_session_id)
+                                            .or_else(|| crate::commands::context_meter::session_context_from_jsonl(&project_path_clone, resumed_id));
+                                        if let Some(usage) = seed {
+                                            if let Err(e) = auto_compact_state.0.seed_session_usage(claude_session_id, usage) {
+                                                log::warn!("Failed to seed context usage for session {}: {}", claude_session_id, e);
+                                            }
+                                        }
+                                    }
                                 }
                             }
 
-                            // Now register with ProcessRegistry using Claude's session ID
-                            match registry_clone.register_claude_session(
-                                claude_session_id.to_string(),
-                                pid,
-                                project_path_clone.clone(),
-                                prompt_clone.clone(),
-                                model_clone.clone(),
-                            ) {
-                                Ok(run_id) => {
-                                    log::info!("Registered Claude session with run_id: {}", run_id);
-                                    let mut run_id_guard = run_id_holder_clone.lock().unwrap();
-                                    *run_id_guard = Some(run_id);
+                            // Fire OnSessionStart hooks and watch the project for OnFileChange
+                            if let Some(hook_manager) = app_handle.try_state::<crate::commands::enhanced_hooks::HookManager>() {
+                                hook_manager.watch_project(&project_path_clone, claude_session_id);
+                                hook_manager.fire(
+                                    crate::commands::enhanced_hooks::HookEvent::OnSessionStart,
+                                    claude_session_id,
+                                    &project_path_clone,
+                                    serde_json::json!({
+                                        "model": model_clone,
+                                        "tab_id": tab_id_clone,
+                                        "run_id": run_id,
+                                    }),
+                                );
+                            }
 
+                            // Attach Claude's session ID to our registry entry
+                            match registry_clone.set_claude_session_id(run_id, claude_session_id.to_string()) {
+                                Ok(_) => {
                                     // ✨ Phase 2: Emit event for real-time session tracking
                                     let event_payload = serde_json::json!({
                                         "session_id": claude_session_id,
@@ -2304,6 +2261,7 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                                         "status": "started",
                                         "pid": pid,
                                         "run_id": run_id,
+                                        "tab_id": tab_id_clone,
                                     });
                                     if let Err(e) = app_handle.emit("claude-session-state", &event_payload) {
                                         log::warn!("Failed to emit claude-session-state event: {}", e);
@@ -2321,147 +2279,133 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String,
                     }
                 }
 
-                // Check for usage information and update context tracking
-                if let Some(usage) = msg.get("usage") {
-                    if let (Some(input_tokens), Some(output_tokens)) =
-                        (usage.get("input_tokens").and_then(|t| t.as_u64()),
-                         usage.get("output_tokens").and_then(|t| t.as_u64())) {
-
-                        let total_tokens = (input_tokens + output_tokens) as usize;
-
-                        // Extract cache tokens if available
-                        let _cache_creation_tokens = usage.get("cache_creation_input_tokens").and_then(|t| t.as_u64());
-                        let _cache_read_tokens = usage.get("cache_read_input_tokens").and_then(|t| t.as_u64());
-
-                        // Store usage data in database for real-time token statistics
-                        let session_id_for_update = {
-                            session_id_holder_clone.lock().unwrap().as_ref().cloned()
-                        };
+                // Track spend against budgets from the live token stream
+                if msg["type"] == "assistant" {
+                    let app_for_budget = app_handle.clone();
+                    let project_path_for_budget = project_path_clone.clone();
+                    let msg_for_budget = msg.clone();
+                    tokio::task::spawn_blocking(move || {
+                        crate::commands::budget::record_stream_usage(&app_for_budget, &project_path_for_budget, &msg_for_budget);
+                    });
+                }
 
-                        if let Some(session_id_str) = &session_id_for_update {
-                            // Agent database functionality removed - usage tracking disabled
-                            
-                            // Update auto-compact manager with token count
-                            if auto_compact_available {
-                                if let Some(auto_compact_state) = app_handle.try_state::<crate::commands::context_manager::AutoCompactState>() {
-                                    let auto_compact_state_clone = auto_compact_state.inner().clone();
-                                    let session_id_for_compact = session_id_str.clone();
-
-                                    // Spawn async task to avoid blocking main output loop
-                                    tokio::spawn(async move {
-                                        match auto_compact_state_clone.0.update_session_tokens(&session_id_for_compact, total_tokens).await {
-                                            Ok(compaction_triggered) => {
-                                                if compaction_triggered {
-                                                    log::info!("Auto-compaction triggered for session {}", session_id_for_compact);
-                                                    // The actual compaction will be handled by the background monitoring thread
-                                                }
-                                            }
-                                            Err(e) => {
-                                                log::warn!("Failed to update session tokens for auto-compact: {}", e);
-                                            }
+                // Track the context size from the latest main-conversation turn
+                // (the result message's usage is a sum over all turns, not the context)
+                if let Some(usage) = crate::commands::context_meter::assistant_turn_usage(&msg) {
+                    let message_id = crate::commands::context_meter::assistant_message_id(&msg).map(|id| id.to_string());
+                    let is_new_turn = message_id.is_none() || message_id != last_usage_message_id;
+                    last_usage_message_id = message_id;
+
+                    let session_id_for_update = {
+                        session_id_holder_clone.lock().unwrap().as_ref().cloned()
+                    };
+
+                    if let Some(session_id_str) = &session_id_for_update {
+                        // Update auto-compact manager with the context size. A repeated message id
+                        // replaces the usage of that turn: earlier lines carry partial output_tokens
+                        if auto_compact_available {
+                            if let Some(auto_compact_state) = app_handle.try_state::<crate::commands::context_manager::AutoCompactState>() {
+                                match auto_compact_state.0.update_session_usage(&app_handle, session_id_str, usage, is_new_turn).await {
+                                    Ok(compaction_triggered) => {
+                                        if compaction_triggered {
+                                            log::info!("Auto-compaction triggered for session {}", session_id_str);
+                                            // The actual compaction will be handled by the background monitoring thread
                                         }
-                                    });
+                                    }
+                                    Err(e) => {
+                                        log::warn!("Failed to update session tokens for auto-compact: {}", e);
+                                    }
                                 }
                             }
                         }
                     }
                 }
+
+                // The CLI compacted the conversation on its own; usage is unknown until the next turn
+                if msg["type"] == "system" && msg["subtype"] == "compact_boundary" {
+                    let session_id_for_update = {
+                        session_id_holder_clone.lock().unwrap().as_ref().cloned()
+                    };
+                    if let Some(session_id_str) = &session_id_for_update {
+                        if let Some(auto_compact_state) = app_handle.try_state::<crate::commands::context_manager::AutoCompactState>() {
+                            if let Err(e) = auto_compact_state.0.seed_session_usage(session_id_str, Default::default()) {
+                                log::warn!("Failed to reset context usage for session {}: {}", session_id_str, e);
+                            }
+                        }
+                    }
+                }
             }
             
-            // Store live output in registry if we have a run_id
-            if let Some(run_id) = *run_id_holder_clone.lock().unwrap() {
-                let _ = registry_clone.append_live_output(run_id, &line);
-            }
+            // Store live output in registry
+            let _ = registry_clone.append_live_output(run_id, &line);
             
-            // Emit the line to the frontend with session isolation if we have session ID
-            if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
-                let _ = app_handle.emit(&format!("claude-output:{}", session_id), &line);
-            }
-            // Also emit to the generic event for backward compatibility and early messages
-            let _ = app_handle.emit("claude-output", &line);
+            // Emit the line to the frontend, isolated per session and tab
+            let session_id = session_id_holder_clone.lock().unwrap().clone();
+            emit_scoped_event(&app_handle, "claude-output", session_id.as_deref(), tab_id_clone.as_deref(), &line);
         }
     });
 
     let app_handle_stderr = app.clone();
     let session_id_holder_clone2 = session_id_holder.clone();
+    let tab_id_clone2 = tab_id.clone();
     let stderr_task = tokio::spawn(async move {
         let mut lines = stderr_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
             log::error!("Claude stderr: {}", line);
-            // Emit error lines to the frontend with session isolation if we have session ID
-            if let Some(ref session_id) = *session_id_holder_clone2.lock().unwrap() {
-                let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id), &line);
-            }
-            // Also emit to the generic event for backward compatibility
-            let _ = app_handle_stderr.emit("claude-error", &line);
+            // Emit error lines to the frontend, isolated per session and tab
+            let session_id = session_id_holder_clone2.lock().unwrap().clone();
+            emit_scoped_event(&app_handle_stderr, "claude-error", session_id.as_deref(), tab_id_clone2.as_deref(), &line);
         }
     });
 
     // Wait for the process to complete
     let app_handle_wait = app.clone();
-    let claude_state_wait = claude_state.current_process.clone();
     let session_id_holder_clone3 = session_id_holder.clone();
-    let run_id_holder_clone2 = run_id_holder.clone();
     let registry_clone2 = registry.0.clone();
+    let project_path_wait = project_path.clone();
     tokio::spawn(async move {
         let _ = stdout_task.await;
         let _ = stderr_task.await;
 
-        // Get the child from the state to wait on it
-        let mut current_process = claude_state_wait.lock().await;
-        if let Some(mut child) = current_process.take() {
-            match child.wait().await {
-                Ok(status) => {
-                    log::info!("Claude process exited with status: {}", status);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        // ✨ Phase 2: Emit state change event
-                        let event_payload = serde_json::json!({
-                            "session_id": session_id,
-                            "status": "stopped",
-                            "success": status.success(),
-                        });
-                        let _ = app_handle_wait.emit("claude-session-state", &event_payload);
-                        
-                        let _ = app_handle_wait.emit(
-                            &format!("claude-complete:{}", session_id),
-                            status.success(),
-                        );
-                    }
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", status.success());
-                }
-                Err(e) => {
-                    log::error!("Failed to wait for Claude process: {}", e);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        // ✨ Phase 2: Emit state change event for error case
-                        let event_payload = serde_json::json!({
-                            "session_id": session_id,
-                            "status": "stopped",
-                            "success": false,
-                            "error": e.to_string(),
-                        });
-                        let _ = app_handle_wait.emit("claude-session-state", &event_payload);
-                        
-                        let _ = app_handle_wait
-                            .emit(&format!("claude-complete:{}", session_id), false);
-                    }
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", false);
-                }
+        // The registry owns the child; `None` means it was killed via cancel_claude_execution
+        let success = match registry_clone2.wait_for_exit(run_id).await {
+            Some(status) => {
+                log::info!("Claude process {} exited with status: {}", run_id, status);
+                status.success()
             }
-        }
+            None => {
+                log::info!("Claude process {} was terminated before completion", run_id);
+                false
+            }
+        };
 
-        // Unregister from ProcessRegistry if we have a run_id
-        if let Some(run_id) = *run_id_holder_clone2.lock().unwrap() {
-            let _ = registry_clone2.unregister_process(run_id);
+        // Add a small delay to ensure all messages are processed
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        let session_id = session_id_holder_clone3.lock().unwrap().clone();
+        if let Some(ref session_id) = session_id {
+            // ✨ Phase 2: Emit state change event
+            let event_payload = serde_json::json!({
+                "session_id": session_id,
+                "status": "stopped",
+                "success": success,
+                "tab_id": tab_id,
+            });
+            let _ = app_handle_wait.emit("claude-session-state", &event_payload);
+
+            if let Some(hook_manager) = app_handle_wait.try_state::<crate::commands::enhanced_hooks::HookManager>() {
+                hook_manager.unwatch_project(&project_path_wait);
+                hook_manager.fire(
+                    crate::commands::enhanced_hooks::HookEvent::OnSessionEnd,
+                    session_id,
+                    &project_path_wait,
+                    serde_json::json!({ "success": success, "tab_id": tab_id }),
+                );
+            }
         }
+        emit_scoped_event(&app_handle_wait, "claude-complete", session_id.as_deref(), tab_id.as_deref(), success);
 
-        // Clear the process from state
-        *current_process = None;
+        // Unregister from ProcessRegistry
+        let _ = registry_clone2.unregister_process(run_id);
     });
 
     Ok(())
@@ -2545,8 +2489,13 @@ pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileE
 }
 
 /// Search for files and directories matching a pattern
+/// Respects .gitignore/.ignore and ranks results with fzf-style fuzzy scoring on relative paths
 #[tauri::command]
-pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEntry>, String> {
+pub async fn search_files(
+    base_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<FileEntry>, String> {
     log::info!("Searching files in '{}' for: '{}'", base_path, query);
 
     // Check if path is empty
@@ -2561,104 +2510,43 @@ pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEn
         return Ok(Vec::new());
     }
 
-    let path = PathBuf::from(&base_path);
-    log::debug!("Resolved search base path: {:?}", path);
-
-    if !path.exists() {
-        log::error!("Base path does not exist: {:?}", path);
-        return Err(format!("Path does not exist: {}", base_path));
-    }
-
-    let query_lower = query.to_lowercase();
-    let mut results = Vec::new();
-
-    search_files_recursive(&path, &path, &query_lower, &mut results, 0)?;
-
-    // Sort by relevance: exact matches first, then by name
-    results.sort_by(|a, b| {
-        let a_exact = a.name.to_lowercase() == query_lower;
-        let b_exact = b.name.to_lowercase() == query_lower;
-
-        match (a_exact, b_exact) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-        }
-    });
-
-    // Limit results to prevent overwhelming the UI
-    results.truncate(50);
-
-    Ok(results)
-}
-
-fn search_files_recursive(
-    current_path: &PathBuf,
-    base_path: &PathBuf,
-    query: &str,
-    results: &mut Vec<FileEntry>,
-    depth: usize,
-) -> Result<(), String> {
-    // Limit recursion depth to prevent excessive searching
-    if depth > 5 || results.len() >= 50 {
-        return Ok(());
-    }
-
-    let entries = fs::read_dir(current_path)
-        .map_err(|e| format!("Failed to read directory {:?}: {}", current_path, e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let entry_path = entry.path();
-
-        // Skip hidden files/directories
-        if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') {
-                continue;
-            }
-
-            // Check if name matches query
-            if name.to_lowercase().contains(query) {
-                let metadata = entry
-                    .metadata()
-                    .map_err(|e| format!("Failed to read metadata: {}", e))?;
-
-                let extension = if metadata.is_file() {
-                    entry_path
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .map(|e| e.to_string())
-                } else {
-                    None
-                };
-
-                results.push(FileEntry {
-                    name: name.to_string(),
-                    path: entry_path.to_string_lossy().to_string(),
-                    is_directory: metadata.is_dir(),
-                    size: metadata.len(),
-                    extension,
-                });
-            }
-        }
-
-        // Recurse into directories
-        if entry_path.is_dir() {
-            // Skip common directories that shouldn't be searched
-            if let Some(dir_name) = entry_path.file_name().and_then(|n| n.to_str()) {
-                if matches!(
-                    dir_name,
-                    "node_modules" | "target" | ".git" | "dist" | "build" | ".next" | "__pycache__"
-                ) {
-                    continue;
-                }
+    // Hidden files are included (.github/, .env.example); .gitignore/.ignore still apply
+    let options = super::file_search::FileSearchOptions {
+        max_results: limit,
+        include_hidden: true,
+        include_directories: true,
+        ..Default::default()
+    };
+    let result = tokio::task::spawn_blocking(move || {
+        super::file_search::find_files(&base_path, &query, options)
+    })
+    .await
+    .map_err(|e| format!("File search task failed: {}", e))??;
+    if result.truncated {
+        log::warn!("File search results were truncated");
+    }
+
+    Ok(result
+        .files
+        .into_iter()
+        .map(|m| {
+            let extension = if m.is_directory {
+                None
+            } else {
+                std::path::Path::new(&m.name)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_string())
+            };
+            FileEntry {
+                name: m.name,
+                path: m.path,
+                is_directory: m.is_directory,
+                size: m.size,
+                extension,
             }
-
-            search_files_recursive(&entry_path, base_path, query, results, depth + 1)?;
-        }
-    }
-
-    Ok(())
+        })
+        .collect())
 }
 
 /// Gets hooks configuration from settings at specified scope
@@ -0,0 +1,328 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use tauri::{AppHandle, State};
+
+use super::checkpoint;
+use super::claude::get_claude_dir;
+use super::git_stats::{collect_numstat, FileChangeStat};
+use super::prompt_tracker::{load_git_records, GitRecord};
+use super::storage::AgentDb;
+
+/// (commit_before, commit_after) → per-file numstat
+type NumstatCache = HashMap<(String, String), Vec<FileChangeStat>>;
+
+/// commit_before..commit_after numstat results; commits are immutable so this never goes stale
+static NUMSTAT_CACHE: once_cell::sync::Lazy<std::sync::Mutex<NumstatCache>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Code changed by a single prompt (commit_before → commit_after)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptCodeChange {
+    pub prompt_index: usize,
+    pub timestamp: i64,
+    pub commit_before: String,
+    pub commit_after: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub files: Vec<FileChangeStat>,
+}
+
+/// Code changes attributed to one session's prompts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionCodeAttribution {
+    pub session_id: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub files_touched: usize,
+    /// Prompts that have not completed (no commit_after) are not counted
+    pub pending_prompts: usize,
+    pub prompts: Vec<PromptCodeChange>,
+    /// Usage cost of the session from `usage_entries`
+    pub cost: f64,
+    /// cost / (lines_added + lines_removed); None when nothing changed
+    pub cost_per_line: Option<f64>,
+}
+
+/// Project-wide "AI-authored changes" report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectCodeAttribution {
+    pub project_id: String,
+    pub project_path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub files_touched: usize,
+    pub total_cost: f64,
+    pub cost_per_line: Option<f64>,
+    /// Per-file totals across all sessions, most changed first
+    pub files: Vec<FileChangeStat>,
+    /// Sessions with code changes, most changed first
+    pub sessions: Vec<SessionCodeAttribution>,
+}
+
+fn cost_per_line(cost: f64, lines_added: usize, lines_removed: usize) -> Option<f64> {
+    let lines = lines_added + lines_removed;
+    if lines > 0 {
+        Some(cost / lines as f64)
+    } else {
+        None
+    }
+}
+
+/// Sessions of a project that have git records
+fn list_record_sessions(project_id: &str) -> Result<Vec<String>, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let sessions_dir = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join("sessions");
+    if !sessions_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(&sessions_dir)
+        .map_err(|e| format!("Failed to read sessions directory: {}", e))?
+        .flatten()
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // Skip rewind backups (<sid>.rewind-<stamp>.git-records.json)
+        if let Some(session_id) = name.strip_suffix(".git-records.json") {
+            if !session_id.contains(".rewind-") {
+                sessions.push(session_id.to_string());
+            }
+        }
+    }
+    sessions.sort();
+
+    Ok(sessions)
+}
+
+fn prompt_numstat(
+    app: &AppHandle,
+    project_path: &str,
+    record: &GitRecord,
+    commit_after: &str,
+) -> Result<Vec<FileChangeStat>, String> {
+    let key = (record.commit_before.clone(), commit_after.to_string());
+    if let Some(stats) = NUMSTAT_CACHE.lock().map_err(|e| e.to_string())?.get(&key) {
+        return Ok(stats.clone());
+    }
+
+    let repo = checkpoint::open_repo(app, project_path, record.backend)?;
+    let stats = collect_numstat(&repo, &record.commit_before, commit_after)?;
+    NUMSTAT_CACHE
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(key, stats.clone());
+
+    Ok(stats)
+}
+
+/// Take lines later restored with revert_prompt_paths out of a prompt's numstat
+fn subtract_partial_reverts(files: Vec<FileChangeStat>, record: &GitRecord) -> Vec<FileChangeStat> {
+    let mut reverted: HashMap<&str, (usize, usize)> = HashMap::new();
+    for stat in record
+        .partial_reverts
+        .iter()
+        .flat_map(|r| &r.reverted_lines)
+    {
+        let total = reverted.entry(stat.path.as_str()).or_default();
+        total.0 += stat.lines_added;
+        total.1 += stat.lines_removed;
+    }
+    if reverted.is_empty() {
+        return files;
+    }
+
+    files
+        .into_iter()
+        .filter_map(|mut file| {
+            if let Some((added, removed)) = reverted.get(file.path.as_str()) {
+                file.lines_added = file.lines_added.saturating_sub(*added);
+                file.lines_removed = file.lines_removed.saturating_sub(*removed);
+                if file.lines_added + file.lines_removed == 0 {
+                    return None;
+                }
+            }
+            Some(file)
+        })
+        .collect()
+}
+
+/// Attribute a session's changes to its prompts (cost is filled in by the caller)
+fn attribute_session(
+    app: &AppHandle,
+    session_id: &str,
+    project_id: &str,
+    project_path: &str,
+) -> Result<SessionCodeAttribution, String> {
+    let records = load_git_records(session_id, project_id)
+        .map_err(|e| format!("Failed to load git records: {}", e))?;
+    let mut indices: Vec<usize> = records.keys().copied().collect();
+    indices.sort_unstable();
+
+    let mut session = SessionCodeAttribution {
+        session_id: session_id.to_string(),
+        lines_added: 0,
+        lines_removed: 0,
+        files_touched: 0,
+        pending_prompts: 0,
+        prompts: Vec::new(),
+        cost: 0.0,
+        cost_per_line: None,
+    };
+    let mut touched = HashSet::new();
+
+    for index in indices {
+        let record = &records[&index];
+        let Some(commit_after) = &record.commit_after else {
+            session.pending_prompts += 1;
+            continue;
+        };
+        if commit_after == &record.commit_before {
+            continue;
+        }
+
+        let files = match prompt_numstat(app, project_path, record, commit_after) {
+            Ok(files) => files,
+            Err(e) => {
+                // Commits may be gone (e.g. history rewritten or shadow repo removed)
+                log::warn!(
+                    "Skipping prompt #{} of session {}: {}",
+                    index,
+                    session_id,
+                    e
+                );
+                continue;
+            }
+        };
+        let files = subtract_partial_reverts(files, record);
+        if files.is_empty() {
+            continue;
+        }
+
+        let lines_added = files.iter().map(|f| f.lines_added).sum();
+        let lines_removed = files.iter().map(|f| f.lines_removed).sum();
+        session.lines_added += lines_added;
+        session.lines_removed += lines_removed;
+        touched.extend(files.iter().map(|f| f.path.clone()));
+
+        session.prompts.push(PromptCodeChange {
+            prompt_index: index,
+            timestamp: record.timestamp,
+            commit_before: record.commit_before.clone(),
+            commit_after: commit_after.clone(),
+            lines_added,
+            lines_removed,
+            files,
+        });
+    }
+
+    session.files_touched = touched.len();
+    Ok(session)
+}
+
+/// Usage cost per session id from `usage_entries`
+fn session_costs(db: &AgentDb, session_ids: &[String]) -> Result<HashMap<String, f64>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT COALESCE(SUM(cost), 0.0) FROM usage_entries WHERE session_id = ?1")
+        .map_err(|e| e.to_string())?;
+
+    let mut costs = HashMap::new();
+    for session_id in session_ids {
+        let cost: f64 = stmt
+            .query_row(params![session_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        costs.insert(session_id.clone(), cost);
+    }
+
+    Ok(costs)
+}
+
+/// Lines added/removed and files touched by one session, attributed to its prompts
+#[tauri::command]
+pub async fn get_session_code_attribution(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+) -> Result<SessionCodeAttribution, String> {
+    let mut session = attribute_session(&app, &session_id, &project_id, &project_path)?;
+
+    session.cost = session_costs(&db, std::slice::from_ref(&session_id))?
+        .remove(&session_id)
+        .unwrap_or(0.0);
+    session.cost_per_line = cost_per_line(session.cost, session.lines_added, session.lines_removed);
+
+    Ok(session)
+}
+
+/// AI-authored changes across all sessions of a project, with cost per line changed
+#[tauri::command]
+pub async fn get_project_code_attribution(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_id: String,
+    project_path: String,
+) -> Result<ProjectCodeAttribution, String> {
+    log::info!("Building code attribution report for {}", project_path);
+
+    let mut sessions = Vec::new();
+    for session_id in list_record_sessions(&project_id)? {
+        let session = attribute_session(&app, &session_id, &project_id, &project_path)?;
+        if !session.prompts.is_empty() {
+            sessions.push(session);
+        }
+    }
+
+    let session_ids: Vec<String> = sessions.iter().map(|s| s.session_id.clone()).collect();
+    let costs = session_costs(&db, &session_ids)?;
+
+    let mut files: BTreeMap<String, FileChangeStat> = BTreeMap::new();
+    for session in &mut sessions {
+        session.cost = costs.get(&session.session_id).copied().unwrap_or(0.0);
+        session.cost_per_line =
+            cost_per_line(session.cost, session.lines_added, session.lines_removed);
+
+        for stat in session.prompts.iter().flat_map(|p| &p.files) {
+            let total = files
+                .entry(stat.path.clone())
+                .or_insert_with(|| FileChangeStat {
+                    path: stat.path.clone(),
+                    lines_added: 0,
+                    lines_removed: 0,
+                    binary: stat.binary,
+                });
+            total.lines_added += stat.lines_added;
+            total.lines_removed += stat.lines_removed;
+        }
+    }
+
+    let mut files: Vec<FileChangeStat> = files.into_values().collect();
+    files.sort_by_key(|f| std::cmp::Reverse(f.lines_added + f.lines_removed));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.lines_added + s.lines_removed));
+
+    let lines_added = sessions.iter().map(|s| s.lines_added).sum();
+    let lines_removed = sessions.iter().map(|s| s.lines_removed).sum();
+    let total_cost = sessions.iter().map(|s| s.cost).sum();
+
+    Ok(ProjectCodeAttribution {
+        project_id,
+        project_path,
+        lines_added,
+        lines_removed,
+        files_touched: files.len(),
+        total_cost,
+        cost_per_line: cost_per_line(total_cost, lines_added, lines_removed),
+        files,
+        sessions,
+    })
+}
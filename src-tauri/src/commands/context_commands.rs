@@ -3,8 +3,10 @@
 /// These commands integrate the AutoCompactManager with the frontend,
 /// providing comprehensive context window management capabilities.
 use crate::commands::context_manager::{
-    AutoCompactConfig, AutoCompactManager, AutoCompactState, SessionContext,
+    query_compaction_timeline, AutoCompactConfig, AutoCompactConfigOverride, AutoCompactManager,
+    AutoCompactState, CompactionEvent, SessionContext, SessionContextStats,
 };
+use crate::commands::storage::AgentDb;
 use log::{error, info};
 use tauri::{command, AppHandle, Manager, State};
 
@@ -47,10 +49,11 @@ pub async fn update_session_context(
 ) -> Result<bool, String> {
     let compaction_triggered = state
         .0
-        .update_session_tokens(&session_id, token_count)
+        .update_session_tokens(&app, &session_id, token_count)
         .await?;
 
-    if compaction_triggered {
+    // A session that is still streaming is compacted by the monitor once it finishes
+    if compaction_triggered && !AutoCompactManager::is_session_running(&app, &session_id) {
         info!("Auto-compaction triggered for session {}", session_id);
 
         // Execute compaction in background
@@ -76,14 +79,11 @@ pub async fn trigger_manual_compaction(
 ) -> Result<(), String> {
     info!("Manual compaction triggered for session {}", session_id);
 
-    // Temporarily override custom instructions if provided
-    if let Some(instructions) = custom_instructions {
-        let mut config = state.0.get_config()?;
-        config.custom_instructions = Some(instructions);
-        state.0.update_config(config)?;
-    }
-
-    state.0.execute_compaction(app, &session_id).await?;
+    // Custom instructions apply to this run only and are not saved to the config
+    state
+        .0
+        .execute_manual_compaction(app, &session_id, custom_instructions)
+        .await?;
     Ok(())
 }
 
@@ -106,12 +106,51 @@ pub async fn update_auto_compact_config(
     Ok(())
 }
 
-/// Get session context statistics
+/// Get the auto-compact overrides stored for a project
+#[command]
+pub async fn get_project_auto_compact_config(
+    state: State<'_, AutoCompactState>,
+    project_path: String,
+) -> Result<Option<AutoCompactConfigOverride>, String> {
+    state.0.get_project_override(&project_path)
+}
+
+/// Set auto-compact overrides for a project (None clears them)
+#[command]
+pub async fn set_project_auto_compact_config(
+    state: State<'_, AutoCompactState>,
+    project_path: String,
+    overrides: Option<AutoCompactConfigOverride>,
+) -> Result<(), String> {
+    info!("Updating auto-compact overrides for {}", project_path);
+    state.0.set_project_override(project_path, overrides)
+}
+
+/// Get the configuration in effect for a project (global config + overrides)
+#[command]
+pub async fn get_effective_auto_compact_config(
+    state: State<'_, AutoCompactState>,
+    project_path: String,
+) -> Result<AutoCompactConfig, String> {
+    state.0.effective_config(&project_path)
+}
+
+/// Get the compaction timeline of a session from `compaction_events`
+#[command]
+pub async fn get_compaction_timeline(
+    db: State<'_, AgentDb>,
+    session_id: String,
+) -> Result<Vec<CompactionEvent>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_compaction_timeline(&conn, &session_id)
+}
+
+/// Get session context statistics (with percent of the model's context window used)
 #[command]
 pub fn get_session_context_stats(
     state: State<'_, AutoCompactState>,
     session_id: String,
-) -> Result<Option<SessionContext>, String> {
+) -> Result<Option<SessionContextStats>, String> {
     state.0.get_session_stats(&session_id)
 }
 
@@ -1,22 +1,44 @@
-use log::{error, info};
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 /// Auto-compact context management system for Claude Code SDK integration
 ///
 /// This module provides intelligent context window management with automatic compaction
 /// based on Claude Code SDK best practices and the official documentation.
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 use tokio::time::sleep;
 
+use super::context_meter::ContextUsage;
+use super::storage::AgentDb;
+
+/// Upper bound for a single `/compact` run
+const COMPACTION_TIMEOUT_SECS: u64 = 600;
+
+/// Global config and per-project overrides, stored next to `execution_config.json`
+const CONFIG_FILE: &str = "auto_compact_config.json";
+
+/// Tracked sessions, so compaction history and intervals survive restarts
+const SESSIONS_FILE: &str = "auto_compact_sessions.json";
+
+/// Session changes within this window are written to `SESSIONS_FILE` together
+const SESSIONS_SAVE_DEBOUNCE_MS: u64 = 2000;
+
+/// Sessions without activity for this long are dropped from tracking
+const SESSION_IDLE_PRUNE_SECS: u64 = 30 * 24 * 60 * 60;
+
 /// Configuration for auto-compact behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AutoCompactConfig {
     /// Enable automatic compaction
     pub enabled: bool,
-    /// Maximum context tokens before triggering compaction (default: 120000 for Claude 4)
+    /// Context window assumed for models missing from the capability table (default: 120000)
     pub max_context_tokens: usize,
-    /// Threshold percentage to trigger compaction (0.0-1.0, default: 0.85)
+    /// Fraction of the model's context window that triggers compaction (0.0-1.0, default: 0.85)
     pub compaction_threshold: f64,
     /// Minimum time between compactions in seconds (default: 300s = 5min)
     pub min_compaction_interval: u64,
@@ -43,6 +65,101 @@ pub enum CompactionStrategy {
     Custom(String),
 }
 
+impl CompactionStrategy {
+    /// Name recorded in `compaction_events`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompactionStrategy::Smart => "smart",
+            CompactionStrategy::Aggressive => "aggressive",
+            CompactionStrategy::Conservative => "conservative",
+            CompactionStrategy::Custom(_) => "custom",
+        }
+    }
+}
+
+/// Per-project overrides; unset fields fall back to the global config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoCompactConfigOverride {
+    pub enabled: Option<bool>,
+    pub max_context_tokens: Option<usize>,
+    pub compaction_threshold: Option<f64>,
+    pub min_compaction_interval: Option<u64>,
+    pub compaction_strategy: Option<CompactionStrategy>,
+    pub preserve_recent_messages: Option<bool>,
+    pub preserve_message_count: Option<usize>,
+    pub custom_instructions: Option<String>,
+}
+
+impl AutoCompactConfigOverride {
+    /// Effective config for a project using these overrides
+    pub fn apply(&self, base: &AutoCompactConfig) -> AutoCompactConfig {
+        AutoCompactConfig {
+            enabled: self.enabled.unwrap_or(base.enabled),
+            max_context_tokens: self.max_context_tokens.unwrap_or(base.max_context_tokens),
+            compaction_threshold: self
+                .compaction_threshold
+                .unwrap_or(base.compaction_threshold),
+            min_compaction_interval: self
+                .min_compaction_interval
+                .unwrap_or(base.min_compaction_interval),
+            compaction_strategy: self
+                .compaction_strategy
+                .clone()
+                .unwrap_or_else(|| base.compaction_strategy.clone()),
+            preserve_recent_messages: self
+                .preserve_recent_messages
+                .unwrap_or(base.preserve_recent_messages),
+            preserve_message_count: self
+                .preserve_message_count
+                .unwrap_or(base.preserve_message_count),
+            custom_instructions: self
+                .custom_instructions
+                .clone()
+                .or_else(|| base.custom_instructions.clone()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.enabled.is_none()
+            && self.max_context_tokens.is_none()
+            && self.compaction_threshold.is_none()
+            && self.min_compaction_interval.is_none()
+            && self.compaction_strategy.is_none()
+            && self.preserve_recent_messages.is_none()
+            && self.preserve_message_count.is_none()
+            && self.custom_instructions.is_none()
+    }
+}
+
+/// On-disk layout of `auto_compact_config.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PersistedAutoCompact {
+    global: AutoCompactConfig,
+    /// Keyed by project path
+    projects: HashMap<String, AutoCompactConfigOverride>,
+}
+
+/// One row of `compaction_events`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionEvent {
+    pub id: Option<i64>,
+    pub session_id: String,
+    pub project_path: String,
+    /// RFC 3339
+    pub timestamp: String,
+    /// "auto" or "manual"
+    pub trigger: String,
+    pub strategy: String,
+    pub tokens_before: usize,
+    pub tokens_after: Option<usize>,
+    pub success: bool,
+    pub error: Option<String>,
+    /// Session the CLI continued with, if it differs from `session_id`
+    pub new_session_id: Option<String>,
+}
+
 /// Session context tracking information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionContext {
@@ -55,6 +172,25 @@ pub struct SessionContext {
     pub compaction_count: usize,
     pub model: String,
     pub status: SessionStatus,
+    /// Last compaction attempt, successful or not (failed attempts also wait out the interval)
+    #[serde(with = "systemtime_serde", default)]
+    pub last_attempt: Option<SystemTime>,
+    /// Token breakdown of the latest assistant turn, when reported by the CLI
+    #[serde(default)]
+    pub last_usage: Option<ContextUsage>,
+    /// Compaction event whose `tokens_after` is filled in by the next reported turn
+    #[serde(default)]
+    pub pending_compaction_event: Option<i64>,
+    /// Last registration, turn or compaction; long-idle sessions are pruned
+    #[serde(with = "systemtime_serde", default)]
+    pub last_activity: Option<SystemTime>,
+}
+
+/// What the CLI reported for a finished compaction
+#[derive(Debug, Clone, Default)]
+struct CompactionOutcome {
+    /// Session id reported by the CLI (differs from the tracked id if the CLI forked)
+    session_id: Option<String>,
 }
 
 mod systemtime_serde {
@@ -93,11 +229,29 @@ pub enum SessionStatus {
     CompactionFailed(String),
 }
 
+/// Session context plus the limits of its model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionContextStats {
+    #[serde(flatten)]
+    pub context: SessionContext,
+    /// Capability table entry the model resolved to (None: fallback window)
+    pub model_capability: Option<String>,
+    pub context_window: usize,
+    pub max_output_tokens: Option<usize>,
+    pub threshold_tokens: usize,
+    /// current_tokens as a percentage of the context window
+    pub context_usage_percent: f64,
+}
+
 /// Auto-compact manager state
+#[derive(Clone)]
 pub struct AutoCompactManager {
     pub sessions: Arc<Mutex<HashMap<String, SessionContext>>>,
     pub config: Arc<Mutex<AutoCompactConfig>>,
+    pub project_overrides: Arc<Mutex<HashMap<String, AutoCompactConfigOverride>>>,
     pub is_monitoring: Arc<Mutex<bool>>,
+    /// A debounced write of `sessions` is scheduled
+    save_pending: Arc<AtomicBool>,
 }
 
 impl Default for AutoCompactConfig {
@@ -116,13 +270,106 @@ impl Default for AutoCompactConfig {
 }
 
 impl AutoCompactManager {
-    /// Create a new AutoCompactManager instance
+    /// Create a new AutoCompactManager instance, restoring persisted config and sessions
     pub fn new() -> Self {
+        let persisted = load_persisted_config();
         Self {
-            sessions: Arc::new(Mutex::new(HashMap::new())),
-            config: Arc::new(Mutex::new(AutoCompactConfig::default())),
+            sessions: Arc::new(Mutex::new(load_persisted_sessions())),
+            config: Arc::new(Mutex::new(persisted.global)),
+            project_overrides: Arc::new(Mutex::new(persisted.projects)),
             is_monitoring: Arc::new(Mutex::new(false)),
+            save_pending: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Write the global config and project overrides to disk
+    fn save_config(&self) -> Result<(), String> {
+        let persisted = PersistedAutoCompact {
+            global: self.config.lock().map_err(|e| e.to_string())?.clone(),
+            projects: self
+                .project_overrides
+                .lock()
+                .map_err(|e| e.to_string())?
+                .clone(),
+        };
+        write_json_file(CONFIG_FILE, &persisted)
+    }
+
+    /// Schedule a write of the tracked sessions. Changes within the debounce window are
+    /// written together, from a copy taken outside the caller's lock, and sessions idle
+    /// for longer than `SESSION_IDLE_PRUNE_SECS` are dropped first. Failures are only
+    /// logged: losing a token count must not fail the operation that produced it.
+    fn save_sessions(&self) {
+        if self.save_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let sessions = self.sessions.clone();
+        let save_pending = self.save_pending.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(SESSIONS_SAVE_DEBOUNCE_MS));
+            // Changes made from here on schedule another write
+            save_pending.store(false, Ordering::Release);
+
+            let mut list: Vec<SessionContext> = match sessions.lock() {
+                Ok(mut sessions) => {
+                    prune_idle_sessions(&mut sessions);
+                    sessions.values().cloned().collect()
+                }
+                Err(e) => {
+                    warn!("Failed to persist auto-compact sessions: {}", e);
+                    return;
+                }
+            };
+            list.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+            if let Err(e) = write_json_file(SESSIONS_FILE, &list) {
+                warn!("Failed to persist auto-compact sessions: {}", e);
+            }
+        });
+    }
+
+    /// Global config with the project's overrides applied
+    pub fn effective_config(&self, project_path: &str) -> Result<AutoCompactConfig, String> {
+        let config = self.config.lock().map_err(|e| e.to_string())?;
+        let overrides = self.project_overrides.lock().map_err(|e| e.to_string())?;
+        Ok(match overrides.get(project_path) {
+            Some(project) => project.apply(&config),
+            None => config.clone(),
+        })
+    }
+
+    /// Overrides stored for a project
+    pub fn get_project_override(
+        &self,
+        project_path: &str,
+    ) -> Result<Option<AutoCompactConfigOverride>, String> {
+        let overrides = self.project_overrides.lock().map_err(|e| e.to_string())?;
+        Ok(overrides.get(project_path).cloned())
+    }
+
+    /// Set (or with None / no fields set, remove) a project's overrides
+    pub fn set_project_override(
+        &self,
+        project_path: String,
+        overrides: Option<AutoCompactConfigOverride>,
+    ) -> Result<(), String> {
+        {
+            let mut projects = self.project_overrides.lock().map_err(|e| e.to_string())?;
+            match overrides.filter(|o| !o.is_empty()) {
+                Some(o) => {
+                    projects.insert(project_path.clone(), o);
+                }
+                None => {
+                    projects.remove(&project_path);
+                }
+            }
         }
+        self.save_config()?;
+        info!(
+            "Auto-compact overrides updated for project {}",
+            project_path
+        );
+        Ok(())
     }
 
     /// Register a new session for monitoring
@@ -134,18 +381,33 @@ impl AutoCompactManager {
     ) -> Result<(), String> {
         let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
 
-        let context = SessionContext {
-            session_id: session_id.clone(),
-            project_path,
-            current_tokens: 0,
-            message_count: 0,
-            last_compaction: None,
-            compaction_count: 0,
-            model,
-            status: SessionStatus::Active,
-        };
-
-        sessions.insert(session_id.clone(), context);
+        // Re-registering a known session (e.g. after a restart) keeps its compaction history
+        match sessions.get_mut(&session_id) {
+            Some(existing) => {
+                existing.project_path = project_path;
+                existing.model = model;
+                existing.status = SessionStatus::Active;
+                existing.last_activity = Some(SystemTime::now());
+            }
+            None => {
+                let context = SessionContext {
+                    session_id: session_id.clone(),
+                    project_path,
+                    current_tokens: 0,
+                    message_count: 0,
+                    last_compaction: None,
+                    compaction_count: 0,
+                    model,
+                    status: SessionStatus::Active,
+                    last_attempt: None,
+                    last_usage: None,
+                    pending_compaction_event: None,
+                    last_activity: Some(SystemTime::now()),
+                };
+                sessions.insert(session_id.clone(), context);
+            }
+        }
+        self.save_sessions();
         info!(
             "Registered session {} for auto-compact monitoring",
             session_id
@@ -156,46 +418,131 @@ impl AutoCompactManager {
     /// Update session token count and trigger compaction if needed
     pub async fn update_session_tokens(
         &self,
+        app: &tauri::AppHandle,
+        session_id: &str,
+        token_count: usize,
+    ) -> Result<bool, String> {
+        self.record_turn(app, session_id, token_count, None, true)
+    }
+
+    /// Record the usage of a session's latest assistant turn; its context size
+    /// replaces the previous one. `new_turn` is false when the CLI reports the same
+    /// message again with updated usage. Returns true if compaction is needed.
+    pub async fn update_session_usage(
+        &self,
+        app: &tauri::AppHandle,
+        session_id: &str,
+        usage: ContextUsage,
+        new_turn: bool,
+    ) -> Result<bool, String> {
+        self.record_turn(
+            app,
+            session_id,
+            usage.context_tokens(),
+            Some(usage),
+            new_turn,
+        )
+    }
+
+    /// Set a session's context size without counting a turn, e.g. from the JSONL of a
+    /// resumed session or after the CLI compacted the conversation itself
+    pub fn seed_session_usage(&self, session_id: &str, usage: ContextUsage) -> Result<(), String> {
+        let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+        if let Some(session) = sessions.get_mut(session_id) {
+            session.current_tokens = usage.context_tokens();
+            session.last_usage = Some(usage);
+            info!(
+                "Seeded context of session {}: {} tokens",
+                session_id, session.current_tokens
+            );
+            self.save_sessions();
+        }
+        Ok(())
+    }
+
+    fn record_turn(
+        &self,
+        app: &tauri::AppHandle,
         session_id: &str,
         token_count: usize,
+        usage: Option<ContextUsage>,
+        new_turn: bool,
     ) -> Result<bool, String> {
         let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
-        let config = self.config.lock().map_err(|e| e.to_string())?;
 
-        if !config.enabled {
+        let Some(session) = sessions.get_mut(session_id) else {
             return Ok(false);
+        };
+        session.current_tokens = token_count;
+        if new_turn {
+            session.message_count += 1;
+        }
+        session.last_activity = Some(SystemTime::now());
+        if usage.is_some() {
+            session.last_usage = usage;
+        }
+        // The first turn after a compaction reports the compacted context size
+        if let Some(event_id) = session.pending_compaction_event.take() {
+            set_compaction_tokens_after(app, event_id, token_count);
         }
 
-        if let Some(session) = sessions.get_mut(session_id) {
-            session.current_tokens = token_count;
-            session.message_count += 1;
+        let config = self.effective_config(&session.project_path)?;
+        let needs_compaction = Self::needs_compaction(session, &config);
+        if needs_compaction {
+            info!(
+                "Auto-compaction threshold reached for session {}: {} tokens (threshold: {})",
+                session_id,
+                token_count,
+                Self::threshold_tokens(&config, Self::context_window(&session.model, &config))
+            );
+        }
+        self.save_sessions();
 
-            // Check if compaction is needed
-            let threshold_tokens =
-                (config.max_context_tokens as f64 * config.compaction_threshold) as usize;
-            let needs_compaction = token_count >= threshold_tokens;
-
-            // Check minimum interval
-            let interval_ok = if let Some(last_compaction) = session.last_compaction {
-                let elapsed = SystemTime::now()
-                    .duration_since(last_compaction)
-                    .unwrap_or(Duration::from_secs(0));
-                elapsed.as_secs() >= config.min_compaction_interval
-            } else {
-                true // No previous compaction
-            };
+        Ok(needs_compaction)
+    }
 
-            if needs_compaction && interval_ok {
-                info!(
-                    "Auto-compaction triggered for session {}: {} tokens (threshold: {})",
-                    session_id, token_count, threshold_tokens
-                );
-                session.status = SessionStatus::Compacting;
-                return Ok(true);
-            }
+    /// Context window of a session's model, or the configured fallback for unknown models
+    fn context_window(model: &str, config: &AutoCompactConfig) -> usize {
+        super::model_capabilities::resolve(model)
+            .map(|capability| capability.context_window)
+            .unwrap_or(config.max_context_tokens)
+    }
+
+    fn threshold_tokens(config: &AutoCompactConfig, context_window: usize) -> usize {
+        (context_window as f64 * config.compaction_threshold) as usize
+    }
+
+    /// Whether a session is over the threshold, outside `min_compaction_interval`
+    /// and not already being compacted
+    fn needs_compaction(session: &SessionContext, config: &AutoCompactConfig) -> bool {
+        if !config.enabled || matches!(session.status, SessionStatus::Compacting) {
+            return false;
         }
 
-        Ok(false)
+        let interval_ok = match session.last_attempt.or(session.last_compaction) {
+            Some(last) => {
+                SystemTime::now()
+                    .duration_since(last)
+                    .unwrap_or(Duration::from_secs(0))
+                    .as_secs()
+                    >= config.min_compaction_interval
+            }
+            None => true, // No previous compaction
+        };
+
+        interval_ok
+            && session.current_tokens
+                >= Self::threshold_tokens(config, Self::context_window(&session.model, config))
+    }
+
+    /// Whether a Claude process is currently running this session.
+    /// Compacting it from a second process would race with the live one.
+    pub fn is_session_running(app: &tauri::AppHandle, session_id: &str) -> bool {
+        use tauri::Manager;
+        app.try_state::<crate::process::ProcessRegistryState>()
+            .and_then(|registry| registry.0.get_claude_session_by_id(session_id).ok())
+            .flatten()
+            .is_some()
     }
 
     /// Execute compaction for a session
@@ -204,65 +551,180 @@ impl AutoCompactManager {
         app: tauri::AppHandle,
         session_id: &str,
     ) -> Result<(), String> {
-        info!("Executing auto-compaction for session {}", session_id);
+        self.run_compaction(app, session_id, "auto", None).await
+    }
 
-        let (project_path, custom_instructions) = {
-            let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
-            let config = self.config.lock().map_err(|e| e.to_string())?;
+    /// Compact a session on request; `custom_instructions` applies to this run only
+    pub async fn execute_manual_compaction(
+        &self,
+        app: tauri::AppHandle,
+        session_id: &str,
+        custom_instructions: Option<String>,
+    ) -> Result<(), String> {
+        self.run_compaction(app, session_id, "manual", custom_instructions)
+            .await
+    }
+
+    async fn run_compaction(
+        &self,
+        app: tauri::AppHandle,
+        session_id: &str,
+        trigger: &str,
+        custom_instructions: Option<String>,
+    ) -> Result<(), String> {
+        info!(
+            "Executing {} compaction for session {}",
+            trigger, session_id
+        );
+
+        if Self::is_session_running(&app, session_id) {
+            return Err(format!(
+                "Session {} is still running; compaction deferred",
+                session_id
+            ));
+        }
+
+        // Claim the session so concurrent triggers (monitor, frontend, manual) compact it once
+        let (project_path, config, tokens_before) = {
+            let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
 
             let session = sessions
-                .get(session_id)
+                .get_mut(session_id)
                 .ok_or_else(|| format!("Session {} not found", session_id))?;
+            if matches!(session.status, SessionStatus::Compacting) {
+                return Err(format!("Session {} is already being compacted", session_id));
+            }
+            let config = self.effective_config(&session.project_path)?;
+            session.status = SessionStatus::Compacting;
+            session.last_attempt = Some(SystemTime::now());
+            session.last_activity = session.last_attempt;
 
-            (
-                session.project_path.clone(),
-                config.custom_instructions.clone(),
-            )
+            (session.project_path.clone(), config, session.current_tokens)
         };
 
         // Build compaction command based on strategy
-        let compaction_cmd = self.build_compaction_command(&custom_instructions).await?;
+        let custom_instructions =
+            custom_instructions.or_else(|| config.custom_instructions.clone());
+        let compaction_cmd = Self::build_compaction_command(&config, &custom_instructions);
+        let mut event = CompactionEvent {
+            id: None,
+            session_id: session_id.to_string(),
+            project_path: project_path.clone(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            trigger: trigger.to_string(),
+            strategy: config.compaction_strategy.name().to_string(),
+            tokens_before,
+            tokens_after: None,
+            success: false,
+            error: None,
+            new_session_id: None,
+        };
 
         // Execute compaction using Claude CLI
         match self
-            .execute_claude_compaction(&app, &project_path, &compaction_cmd)
+            .execute_claude_compaction(&app, session_id, &project_path, &compaction_cmd)
             .await
         {
-            Ok(_) => {
+            Ok(outcome) => {
                 // Update session state after successful compaction
-                let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
-                if let Some(session) = sessions.get_mut(session_id) {
-                    session.last_compaction = Some(SystemTime::now());
-                    session.compaction_count += 1;
-                    session.status = SessionStatus::Active;
-                    session.current_tokens = session.current_tokens / 3; // Estimated token reduction
-
-                    info!(
-                        "Auto-compaction completed for session {}: compaction #{}, estimated tokens: {}",
-                        session_id, session.compaction_count, session.current_tokens
-                    );
+                let mut hook_data = serde_json::json!({ "success": true });
+                {
+                    let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+                    if let Some(mut session) = sessions.remove(session_id) {
+                        session.last_compaction = Some(SystemTime::now());
+                        session.compaction_count += 1;
+                        session.status = SessionStatus::Active;
+                        // Unknown until the next assistant message reports usage
+                        session.current_tokens = 0;
+
+                        // Keep tracking under the id the CLI continues with
+                        let tracked_id = match &outcome.session_id {
+                            Some(new_id) if new_id != session_id => {
+                                info!(
+                                    "Compaction of {} continued as session {}",
+                                    session_id, new_id
+                                );
+                                new_id.clone()
+                            }
+                            _ => session_id.to_string(),
+                        };
+                        session.session_id = tracked_id.clone();
+
+                        info!(
+                            "Auto-compaction completed for session {}: compaction #{}, {} tokens before",
+                            session_id, session.compaction_count, tokens_before
+                        );
+                        hook_data = serde_json::json!({
+                            "success": true,
+                            "compaction_count": session.compaction_count,
+                            "tokens_before": tokens_before,
+                            "new_session_id": outcome.session_id,
+                        });
+                        sessions.insert(tracked_id, session);
+                    }
+                    self.save_sessions();
+                }
+                event.success = true;
+                event.new_session_id = outcome.session_id.clone().filter(|id| id != session_id);
+                // tokens_after is filled in when the next turn reports usage
+                if let Some(event_id) = record_compaction_event(&app, &event) {
+                    let tracked_id = outcome.session_id.as_deref().unwrap_or(session_id);
+                    let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+                    if let Some(session) = sessions.get_mut(tracked_id) {
+                        session.pending_compaction_event = Some(event_id);
+                        self.save_sessions();
+                    }
                 }
+                self.fire_compact_hooks(&app, session_id, &project_path, hook_data);
                 Ok(())
             }
             Err(e) => {
                 // Update session state after failed compaction
-                let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
-                if let Some(session) = sessions.get_mut(session_id) {
-                    session.status = SessionStatus::CompactionFailed(e.clone());
+                {
+                    let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
+                    if let Some(session) = sessions.get_mut(session_id) {
+                        session.status = SessionStatus::CompactionFailed(e.clone());
+                    }
+                    self.save_sessions();
                 }
+                event.error = Some(e.clone());
+                record_compaction_event(&app, &event);
                 error!("Auto-compaction failed for session {}: {}", session_id, e);
+                self.fire_compact_hooks(
+                    &app,
+                    session_id,
+                    &project_path,
+                    serde_json::json!({ "success": false, "error": e }),
+                );
                 Err(e)
             }
         }
     }
 
-    /// Build compaction command based on strategy
-    async fn build_compaction_command(
+    /// Fires OnContextCompact hooks for a finished compaction attempt
+    fn fire_compact_hooks(
         &self,
-        custom_instructions: &Option<String>,
-    ) -> Result<String, String> {
-        let config = self.config.lock().map_err(|e| e.to_string())?;
+        app: &tauri::AppHandle,
+        session_id: &str,
+        project_path: &str,
+        data: serde_json::Value,
+    ) {
+        use tauri::Manager;
+        if let Some(hook_manager) = app.try_state::<super::enhanced_hooks::HookManager>() {
+            hook_manager.fire(
+                super::enhanced_hooks::HookEvent::OnContextCompact,
+                session_id,
+                project_path,
+                data,
+            );
+        }
+    }
 
+    /// Build compaction command based on strategy
+    fn build_compaction_command(
+        config: &AutoCompactConfig,
+        custom_instructions: &Option<String>,
+    ) -> String {
         let base_instruction = match &config.compaction_strategy {
             CompactionStrategy::Smart => {
                 "Focus on preserving key information, decisions made, and current context. \
@@ -280,47 +742,54 @@ impl AutoCompactManager {
             CompactionStrategy::Custom(instructions) => instructions,
         };
 
-        let final_instruction = if let Some(custom) = custom_instructions {
+        if let Some(custom) = custom_instructions {
             format!(
                 "{}\n\nAdditional instructions: {}",
                 base_instruction, custom
             )
         } else {
             base_instruction.to_string()
-        };
-
-        Ok(final_instruction)
+        }
     }
 
-    /// Execute Claude CLI compaction command
+    /// Run `/compact` on the exact session via `claude --resume <session_id> --print`
     async fn execute_claude_compaction(
         &self,
         app: &tauri::AppHandle,
+        session_id: &str,
         project_path: &str,
         instructions: &str,
-    ) -> Result<(), String> {
+    ) -> Result<CompactionOutcome, String> {
         // Find Claude CLI binary
         let claude_path = crate::claude_binary::find_claude_binary(app)?;
 
         // Build compaction command
-        let mut cmd = tokio::process::Command::new(&claude_path);
-        cmd.args(&["/compact"])
+        let mut cmd = tokio::process::Command::from(crate::claude_binary::create_command_with_env(
+            &claude_path,
+        ));
+        cmd.args(["--resume", session_id, "--print", "--output-format", "json"])
             .current_dir(project_path)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped());
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true);
+
+        #[cfg(target_os = "windows")]
+        {
+            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
 
         // Execute compaction
         let mut child = cmd
             .spawn()
             .map_err(|e| format!("Failed to spawn compaction process: {}", e))?;
 
-        // Send instructions to stdin
+        // Send the slash command (with instructions) to stdin
         if let Some(stdin) = child.stdin.take() {
             use tokio::io::AsyncWriteExt;
             let mut stdin = stdin;
             stdin
-                .write_all(instructions.as_bytes())
+                .write_all(format!("/compact {}", instructions).as_bytes())
                 .await
                 .map_err(|e| format!("Failed to write compaction instructions: {}", e))?;
             stdin
@@ -330,17 +799,20 @@ impl AutoCompactManager {
         }
 
         // Wait for completion
-        let output = child
-            .wait_with_output()
-            .await
-            .map_err(|e| format!("Failed to wait for compaction: {}", e))?;
+        let output = tokio::time::timeout(
+            Duration::from_secs(COMPACTION_TIMEOUT_SECS),
+            child.wait_with_output(),
+        )
+        .await
+        .map_err(|_| format!("Compaction timed out after {}s", COMPACTION_TIMEOUT_SECS))?
+        .map_err(|e| format!("Failed to wait for compaction: {}", e))?;
 
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(format!("Compaction failed: {}", error));
         }
 
-        Ok(())
+        parse_compaction_output(&String::from_utf8_lossy(&output.stdout))
     }
 
     /// Start background monitoring
@@ -354,48 +826,42 @@ impl AutoCompactManager {
         *is_monitoring = true;
         drop(is_monitoring);
 
-        let sessions = self.sessions.clone();
-        let config = self.config.clone();
-        let is_monitoring_flag = self.is_monitoring.clone();
+        let manager = self.clone();
 
         tokio::spawn(async move {
             info!("Starting auto-compact monitoring loop");
 
             while {
-                let flag = is_monitoring_flag.lock().unwrap();
+                let flag = manager.is_monitoring.lock().unwrap();
                 *flag
             } {
                 // Check all sessions for compaction needs
                 let session_ids: Vec<String> = {
-                    let sessions = sessions.lock().unwrap();
+                    let sessions = manager.sessions.lock().unwrap();
                     sessions.keys().cloned().collect()
                 };
 
                 for session_id in session_ids {
                     let needs_compaction = {
-                        let sessions = sessions.lock().unwrap();
-                        let config = config.lock().unwrap();
-
-                        if !config.enabled {
-                            continue;
-                        }
-
-                        if let Some(session) = sessions.get(&session_id) {
-                            matches!(session.status, SessionStatus::Compacting)
-                        } else {
-                            false
-                        }
+                        let sessions = manager.sessions.lock().unwrap();
+
+                        sessions
+                            .get(&session_id)
+                            .and_then(|session| {
+                                manager
+                                    .effective_config(&session.project_path)
+                                    .ok()
+                                    .map(|config| Self::needs_compaction(session, &config))
+                            })
+                            .unwrap_or(false)
                     };
 
-                    if needs_compaction {
+                    // Sessions still streaming are picked up on a later pass once they finish
+                    if needs_compaction && !Self::is_session_running(&app, &session_id) {
                         // Execute compaction in a separate task
                         let app_clone = app.clone();
                         let session_id_clone = session_id.clone();
-                        let manager = AutoCompactManager {
-                            sessions: sessions.clone(),
-                            config: config.clone(),
-                            is_monitoring: is_monitoring_flag.clone(),
-                        };
+                        let manager = manager.clone();
 
                         tokio::spawn(async move {
                             if let Err(e) = manager
@@ -431,8 +897,11 @@ impl AutoCompactManager {
 
     /// Update configuration
     pub fn update_config(&self, new_config: AutoCompactConfig) -> Result<(), String> {
-        let mut config = self.config.lock().map_err(|e| e.to_string())?;
-        *config = new_config;
+        {
+            let mut config = self.config.lock().map_err(|e| e.to_string())?;
+            *config = new_config;
+        }
+        self.save_config()?;
         info!("Auto-compact configuration updated");
         Ok(())
     }
@@ -443,16 +912,48 @@ impl AutoCompactManager {
         Ok(config.clone())
     }
 
-    /// Get session statistics
-    pub fn get_session_stats(&self, session_id: &str) -> Result<Option<SessionContext>, String> {
-        let sessions = self.sessions.lock().map_err(|e| e.to_string())?;
-        Ok(sessions.get(session_id).cloned())
+    /// Get session statistics, including how much of the model's window is used
+    pub fn get_session_stats(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<SessionContextStats>, String> {
+        let Some(context) = self
+            .sessions
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(session_id)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+
+        let config = self.effective_config(&context.project_path)?;
+        let capability = super::model_capabilities::resolve(&context.model);
+        let context_window = capability
+            .as_ref()
+            .map(|c| c.context_window)
+            .unwrap_or(config.max_context_tokens);
+        let context_usage_percent = if context_window > 0 {
+            context.current_tokens as f64 / context_window as f64 * 100.0
+        } else {
+            0.0
+        };
+
+        Ok(Some(SessionContextStats {
+            model_capability: capability.as_ref().map(|c| c.id.clone()),
+            max_output_tokens: capability.map(|c| c.max_output_tokens),
+            threshold_tokens: Self::threshold_tokens(&config, context_window),
+            context_window,
+            context_usage_percent,
+            context,
+        }))
     }
 
     /// Remove session from monitoring
     pub fn unregister_session(&self, session_id: &str) -> Result<(), String> {
         let mut sessions = self.sessions.lock().map_err(|e| e.to_string())?;
         sessions.remove(session_id);
+        self.save_sessions();
         info!(
             "Unregistered session {} from auto-compact monitoring",
             session_id
@@ -461,6 +962,215 @@ impl AutoCompactManager {
     }
 }
 
+/// Parse `claude --print --output-format json` output of a `/compact` run.
+/// Accepts the single result object or the message array printed with `--verbose`.
+fn parse_compaction_output(stdout: &str) -> Result<CompactionOutcome, String> {
+    let value: serde_json::Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Failed to parse compaction output: {}", e))?;
+
+    let result = match &value {
+        serde_json::Value::Array(messages) => messages
+            .iter()
+            .rev()
+            .find(|m| m.get("type").and_then(|t| t.as_str()) == Some("result"))
+            .cloned()
+            .unwrap_or(serde_json::Value::Null),
+        _ => value,
+    };
+
+    if result.get("is_error").and_then(|v| v.as_bool()) == Some(true) {
+        let message = result
+            .get("result")
+            .and_then(|r| r.as_str())
+            .unwrap_or("unknown error");
+        return Err(format!("Compaction failed: {}", message));
+    }
+
+    // The result's usage covers the summarizing call, not the compacted context;
+    // the context size is only known once the next turn reports usage
+    Ok(CompactionOutcome {
+        session_id: result
+            .get("session_id")
+            .and_then(|s| s.as_str())
+            .map(|s| s.to_string()),
+    })
+}
+
+fn auto_compact_file(name: &str) -> Result<std::path::PathBuf, String> {
+    super::claude::get_claude_dir()
+        .map(|dir| dir.join(name))
+        .map_err(|e| format!("Failed to get Claude directory: {}", e))
+}
+
+fn write_json_file<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<(), String> {
+    let path = auto_compact_file(name)?;
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn read_json_file<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    let path = auto_compact_file(name).ok()?;
+    let content = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Failed to parse {}: {}, ignoring", path.display(), e);
+            None
+        }
+    }
+}
+
+fn load_persisted_config() -> PersistedAutoCompact {
+    read_json_file(CONFIG_FILE).unwrap_or_default()
+}
+
+/// Sessions from the previous run. No process or compaction survives a restart,
+/// so in-flight states are reset to Idle.
+fn load_persisted_sessions() -> HashMap<String, SessionContext> {
+    let sessions: Vec<SessionContext> = read_json_file(SESSIONS_FILE).unwrap_or_default();
+    let mut sessions: HashMap<String, SessionContext> = sessions
+        .into_iter()
+        .map(|mut session| {
+            if matches!(
+                session.status,
+                SessionStatus::Active | SessionStatus::Compacting
+            ) {
+                session.status = SessionStatus::Idle;
+            }
+            // Files written before activity was tracked start their idle clock now
+            session.last_activity.get_or_insert_with(SystemTime::now);
+            (session.session_id.clone(), session)
+        })
+        .collect();
+    prune_idle_sessions(&mut sessions);
+    sessions
+}
+
+/// Drop sessions with no activity for `SESSION_IDLE_PRUNE_SECS` (never one being compacted)
+fn prune_idle_sessions(sessions: &mut HashMap<String, SessionContext>) {
+    let before = sessions.len();
+    sessions.retain(|_, session| {
+        matches!(session.status, SessionStatus::Compacting)
+            || session
+                .last_activity
+                .and_then(|last| SystemTime::now().duration_since(last).ok())
+                .is_none_or(|idle| idle.as_secs() < SESSION_IDLE_PRUNE_SECS)
+    });
+    if sessions.len() < before {
+        info!(
+            "Pruned {} idle auto-compact sessions",
+            before - sessions.len()
+        );
+    }
+}
+
+/// Store a compaction attempt in `compaction_events`, returning its id
+fn record_compaction_event(app: &tauri::AppHandle, event: &CompactionEvent) -> Option<i64> {
+    use tauri::Manager;
+    let db = app.try_state::<AgentDb>()?;
+    let result = db.0.lock().map_err(|e| e.to_string()).and_then(|conn| {
+        conn.execute(
+            "INSERT INTO compaction_events
+                (session_id, project_path, timestamp, trigger, strategy,
+                 tokens_before, tokens_after, success, error, new_session_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                event.session_id,
+                event.project_path,
+                event.timestamp,
+                event.trigger,
+                event.strategy,
+                event.tokens_before as i64,
+                event.tokens_after.map(|t| t as i64),
+                event.success,
+                event.error,
+                event.new_session_id,
+            ],
+        )
+        .map(|_| conn.last_insert_rowid())
+        .map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!("Failed to record compaction event: {}", e);
+            None
+        }
+    }
+}
+
+/// Fill in the context size a compaction left behind
+fn set_compaction_tokens_after(app: &tauri::AppHandle, event_id: i64, tokens_after: usize) {
+    use tauri::Manager;
+    let Some(db) = app.try_state::<AgentDb>() else {
+        return;
+    };
+    let result = db.0.lock().map_err(|e| e.to_string()).and_then(|conn| {
+        conn.execute(
+            "UPDATE compaction_events SET tokens_after = ?1 WHERE id = ?2",
+            params![tokens_after as i64, event_id],
+        )
+        .map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        warn!("Failed to update compaction event {}: {}", event_id, e);
+    }
+}
+
+/// Compaction events of a session, oldest first. Follows the chain of session
+/// ids a compaction continued under, so the timeline is complete from either end.
+pub fn query_compaction_timeline(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<Vec<CompactionEvent>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, session_id, project_path, timestamp, trigger, strategy,
+                    tokens_before, tokens_after, success, error, new_session_id
+             FROM compaction_events
+             WHERE session_id = ?1 OR new_session_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut events: HashMap<i64, CompactionEvent> = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![session_id.to_string()];
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id.clone()) {
+            continue;
+        }
+        let rows = stmt
+            .query_map(params![id], |row| {
+                Ok(CompactionEvent {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    project_path: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    timestamp: row.get(3)?,
+                    trigger: row.get(4)?,
+                    strategy: row.get(5)?,
+                    tokens_before: row.get::<_, i64>(6)? as usize,
+                    tokens_after: row.get::<_, Option<i64>>(7)?.map(|t| t as usize),
+                    success: row.get(8)?,
+                    error: row.get(9)?,
+                    new_session_id: row.get(10)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+
+        for event in rows {
+            let event = event.map_err(|e| e.to_string())?;
+            pending.push(event.session_id.clone());
+            pending.extend(event.new_session_id.clone());
+            events.entry(event.id.unwrap_or_default()).or_insert(event);
+        }
+    }
+
+    let mut timeline: Vec<CompactionEvent> = events.into_values().collect();
+    timeline.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+    Ok(timeline)
+}
+
 /// State wrapper for AutoCompactManager
 #[derive(Clone)]
 pub struct AutoCompactState(pub Arc<AutoCompactManager>);
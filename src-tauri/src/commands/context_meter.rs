@@ -0,0 +1,104 @@
+/// Context window accounting from Claude usage reports
+///
+/// Every assistant turn reports the size of the prompt it was sent: `input_tokens` only counts
+/// the uncached tail, the rest of the conversation arrives as `cache_read_input_tokens` /
+/// `cache_creation_input_tokens`. The context a session occupies is therefore the latest turn's
+/// full prompt plus its reply, not a running sum over turns.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader};
+
+use super::claude::{encode_project_path, get_claude_dir};
+
+/// Token breakdown of one assistant turn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextUsage {
+    pub input_tokens: usize,
+    pub cache_creation_tokens: usize,
+    pub cache_read_tokens: usize,
+    pub output_tokens: usize,
+}
+
+impl ContextUsage {
+    /// Parse a `usage` object (stream-json and session JSONL share the format)
+    pub fn from_usage(usage: &serde_json::Value) -> Option<Self> {
+        let field = |name: &str| usage.get(name).and_then(|t| t.as_u64()).unwrap_or(0) as usize;
+        usage.get("input_tokens")?.as_u64()?;
+
+        Some(Self {
+            input_tokens: field("input_tokens"),
+            cache_creation_tokens: field("cache_creation_input_tokens"),
+            cache_read_tokens: field("cache_read_input_tokens"),
+            output_tokens: field("output_tokens"),
+        })
+    }
+
+    /// Full prompt of the turn, cached or not
+    pub fn prompt_tokens(&self) -> usize {
+        self.input_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+
+    /// Tokens in the context window after the turn: the prompt plus the reply,
+    /// which becomes part of the next prompt
+    pub fn context_tokens(&self) -> usize {
+        self.prompt_tokens() + self.output_tokens
+    }
+}
+
+/// Usage of a main-conversation assistant message.
+/// Returns None for other message types, subagent (sidechain) turns that run in their own
+/// context, and synthetic messages without usage.
+pub fn assistant_turn_usage(msg: &serde_json::Value) -> Option<ContextUsage> {
+    if msg.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+        return None;
+    }
+    if msg.get("isSidechain").and_then(|v| v.as_bool()) == Some(true) {
+        return None;
+    }
+    if msg
+        .get("parent_tool_use_id")
+        .map(|v| !v.is_null())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    let usage = ContextUsage::from_usage(msg.get("message")?.get("usage")?)?;
+    (usage.context_tokens() > 0).then_some(usage)
+}
+
+/// Id of the API message an assistant line belongs to. Stream-json emits one line per
+/// content block, all carrying the same id and usage.
+pub fn assistant_message_id(msg: &serde_json::Value) -> Option<&str> {
+    msg.get("message")?.get("id")?.as_str()
+}
+
+fn is_compact_boundary(msg: &serde_json::Value) -> bool {
+    msg.get("type").and_then(|t| t.as_str()) == Some("system")
+        && msg.get("subtype").and_then(|t| t.as_str()) == Some("compact_boundary")
+}
+
+/// Context usage at the end of a stored session, read from its JSONL.
+/// A trailing compaction resets the meter to zero until the next turn reports usage.
+pub fn session_context_from_jsonl(project_path: &str, session_id: &str) -> Option<ContextUsage> {
+    let session_file = get_claude_dir()
+        .ok()?
+        .join("projects")
+        .join(encode_project_path(project_path))
+        .join(format!("{}.jsonl", session_id));
+    let file = fs::File::open(&session_file).ok()?;
+
+    let mut latest = None;
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if is_compact_boundary(&msg) {
+            latest = Some(ContextUsage::default());
+        } else if let Some(usage) = assistant_turn_usage(&msg) {
+            latest = Some(usage);
+        }
+    }
+
+    latest
+}
@@ -8,8 +8,10 @@ use log::{debug, error, info, warn};
 /// - 错误处理和回滚机制
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, State};
 use tokio::process::Command;
 
 /// 扩展的Hook事件类型
@@ -316,58 +318,67 @@ impl HookExecutor {
         Ok(())
     }
 
-    /// 评估条件表达式
+    /// 评估条件表达式（语法见 hook_conditions 模块）
     fn evaluate_condition(&self, condition: &str, context: &HookContext) -> Result<bool, String> {
-        // 简单的条件评估实现
-        // 支持的格式：
-        // - "session_id == 'xyz'"
-        // - "data.tokens > 100000"
-        // - "event == 'OnContextCompact'"
-
-        // 这里使用简单的字符串匹配，未来可以集成更强大的表达式引擎
-        if condition.contains("==") {
-            let parts: Vec<&str> = condition.split("==").collect();
-            if parts.len() == 2 {
-                let left = parts[0].trim();
-                let right = parts[1].trim().trim_matches(|c| c == '\'' || c == '"');
-
-                match left {
-                    "event" => Ok(context.event == right),
-                    "session_id" => Ok(context.session_id == right),
-                    _ => Ok(false),
-                }
-            } else {
-                Ok(false)
-            }
-        } else {
-            // 默认返回true
-            Ok(true)
-        }
+        super::hook_conditions::evaluate_condition(condition, context)
     }
 }
 
 // ============ Hook事件触发器 ============
 
-/// Hook管理器 - 管理hooks的注册和触发，保留用于未来扩展
-#[allow(dead_code)]
+/// 文件变更轮询间隔
+const FILE_WATCH_INTERVAL_SECS: u64 = 2;
+
+/// 单个项目最多跟踪的文件数，超出部分不再检测变更
+const MAX_WATCHED_FILES: usize = 20_000;
+
+/// 项目文件监听状态（同一项目的多个会话共享一个监听任务）
+struct ProjectWatch {
+    sessions: usize,
+    session_id: Arc<Mutex<String>>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Hook管理器 - 作为应用状态管理，从hooks配置加载并自动触发增强事件
+#[derive(Clone)]
 pub struct HookManager {
     executor: Arc<HookExecutor>,
-    registered_hooks: Arc<Mutex<HashMap<String, Vec<EnhancedHook>>>>,
+    watches: Arc<Mutex<HashMap<String, ProjectWatch>>>,
 }
 
-#[allow(dead_code)]
 impl HookManager {
     pub fn new(app: AppHandle) -> Self {
         Self {
             executor: Arc::new(HookExecutor::new(app)),
-            registered_hooks: Arc::new(Mutex::new(HashMap::new())),
+            watches: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// 注册Hook
-    pub fn register_hooks(&self, event: HookEvent, hooks: Vec<EnhancedHook>) {
-        let mut registered = self.registered_hooks.lock().unwrap();
-        registered.insert(event.as_str().to_string(), hooks);
+    /// 从 user、project、local 三个作用域的 settings 中加载某事件的hooks（按此顺序执行）
+    pub async fn load_hooks(&self, event: &HookEvent, project_path: &str) -> Vec<EnhancedHook> {
+        let mut hooks = Vec::new();
+        for scope in ["user", "project", "local"] {
+            let config = match crate::commands::claude::get_hooks_config(
+                scope.to_string(),
+                Some(project_path.to_string()),
+            )
+            .await
+            {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("Failed to load {} hooks config: {}", scope, e);
+                    continue;
+                }
+            };
+            if let Some(entries) = config.get(event.as_str()).and_then(|v| v.as_array()) {
+                hooks.extend(
+                    entries
+                        .iter()
+                        .filter_map(|v| serde_json::from_value::<EnhancedHook>(v.clone()).ok()),
+                );
+            }
+        }
+        hooks
     }
 
     /// 触发Hook事件
@@ -376,10 +387,7 @@ impl HookManager {
         event: HookEvent,
         context: HookContext,
     ) -> Result<HookChainResult, String> {
-        let hooks = {
-            let registered = self.registered_hooks.lock().unwrap();
-            registered.get(event.as_str()).cloned().unwrap_or_default()
-        };
+        let hooks = self.load_hooks(&event, &context.project_path).await;
 
         if hooks.is_empty() {
             debug!("No hooks registered for event: {:?}", event);
@@ -397,6 +405,242 @@ impl HookManager {
             .execute_hook_chain(event, context, hooks)
             .await
     }
+
+    /// 在后台触发事件，不阻塞调用方（会话流、压缩流程等）
+    pub fn fire(
+        &self,
+        event: HookEvent,
+        session_id: &str,
+        project_path: &str,
+        data: serde_json::Value,
+    ) {
+        let manager = self.clone();
+        let context = HookContext {
+            event: event.as_str().to_string(),
+            session_id: session_id.to_string(),
+            project_path: project_path.to_string(),
+            data,
+        };
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = manager.trigger(event, context).await {
+                error!("Failed to run hooks: {}", e);
+            }
+        });
+    }
+
+    /// 开始监听项目文件变更以触发 OnFileChange（按会话计数）
+    pub fn watch_project(&self, project_path: &str, session_id: &str) {
+        let mut watches = match self.watches.lock() {
+            Ok(watches) => watches,
+            Err(_) => return,
+        };
+        if let Some(watch) = watches.get_mut(project_path) {
+            watch.sessions += 1;
+            if let Ok(mut current) = watch.session_id.lock() {
+                *current = session_id.to_string();
+            }
+            return;
+        }
+
+        let watch = ProjectWatch {
+            sessions: 1,
+            session_id: Arc::new(Mutex::new(session_id.to_string())),
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+        let manager = self.clone();
+        let root = PathBuf::from(project_path);
+        let session_id = watch.session_id.clone();
+        let stop = watch.stop.clone();
+        tauri::async_runtime::spawn(async move {
+            manager.run_file_watch(root, session_id, stop).await;
+        });
+        watches.insert(project_path.to_string(), watch);
+        info!("Watching {} for file changes", project_path);
+    }
+
+    /// 会话结束时释放监听，最后一个会话结束后停止
+    pub fn unwatch_project(&self, project_path: &str) {
+        let mut watches = match self.watches.lock() {
+            Ok(watches) => watches,
+            Err(_) => return,
+        };
+        let finished = match watches.get_mut(project_path) {
+            Some(watch) => {
+                watch.sessions = watch.sessions.saturating_sub(1);
+                watch.sessions == 0
+            }
+            None => false,
+        };
+        if finished {
+            if let Some(watch) = watches.remove(project_path) {
+                watch.stop.store(true, Ordering::Relaxed);
+                info!("Stopped watching {} for file changes", project_path);
+            }
+        }
+    }
+
+    /// 轮询项目文件的修改时间，发现变更时批量触发 OnFileChange
+    async fn run_file_watch(
+        &self,
+        root: PathBuf,
+        session_id: Arc<Mutex<String>>,
+        stop: Arc<AtomicBool>,
+    ) {
+        let project_path = root.to_string_lossy().to_string();
+        let mut snapshot: Option<FileSnapshot> = None;
+        let mut hooks: Vec<EnhancedHook> = Vec::new();
+        let mut settings_stamp: Option<SettingsStamp> = None;
+
+        while !stop.load(Ordering::Relaxed) {
+            tokio::time::sleep(tokio::time::Duration::from_secs(FILE_WATCH_INTERVAL_SECS)).await;
+
+            // settings 文件变化时才重新加载 hooks 配置
+            let stamp = hook_settings_stamp(&root);
+            if settings_stamp.as_ref() != Some(&stamp) {
+                hooks = self
+                    .load_hooks(&HookEvent::OnFileChange, &project_path)
+                    .await;
+                settings_stamp = Some(stamp);
+            }
+
+            // 没有配置 OnFileChange hooks 时不扫描
+            if hooks.is_empty() {
+                snapshot = None;
+                continue;
+            }
+
+            let scan_root = root.clone();
+            let current =
+                match tokio::task::spawn_blocking(move || scan_project_files(&scan_root)).await {
+                    Ok(current) => current,
+                    Err(e) => {
+                        error!("File watch scan failed: {}", e);
+                        continue;
+                    }
+                };
+            let previous = snapshot.replace(current);
+            let (previous, current) = match (previous, &snapshot) {
+                (Some(previous), Some(current)) => (previous, current),
+                // 首次扫描只建立基线
+                _ => continue,
+            };
+
+            let changes = diff_snapshots(&previous, current, &root);
+            if changes.is_empty() || stop.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            let files: Vec<serde_json::Value> = changes
+                .iter()
+                .map(|change| change["path"].clone())
+                .collect();
+            let context = HookContext {
+                event: HookEvent::OnFileChange.as_str().to_string(),
+                session_id: session_id.lock().map(|s| s.clone()).unwrap_or_default(),
+                project_path: project_path.clone(),
+                data: serde_json::json!({
+                    "path": files.first().cloned().unwrap_or_default(),
+                    "files": files,
+                    "changes": changes,
+                    "count": changes.len(),
+                }),
+            };
+            if let Err(e) = self
+                .executor
+                .execute_hook_chain(HookEvent::OnFileChange, context, hooks.clone())
+                .await
+            {
+                error!("Failed to run OnFileChange hooks: {}", e);
+            }
+        }
+    }
+}
+
+/// 文件路径 -> (修改时间, 大小)
+type FileSnapshot = HashMap<PathBuf, (std::time::SystemTime, u64)>;
+
+/// user、project、local 三个 settings 文件的 (修改时间, 大小)
+type SettingsStamp = Vec<Option<(std::time::SystemTime, u64)>>;
+
+fn hook_settings_stamp(root: &Path) -> SettingsStamp {
+    let user = crate::commands::claude::get_claude_dir()
+        .ok()
+        .map(|dir| dir.join("settings.json"));
+    [
+        user,
+        Some(root.join(".claude").join("settings.json")),
+        Some(root.join(".claude").join("settings.local.json")),
+    ]
+    .into_iter()
+    .map(|path| {
+        let metadata = std::fs::metadata(path?).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    })
+    .collect()
+}
+
+/// 扫描项目文件，遵循 .gitignore / .ignore 规则（与文件搜索相同），跳过 .git
+fn scan_project_files(root: &Path) -> FileSnapshot {
+    let mut files = HashMap::new();
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .ignore(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .parents(true)
+        .require_git(false)
+        .follow_links(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+    for entry in walker.filter_map(Result::ok) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if files.len() >= MAX_WATCHED_FILES {
+            warn!(
+                "{:?} has more than {} files, ignoring the rest for OnFileChange",
+                root, MAX_WATCHED_FILES
+            );
+            break;
+        }
+        if let Ok(metadata) = entry.metadata() {
+            let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+            files.insert(entry.into_path(), (modified, metadata.len()));
+        }
+    }
+    files
+}
+
+/// 比较两次扫描结果，返回 [{path, kind}]，路径相对项目根目录且使用 '/' 分隔
+fn diff_snapshots(
+    previous: &FileSnapshot,
+    current: &FileSnapshot,
+    root: &Path,
+) -> Vec<serde_json::Value> {
+    let relative = |path: &PathBuf| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+
+    let mut changes = Vec::new();
+    for (path, state) in current {
+        let kind = match previous.get(path) {
+            None => "created",
+            Some(old) if old != state => "modified",
+            Some(_) => continue,
+        };
+        changes.push(serde_json::json!({ "path": relative(path), "kind": kind }));
+    }
+    for path in previous.keys() {
+        if !current.contains_key(path) {
+            changes.push(serde_json::json!({ "path": relative(path), "kind": "deleted" }));
+        }
+    }
+    changes.sort_by(|a, b| a["path"].as_str().cmp(&b["path"].as_str()));
+    changes
 }
 
 // ============ Tauri Commands ============
@@ -404,7 +648,7 @@ impl HookManager {
 /// 触发Hook事件
 #[tauri::command]
 pub async fn trigger_hook_event(
-    app: AppHandle,
+    manager: State<'_, HookManager>,
     event: String,
     context: HookContext,
 ) -> Result<HookChainResult, String> {
@@ -418,27 +662,7 @@ pub async fn trigger_hook_event(
         _ => return Err(format!("Unknown hook event: {}", event)),
     };
 
-    // 从配置中加载hooks
-    let hooks_config = crate::commands::claude::get_hooks_config(
-        "project".to_string(),
-        Some(context.project_path.clone()),
-    )
-    .await?;
-
-    let hooks_array = hooks_config
-        .get(&event)
-        .and_then(|v| v.as_array())
-        .map(|arr| {
-            arr.iter()
-                .filter_map(|v| serde_json::from_value::<EnhancedHook>(v.clone()).ok())
-                .collect()
-        })
-        .unwrap_or_default();
-
-    let executor = HookExecutor::new(app);
-    executor
-        .execute_hook_chain(event_enum, context, hooks_array)
-        .await
+    manager.trigger(event_enum, context).await
 }
 
 /// 测试Hook条件
@@ -491,30 +715,27 @@ impl Default for PreCommitCodeReviewConfig {
 }
 
 /// 提交前代码审查Hook - 智能化自动化场景的具体实现
-#[allow(dead_code)]
 pub struct PreCommitCodeReviewHook {
     config: PreCommitCodeReviewConfig,
-    _app: AppHandle, // 保留用于未来扩展，如通知用户等
+    app: AppHandle,
 }
 
-#[allow(dead_code)]
 impl PreCommitCodeReviewHook {
     pub fn new(app: AppHandle, config: PreCommitCodeReviewConfig) -> Self {
-        Self { config, _app: app }
+        Self { config, app }
     }
 
-    /// 执行提交前代码审查 (Disabled - agent functionality removed)
-    pub async fn execute(&self, _project_path: &str) -> Result<CommitDecision, String> {
-        // Agent functionality removed - always allow commits
-        Ok(CommitDecision::Allow {
-            message: "代码审查功能已禁用 (Agent functionality removed)".to_string(),
-            suggestions: vec![],
-        })
+    /// 执行提交前代码审查：审查暂存区 diff 并根据阈值决定是否允许提交
+    pub async fn execute(&self, project_path: &str) -> Result<CommitDecision, String> {
+        let claude_path = crate::claude_binary::find_claude_binary(&self.app)?;
+        super::pre_commit_review::review_staged_changes(&claude_path, project_path, &self.config)
+            .await
     }
 }
 
 /// 提交决策结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum CommitDecision {
     Allow {
         message: String,
@@ -522,21 +743,18 @@ pub enum CommitDecision {
     },
     Block {
         reason: String,
-        details: String, // Changed from CodeReviewResult - agent functionality removed
+        details: String,
         suggestions: Vec<String>,
     },
 }
 
-/// 执行提交前代码审查Hook (Disabled - agent functionality removed)
+/// 执行提交前代码审查Hook
 #[tauri::command]
 pub async fn execute_pre_commit_review(
-    _app: tauri::AppHandle,
-    _project_path: String,
-    _config: Option<PreCommitCodeReviewConfig>,
+    app: tauri::AppHandle,
+    project_path: String,
+    config: Option<PreCommitCodeReviewConfig>,
 ) -> Result<CommitDecision, String> {
-    // Agent functionality has been removed - return allow decision
-    Ok(CommitDecision::Allow {
-        message: "代码审查功能已禁用 (Agent functionality removed)".to_string(),
-        suggestions: vec![],
-    })
+    let hook = PreCommitCodeReviewHook::new(app, config.unwrap_or_default());
+    hook.execute(&project_path).await
 }
@@ -0,0 +1,713 @@
+//! 文件查找与内容搜索
+//!
+//! - 遵循 `.gitignore` / `.ignore` / 全局 gitignore 的并行目录遍历
+//! - 基于相对路径的 fzf 风格模糊评分（智能大小写、空格分隔多关键词）
+//! - 可选内容搜索模式：返回匹配行，结果通过事件分批推送并支持取消
+
+use ignore::{overrides::OverrideBuilder, WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+/// 默认返回的最大结果数
+const DEFAULT_MAX_RESULTS: usize = 200;
+/// 单次搜索最多遍历的条目数，防止超大仓库拖垮 UI
+const MAX_WALK_ENTRIES: usize = 500_000;
+/// 内容搜索跳过大于该值的文件
+const MAX_CONTENT_FILE_SIZE: u64 = 2 * 1024 * 1024;
+/// 检测二进制文件时读取的字节数
+const BINARY_SNIFF_BYTES: usize = 8192;
+/// 返回的匹配行最大字符数
+const MAX_LINE_CHARS: usize = 400;
+/// 流式推送的批次大小与间隔
+const BATCH_SIZE: usize = 50;
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+
+// fzf 风格评分常量
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+const BONUS_FILENAME: i64 = 12;
+
+/// 搜索模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileSearchMode {
+    /// 按相对路径模糊匹配文件名
+    #[default]
+    Files,
+    /// 搜索文件内容，返回匹配行
+    Content,
+}
+
+/// 搜索选项（均为可选）
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FileSearchOptions {
+    #[serde(default)]
+    pub mode: FileSearchMode,
+    /// 最大结果数
+    pub max_results: Option<usize>,
+    /// 是否包含隐藏文件（`.git` 目录始终排除）
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// 文件模式下是否同时返回目录
+    #[serde(default)]
+    pub include_directories: bool,
+    /// 内容模式下将查询视为正则表达式
+    #[serde(default)]
+    pub regex: bool,
+    /// 强制区分大小写；默认智能大小写（查询含大写字母时区分）
+    pub case_sensitive: Option<bool>,
+    /// 限定搜索范围的 glob，`!` 前缀表示排除，如 `["*.rs", "!target/**"]`
+    #[serde(default)]
+    pub globs: Vec<String>,
+}
+
+/// 模糊匹配到的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct FileMatch {
+    pub path: String,
+    pub relative_path: String,
+    pub name: String,
+    pub is_directory: bool,
+    pub size: u64,
+    pub score: i64,
+    /// `relative_path` 中命中字符的下标（按字符计），用于高亮
+    pub positions: Vec<usize>,
+}
+
+/// 内容搜索匹配到的行
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentMatch {
+    pub path: String,
+    pub relative_path: String,
+    /// 从 1 开始的行号
+    pub line_number: usize,
+    /// 匹配行（过长时截取匹配附近片段）
+    pub line: String,
+    /// `line` 中匹配区间 `[start, end)`（按字符计）
+    pub ranges: Vec<(usize, usize)>,
+}
+
+/// `file-search-results` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSearchBatch {
+    pub search_id: String,
+    pub files: Vec<FileMatch>,
+    pub lines: Vec<ContentMatch>,
+}
+
+/// `file-search-complete` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSearchSummary {
+    pub search_id: String,
+    pub total: usize,
+    pub cancelled: bool,
+    /// 结果达到上限，或遍历条目数达到 `MAX_WALK_ENTRIES` 而提前结束
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+    pub error: Option<String>,
+}
+
+/// `find_files` 的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSearchResult {
+    pub files: Vec<FileMatch>,
+    /// 结果达到上限，或遍历条目数达到 `MAX_WALK_ENTRIES` 而提前结束
+    pub truncated: bool,
+}
+
+/// 一次遍历：结果通道和是否因 `MAX_WALK_ENTRIES` 提前结束
+struct Walk {
+    rx: mpsc::Receiver<Found>,
+    limit_reached: Arc<AtomicBool>,
+}
+
+/// 正在进行的搜索及其取消标记
+#[derive(Default)]
+pub struct FileSearchState(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+// ============ 模糊评分 ============
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    Delimiter,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    if matches!(c, '/' | '\\' | '_' | '-' | '.' | ' ') {
+        CharClass::Delimiter
+    } else if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Digit
+    } else {
+        CharClass::Other
+    }
+}
+
+/// 位置 `i` 的字符作为匹配起点的奖励（单词边界、驼峰、数字）
+fn position_bonus(chars: &[char], i: usize) -> i64 {
+    if i == 0 {
+        return BONUS_BOUNDARY;
+    }
+    let prev = char_class(chars[i - 1]);
+    let curr = char_class(chars[i]);
+    match (prev, curr) {
+        (CharClass::Delimiter, c) if c != CharClass::Delimiter => {
+            if matches!(chars[i - 1], '/' | '\\') {
+                BONUS_BOUNDARY + 1
+            } else {
+                BONUS_BOUNDARY
+            }
+        }
+        (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL,
+        (p, CharClass::Digit) if p != CharClass::Digit => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+fn fold(c: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// 单个关键词的 fzf v1 风格匹配：先正向找到最早的完整匹配，再反向收紧区间，
+/// 最后在区间内计算得分与命中位置
+fn score_term(term: &[char], text: &[char], case_sensitive: bool) -> Option<(i64, Vec<usize>)> {
+    if term.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    // 正向扫描
+    let mut ti = 0;
+    let mut end = None;
+    for (i, c) in text.iter().enumerate() {
+        if fold(*c, case_sensitive) == term[ti] {
+            ti += 1;
+            if ti == term.len() {
+                end = Some(i + 1);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    // 反向收紧
+    let mut ti = term.len();
+    let mut start = 0;
+    for i in (0..end).rev() {
+        if fold(text[i], case_sensitive) == term[ti - 1] {
+            ti -= 1;
+            if ti == 0 {
+                start = i;
+                break;
+            }
+        }
+    }
+
+    // 区间内计分
+    let mut score = 0i64;
+    let mut positions = Vec::with_capacity(term.len());
+    let mut ti = 0;
+    let mut in_gap = false;
+    let mut consecutive = 0i64;
+    let mut first_bonus = 0i64;
+    for (i, c) in text.iter().enumerate().take(end).skip(start) {
+        if ti < term.len() && fold(*c, case_sensitive) == term[ti] {
+            let mut bonus = position_bonus(text, i);
+            if consecutive == 0 {
+                first_bonus = bonus;
+            } else {
+                // 连续匹配沿用片段首字符的奖励
+                bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+            }
+            if ti == 0 {
+                bonus *= BONUS_FIRST_CHAR_MULTIPLIER;
+            }
+            score += SCORE_MATCH + bonus;
+            positions.push(i);
+            consecutive += 1;
+            in_gap = false;
+            ti += 1;
+        } else {
+            score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            consecutive = 0;
+        }
+    }
+
+    Some((score, positions))
+}
+
+/// 对相对路径进行模糊评分；查询按空白拆分为多个关键词，全部命中才算匹配
+pub fn fuzzy_score(query: &str, path: &str, case_sensitive: bool) -> Option<(i64, Vec<usize>)> {
+    let text: Vec<char> = path.chars().collect();
+    let name_start = text
+        .iter()
+        .rposition(|c| matches!(c, '/' | '\\'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    let mut total = 0i64;
+    let mut positions = Vec::new();
+    for term in query.split_whitespace() {
+        let term: Vec<char> = term.chars().map(|c| fold(c, case_sensitive)).collect();
+        let (score, term_positions) = score_term(&term, &text, case_sensitive)?;
+        total += score;
+        // 命中全部落在文件名部分时额外加分
+        if term_positions.first().is_some_and(|p| *p >= name_start) {
+            total += BONUS_FILENAME;
+        }
+        positions.extend(term_positions);
+    }
+
+    positions.sort_unstable();
+    positions.dedup();
+    Some((total, positions))
+}
+
+fn smart_case(query: &str, options: &FileSearchOptions) -> bool {
+    options
+        .case_sensitive
+        .unwrap_or_else(|| query.chars().any(|c| c.is_uppercase()))
+}
+
+// ============ 目录遍历 ============
+
+fn relative_path(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// 构造遵循 ignore 规则的并行遍历器
+fn build_walker(base: &Path, options: &FileSearchOptions) -> Result<ignore::WalkParallel, String> {
+    let mut builder = WalkBuilder::new(base);
+    builder
+        .hidden(!options.include_hidden)
+        .ignore(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .parents(true)
+        .require_git(false)
+        .follow_links(false)
+        .filter_entry(|entry| entry.file_name() != ".git");
+
+    if !options.globs.is_empty() {
+        let mut overrides = OverrideBuilder::new(base);
+        for glob in &options.globs {
+            overrides
+                .add(glob)
+                .map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+        }
+        builder.overrides(
+            overrides
+                .build()
+                .map_err(|e| format!("Invalid globs: {}", e))?,
+        );
+    }
+
+    Ok(builder.build_parallel())
+}
+
+/// 遍历中发现的一条结果
+enum Found {
+    File(FileMatch),
+    Line(ContentMatch),
+}
+
+/// 读取文本文件内容；过大或二进制文件返回 None
+fn read_text_file(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if metadata.len() > MAX_CONTENT_FILE_SIZE {
+        return None;
+    }
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut bytes = Vec::with_capacity(metadata.len() as usize);
+    file.read_to_end(&mut bytes).ok()?;
+    if bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// 把匹配行裁剪到 `MAX_LINE_CHARS` 并换算匹配区间为字符下标
+fn clip_line(line: &str, byte_ranges: &[(usize, usize)]) -> (String, Vec<(usize, usize)>) {
+    let char_ranges: Vec<(usize, usize)> = byte_ranges
+        .iter()
+        .map(|(s, e)| (line[..*s].chars().count(), line[..*e].chars().count()))
+        .collect();
+    let total_chars = line.chars().count();
+    if total_chars <= MAX_LINE_CHARS {
+        return (line.to_string(), char_ranges);
+    }
+
+    let first = char_ranges.first().map(|r| r.0).unwrap_or(0);
+    let start = first.saturating_sub(MAX_LINE_CHARS / 4);
+    let end = (start + MAX_LINE_CHARS).min(total_chars);
+    let clipped: String = line.chars().skip(start).take(end - start).collect();
+    let ranges = char_ranges
+        .into_iter()
+        .filter(|(s, _)| *s >= start && *s < end)
+        .map(|(s, e)| (s - start, e.min(end) - start))
+        .collect();
+    (clipped, ranges)
+}
+
+/// 在一个文件中搜索匹配行
+fn grep_file(base: &Path, path: &Path, pattern: &regex::Regex, tx: &mpsc::Sender<Found>) -> bool {
+    let content = match read_text_file(path) {
+        Some(content) => content,
+        None => return true,
+    };
+    let relative = relative_path(base, path);
+    for (index, line) in content.lines().enumerate() {
+        let byte_ranges: Vec<(usize, usize)> = pattern
+            .find_iter(line)
+            .filter(|m| m.start() < m.end())
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        if byte_ranges.is_empty() {
+            continue;
+        }
+        let (line, ranges) = clip_line(line, &byte_ranges);
+        let found = Found::Line(ContentMatch {
+            path: path.to_string_lossy().to_string(),
+            relative_path: relative.clone(),
+            line_number: index + 1,
+            line,
+            ranges,
+        });
+        if tx.send(found).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// 启动并行遍历线程，结果通过 channel 返回
+fn spawn_walk(
+    base: PathBuf,
+    query: String,
+    options: FileSearchOptions,
+    cancel: Arc<AtomicBool>,
+) -> Result<Walk, String> {
+    let walker = build_walker(&base, &options)?;
+    let case_sensitive = smart_case(&query, &options);
+
+    let pattern = if options.mode == FileSearchMode::Content {
+        let source = if options.regex {
+            query.clone()
+        } else {
+            regex::escape(&query)
+        };
+        Some(
+            regex::RegexBuilder::new(&source)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .map_err(|e| format!("Invalid search pattern: {}", e))?,
+        )
+    } else {
+        None
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let walked = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let limit_reached = Arc::new(AtomicBool::new(false));
+    let walk_limit_reached = limit_reached.clone();
+
+    std::thread::spawn(move || {
+        walker.run(|| {
+            let tx = tx.clone();
+            let base = base.clone();
+            let query = query.clone();
+            let pattern = pattern.clone();
+            let cancel = cancel.clone();
+            let walked = walked.clone();
+            let limit_reached = walk_limit_reached.clone();
+            let include_directories = options.include_directories;
+
+            Box::new(move |entry| {
+                if cancel.load(Ordering::Relaxed) {
+                    return WalkState::Quit;
+                }
+                if walked.fetch_add(1, Ordering::Relaxed) >= MAX_WALK_ENTRIES {
+                    if !limit_reached.swap(true, Ordering::Relaxed) {
+                        log::warn!(
+                            "File search stopped after {} entries, results are partial",
+                            MAX_WALK_ENTRIES
+                        );
+                    }
+                    return WalkState::Quit;
+                }
+                // 不可读的目录/文件直接跳过，不中断整个搜索
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        log::debug!("Skipping unreadable entry: {}", e);
+                        return WalkState::Continue;
+                    }
+                };
+                if entry.depth() == 0 {
+                    return WalkState::Continue;
+                }
+                let is_directory = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+
+                if let Some(pattern) = &pattern {
+                    if !is_directory && !grep_file(&base, entry.path(), pattern, &tx) {
+                        return WalkState::Quit;
+                    }
+                    return WalkState::Continue;
+                }
+
+                if is_directory && !include_directories {
+                    return WalkState::Continue;
+                }
+                let relative = relative_path(&base, entry.path());
+                if let Some((score, positions)) = fuzzy_score(&query, &relative, case_sensitive) {
+                    let found = Found::File(FileMatch {
+                        path: entry.path().to_string_lossy().to_string(),
+                        name: entry.file_name().to_string_lossy().to_string(),
+                        relative_path: relative,
+                        is_directory,
+                        size: if is_directory {
+                            0
+                        } else {
+                            entry.metadata().map(|m| m.len()).unwrap_or(0)
+                        },
+                        score,
+                        positions,
+                    });
+                    if tx.send(found).is_err() {
+                        return WalkState::Quit;
+                    }
+                }
+                WalkState::Continue
+            })
+        });
+    });
+
+    Ok(Walk { rx, limit_reached })
+}
+
+/// 按得分降序、路径长度升序排序并截断
+fn rank_files(files: &mut Vec<FileMatch>, max_results: usize) {
+    files.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.relative_path.len().cmp(&b.relative_path.len()))
+            .then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+    files.truncate(max_results);
+}
+
+/// 同步执行模糊文件查找（阻塞，需在 blocking 线程中调用）
+pub fn find_files(
+    base_path: &str,
+    query: &str,
+    options: FileSearchOptions,
+) -> Result<FileSearchResult, String> {
+    let base = PathBuf::from(base_path);
+    if !base.is_dir() {
+        return Err(format!("Path does not exist: {}", base_path));
+    }
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let options = FileSearchOptions {
+        mode: FileSearchMode::Files,
+        ..options
+    };
+
+    let walk = spawn_walk(
+        base,
+        query.to_string(),
+        options,
+        Arc::new(AtomicBool::new(false)),
+    )?;
+    let mut files: Vec<FileMatch> = walk
+        .rx
+        .into_iter()
+        .filter_map(|found| match found {
+            Found::File(file) => Some(file),
+            Found::Line(_) => None,
+        })
+        .collect();
+    let truncated = files.len() > max_results || walk.limit_reached.load(Ordering::Relaxed);
+    rank_files(&mut files, max_results);
+    Ok(FileSearchResult { files, truncated })
+}
+
+/// 执行一次流式搜索：内容模式边搜边推送，文件模式在遍历结束后推送排序结果
+fn run_streaming_search(
+    app: &AppHandle,
+    search_id: &str,
+    base: PathBuf,
+    query: String,
+    options: FileSearchOptions,
+    cancel: Arc<AtomicBool>,
+) -> Result<(usize, bool), String> {
+    let mode = options.mode;
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    let Walk { rx, limit_reached } = spawn_walk(base, query, options, cancel.clone())?;
+
+    let emit = |files: Vec<FileMatch>, lines: Vec<ContentMatch>| {
+        let _ = app.emit(
+            "file-search-results",
+            FileSearchBatch {
+                search_id: search_id.to_string(),
+                files,
+                lines,
+            },
+        );
+    };
+
+    if mode == FileSearchMode::Files {
+        let mut files = Vec::new();
+        for found in rx {
+            if let Found::File(file) = found {
+                files.push(file);
+            }
+        }
+        if cancel.load(Ordering::Relaxed) {
+            return Ok((0, false));
+        }
+        let truncated = files.len() > max_results || limit_reached.load(Ordering::Relaxed);
+        rank_files(&mut files, max_results);
+        let total = files.len();
+        emit(files, Vec::new());
+        return Ok((total, truncated));
+    }
+
+    let mut total = 0usize;
+    let mut truncated = false;
+    let mut pending = Vec::new();
+    let mut last_flush = Instant::now();
+    loop {
+        match rx.recv_timeout(BATCH_INTERVAL) {
+            Ok(Found::Line(line)) => {
+                if total >= max_results {
+                    // 达到上限后停止遍历
+                    truncated = true;
+                    cancel.store(true, Ordering::Relaxed);
+                    break;
+                }
+                total += 1;
+                pending.push(line);
+            }
+            Ok(Found::File(_)) => {}
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        if !pending.is_empty()
+            && (pending.len() >= BATCH_SIZE || last_flush.elapsed() >= BATCH_INTERVAL)
+        {
+            emit(Vec::new(), std::mem::take(&mut pending));
+            last_flush = Instant::now();
+        }
+    }
+    if !pending.is_empty() {
+        emit(Vec::new(), pending);
+    }
+    truncated |= limit_reached.load(Ordering::Relaxed);
+
+    Ok((total, truncated))
+}
+
+/// 开始一次可取消的流式搜索，立即返回 search_id。
+/// 结果通过 `file-search-results` 分批推送，结束时发送 `file-search-complete`。
+#[tauri::command]
+pub async fn start_file_search(
+    app: AppHandle,
+    state: State<'_, FileSearchState>,
+    base_path: String,
+    query: String,
+    options: Option<FileSearchOptions>,
+) -> Result<String, String> {
+    let base = PathBuf::from(&base_path);
+    if !base.is_dir() {
+        return Err(format!("Path does not exist: {}", base_path));
+    }
+    if query.trim().is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+
+    let search_id = uuid::Uuid::new_v4().to_string();
+    let cancel = Arc::new(AtomicBool::new(false));
+    state
+        .0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(search_id.clone(), cancel.clone());
+
+    let id = search_id.clone();
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let started = Instant::now();
+        let result = run_streaming_search(&app, &id, base, query, options, cancel.clone());
+
+        if let Some(state) = tauri::Manager::try_state::<FileSearchState>(&app) {
+            if let Ok(mut searches) = state.0.lock() {
+                searches.remove(&id);
+            }
+        }
+
+        let (total, truncated, error) = match result {
+            Ok((total, truncated)) => (total, truncated, None),
+            Err(e) => (0, false, Some(e)),
+        };
+        let _ = app.emit(
+            "file-search-complete",
+            FileSearchSummary {
+                search_id: id,
+                total,
+                cancelled: cancel.load(Ordering::Relaxed) && !truncated,
+                truncated,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+                error,
+            },
+        );
+    });
+
+    Ok(search_id)
+}
+
+/// 取消正在进行的搜索
+#[tauri::command]
+pub async fn cancel_file_search(
+    state: State<'_, FileSearchState>,
+    search_id: String,
+) -> Result<bool, String> {
+    let searches = state.0.lock().map_err(|e| e.to_string())?;
+    match searches.get(&search_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::Relaxed);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::process::Command as StdCommand;
+use tauri::AppHandle;
+
+use super::checkpoint;
+use super::prompt_tracker::get_git_record;
+use super::simple_git::{self, GitRepo};
 
 /// Git 代码变更统计
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,7 +30,7 @@ pub async fn get_git_diff_stats(
     // 使用 git diff --numstat 获取统计
     let mut cmd = StdCommand::new("git");
     cmd.current_dir(&project_path);
-    cmd.args(&["diff", "--numstat", &from_commit, &to_ref]);
+    cmd.args(["diff", "--numstat", &from_commit, &to_ref]);
 
     #[cfg(target_os = "windows")]
     {
@@ -84,3 +89,592 @@ pub async fn get_session_code_changes(
 ) -> Result<GitDiffStats, String> {
     get_git_diff_stats(project_path, session_start_commit, None).await
 }
+
+/// 单个文件 patch 超过该行数时省略 hunks（只保留统计）
+const MAX_PATCH_LINES_PER_FILE: usize = 5000;
+
+/// 词级 diff 的单行 token 上限，超过则整行视为变更
+const MAX_WORD_DIFF_TOKENS: usize = 400;
+
+/// 文件变更类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+/// diff 行类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// 行内词级片段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordSegment {
+    pub text: String,
+    /// 该片段是否为本行相对配对行的变更部分
+    pub changed: bool,
+}
+
+/// diff 中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub content: String,
+    /// 旧文件中的行号（新增行为 None）
+    pub old_line: Option<u32>,
+    /// 新文件中的行号（删除行为 None）
+    pub new_line: Option<u32>,
+    /// 词级 diff（仅在开启且该行有配对的删除/新增行时存在）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<WordSegment>>,
+}
+
+/// 一个 hunk（@@ -a,b +c,d @@）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// @@ 行中的函数/上下文提示
+    pub header: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// 单个文件的变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub path: String,
+    /// 重命名/复制前的路径
+    pub old_path: Option<String>,
+    pub status: FileChangeStatus,
+    pub binary: bool,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// patch 过大时为 true，此时 hunks 为空
+    pub truncated: bool,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// 文件级 diff 选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileDiffOptions {
+    /// 上下文行数（默认 3）
+    pub context_lines: Option<u32>,
+    /// 是否计算行内词级 diff
+    pub word_diff: bool,
+    /// 只返回这些路径
+    pub paths: Option<Vec<String>>,
+}
+
+/// 某个提示词的文件级变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptFileDiffs {
+    pub prompt_index: usize,
+    pub from_commit: String,
+    /// 提示词尚未完成时为 None（与当前工作区比较）
+    pub to_commit: Option<String>,
+    pub files: Vec<FileDiff>,
+}
+
+/// 解析 `git diff --name-status -z` 输出
+fn parse_name_status(output: &str) -> Vec<(FileChangeStatus, Option<String>, String)> {
+    let mut entries = Vec::new();
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+
+    while let Some(code) = fields.next() {
+        let status = match code.chars().next() {
+            Some('A') => FileChangeStatus::Added,
+            Some('D') => FileChangeStatus::Deleted,
+            Some('R') => FileChangeStatus::Renamed,
+            Some('C') => FileChangeStatus::Copied,
+            Some('T') => FileChangeStatus::TypeChanged,
+            _ => FileChangeStatus::Modified,
+        };
+        let Some(first) = fields.next() else { break };
+        if matches!(status, FileChangeStatus::Renamed | FileChangeStatus::Copied) {
+            let Some(second) = fields.next() else { break };
+            entries.push((status, Some(first.to_string()), second.to_string()));
+        } else {
+            entries.push((status, None, first.to_string()));
+        }
+    }
+
+    entries
+}
+
+/// 解析 "@@ -a,b +c,d @@ header"
+fn parse_hunk_header(line: &str) -> Option<DiffHunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, header) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+
+    let parse_range = |range: &str| -> Option<(u32, u32)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = parse_range(old)?;
+    let (new_start, new_lines) = parse_range(new)?;
+
+    Some(DiffHunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        header: header.trim().to_string(),
+        lines: Vec::new(),
+    })
+}
+
+/// 解析单个文件的 patch 片段（从 "diff --git" 开始）
+fn parse_file_patch(patch: &str, file: &mut FileDiff) {
+    let mut hunk: Option<DiffHunk> = None;
+    let (mut old_no, mut new_no) = (0u32, 0u32);
+    let mut line_count = 0usize;
+
+    for line in patch.lines() {
+        if line.starts_with("@@ ") {
+            if let Some(done) = hunk.take() {
+                file.hunks.push(done);
+            }
+            hunk = parse_hunk_header(line);
+            if let Some(h) = &hunk {
+                old_no = h.old_start;
+                new_no = h.new_start;
+            }
+            continue;
+        }
+
+        let Some(current) = hunk.as_mut() else {
+            // 文件头部分
+            if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                file.binary = true;
+            }
+            continue;
+        };
+
+        let (kind, content) = match line.chars().next() {
+            Some('+') => (DiffLineKind::Added, &line[1..]),
+            Some('-') => (DiffLineKind::Removed, &line[1..]),
+            Some(' ') => (DiffLineKind::Context, &line[1..]),
+            // "\ No newline at end of file"
+            Some('\\') => continue,
+            None => (DiffLineKind::Context, ""),
+            _ => continue,
+        };
+
+        let (old_line, new_line) = match kind {
+            DiffLineKind::Added => {
+                file.lines_added += 1;
+                new_no += 1;
+                (None, Some(new_no - 1))
+            }
+            DiffLineKind::Removed => {
+                file.lines_removed += 1;
+                old_no += 1;
+                (Some(old_no - 1), None)
+            }
+            DiffLineKind::Context => {
+                old_no += 1;
+                new_no += 1;
+                (Some(old_no - 1), Some(new_no - 1))
+            }
+        };
+
+        line_count += 1;
+        if line_count <= MAX_PATCH_LINES_PER_FILE {
+            current.lines.push(DiffLine {
+                kind,
+                content: content.to_string(),
+                old_line,
+                new_line,
+                segments: None,
+            });
+        }
+    }
+
+    if let Some(done) = hunk.take() {
+        file.hunks.push(done);
+    }
+
+    if line_count > MAX_PATCH_LINES_PER_FILE {
+        file.truncated = true;
+        file.hunks.clear();
+    }
+}
+
+/// 拆分为 token：连续的字母数字/下划线、连续空白、或单个其他字符
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<u8> = None;
+
+    for (i, c) in line.char_indices() {
+        let class = if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        };
+        if i > start && (class == 2 || prev != Some(class)) {
+            tokens.push(&line[start..i]);
+            start = i;
+        }
+        prev = Some(class);
+    }
+    if start < line.len() {
+        tokens.push(&line[start..]);
+    }
+
+    tokens
+}
+
+/// 合并相邻同类片段
+fn push_segment(segments: &mut Vec<WordSegment>, text: &str, changed: bool) {
+    match segments.last_mut() {
+        Some(last) if last.changed == changed => last.text.push_str(text),
+        _ => segments.push(WordSegment {
+            text: text.to_string(),
+            changed,
+        }),
+    }
+}
+
+/// 基于 LCS 的词级 diff，返回 (删除行片段, 新增行片段)
+fn word_diff(old: &str, new: &str) -> (Vec<WordSegment>, Vec<WordSegment>) {
+    let a = tokenize(old);
+    let b = tokenize(new);
+
+    if a.len() > MAX_WORD_DIFF_TOKENS || b.len() > MAX_WORD_DIFF_TOKENS {
+        return (
+            vec![WordSegment {
+                text: old.to_string(),
+                changed: true,
+            }],
+            vec![WordSegment {
+                text: new.to_string(),
+                changed: true,
+            }],
+        );
+    }
+
+    // lcs[i][j] = a[i..] 与 b[j..] 的 LCS 长度
+    let mut lcs = vec![vec![0u16; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut old_segments, mut new_segments) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            push_segment(&mut old_segments, a[i], false);
+            push_segment(&mut new_segments, b[j], false);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            push_segment(&mut old_segments, a[i], true);
+            i += 1;
+        } else {
+            push_segment(&mut new_segments, b[j], true);
+            j += 1;
+        }
+    }
+    for token in &a[i..] {
+        push_segment(&mut old_segments, token, true);
+    }
+    for token in &b[j..] {
+        push_segment(&mut new_segments, token, true);
+    }
+
+    (old_segments, new_segments)
+}
+
+/// 为 hunk 中相邻的 删除块+新增块 按行配对计算词级 diff
+fn apply_word_diff(hunk: &mut DiffHunk) {
+    let lines = &mut hunk.lines;
+    let mut i = 0;
+
+    while i < lines.len() {
+        if lines[i].kind != DiffLineKind::Removed {
+            i += 1;
+            continue;
+        }
+
+        let removed_start = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Removed {
+            i += 1;
+        }
+        let added_start = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Added {
+            i += 1;
+        }
+
+        let pairs = (added_start - removed_start).min(i - added_start);
+        for k in 0..pairs {
+            let (old_segments, new_segments) = word_diff(
+                &lines[removed_start + k].content,
+                &lines[added_start + k].content,
+            );
+            lines[removed_start + k].segments = Some(old_segments);
+            lines[added_start + k].segments = Some(new_segments);
+        }
+    }
+}
+
+/// 计算两个 commit 之间的文件级 diff
+pub(crate) fn collect_file_diffs(
+    repo: &GitRepo,
+    from_commit: &str,
+    to_commit: &str,
+    options: &FileDiffOptions,
+) -> Result<Vec<FileDiff>, String> {
+    let context = format!("-U{}", options.context_lines.unwrap_or(3));
+    let paths = options.paths.clone().unwrap_or_default();
+
+    let mut status_args = vec![
+        "-c",
+        "core.quotePath=false",
+        "diff",
+        "--name-status",
+        "-z",
+        "-M",
+        from_commit,
+        to_commit,
+        "--",
+    ];
+    status_args.extend(paths.iter().map(|p| p.as_str()));
+    let entries = parse_name_status(&simple_git::git_output_raw(repo, &status_args, &[])?);
+
+    let mut patch_args = vec![
+        "-c",
+        "core.quotePath=false",
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+        "-M",
+        context.as_str(),
+        from_commit,
+        to_commit,
+        "--",
+    ];
+    patch_args.extend(paths.iter().map(|p| p.as_str()));
+    let patch = simple_git::git_output_raw(repo, &patch_args, &[])?;
+
+    // patch 与 name-status 的文件顺序一致，按顺序配对
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("diff --git ") && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    let mut files = Vec::with_capacity(entries.len());
+    for (index, (status, old_path, path)) in entries.into_iter().enumerate() {
+        let mut file = FileDiff {
+            path,
+            old_path,
+            status,
+            binary: false,
+            lines_added: 0,
+            lines_removed: 0,
+            truncated: false,
+            hunks: Vec::new(),
+        };
+
+        if let Some(chunk) = chunks.get(index) {
+            parse_file_patch(chunk, &mut file);
+        }
+        if options.word_diff {
+            file.hunks.iter_mut().for_each(apply_word_diff);
+        }
+
+        files.push(file);
+    }
+
+    Ok(files)
+}
+
+/// 某个文件 patch 中各 hunk 的原始文本（顺序与 collect_file_diffs 的 hunks 一致）
+/// `paths` 需包含重命名前后的两个路径，才能与完整 diff 的重命名检测结果一致
+pub(crate) fn raw_file_hunks(
+    repo: &GitRepo,
+    from_commit: &str,
+    to_commit: &str,
+    paths: &[&str],
+    context_lines: Option<u32>,
+) -> Result<Vec<String>, String> {
+    let context = format!("-U{}", context_lines.unwrap_or(3));
+    let mut args = vec![
+        "-c",
+        "core.quotePath=false",
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "-M",
+        context.as_str(),
+        from_commit,
+        to_commit,
+        "--",
+    ];
+    let pathspecs: Vec<String> = paths
+        .iter()
+        .map(|p| format!(":(top,literal){}", p))
+        .collect();
+    args.extend(pathspecs.iter().map(|p| p.as_str()));
+    let patch = simple_git::git_output_raw(repo, &args, &[])?;
+
+    let mut hunks: Vec<String> = Vec::new();
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("@@ ") {
+            hunks.push(line.to_string());
+        } else if line.starts_with("diff --git ") && !hunks.is_empty() {
+            break;
+        } else if let Some(hunk) = hunks.last_mut() {
+            hunk.push_str(line);
+        }
+    }
+
+    Ok(hunks)
+}
+
+/// 单个文件的增删行数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChangeStat {
+    pub path: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// 二进制文件没有行数统计
+    pub binary: bool,
+}
+
+/// 两个 commit 之间每个文件的增删行数（`git diff --numstat -z -M`）
+pub(crate) fn collect_numstat(
+    repo: &GitRepo,
+    from_commit: &str,
+    to_commit: &str,
+) -> Result<Vec<FileChangeStat>, String> {
+    let output = simple_git::git_output_raw(
+        repo,
+        &["diff", "--numstat", "-z", "-M", from_commit, to_commit],
+        &[],
+    )?;
+
+    // 格式：<added>\t<removed>\t<path>\0，重命名为 <added>\t<removed>\t\0<old>\0<new>\0
+    let mut stats = Vec::new();
+    let mut fields = output.split('\0');
+    while let Some(field) = fields.next() {
+        let mut parts = field.splitn(3, '\t');
+        let (Some(added), Some(removed), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let path = if path.is_empty() {
+            // 重命名：跳过旧路径，取新路径
+            fields.next();
+            match fields.next() {
+                Some(new_path) => new_path,
+                None => break,
+            }
+        } else {
+            path
+        };
+
+        stats.push(FileChangeStat {
+            path: path.to_string(),
+            lines_added: added.parse().unwrap_or(0),
+            lines_removed: removed.parse().unwrap_or(0),
+            binary: added == "-",
+        });
+    }
+
+    Ok(stats)
+}
+
+/// 获取两个 commit 之间每个文件的 hunks（项目自身的 Git 仓库）
+#[tauri::command]
+pub async fn get_git_file_diffs(
+    project_path: String,
+    from_commit: String,
+    to_commit: Option<String>,
+    options: Option<FileDiffOptions>,
+) -> Result<Vec<FileDiff>, String> {
+    let to_ref = to_commit.unwrap_or_else(|| "HEAD".to_string());
+    collect_file_diffs(
+        &GitRepo::project(&project_path),
+        &from_commit,
+        &to_ref,
+        &options.unwrap_or_default(),
+    )
+}
+
+/// 获取某个提示词（commit_before → commit_after）改动的文件及 hunks
+#[tauri::command]
+pub async fn get_prompt_file_diffs(
+    app: AppHandle,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    prompt_index: usize,
+    options: Option<FileDiffOptions>,
+) -> Result<PromptFileDiffs, String> {
+    let record = get_git_record(&session_id, &project_id, prompt_index)
+        .map_err(|e| format!("Failed to get git record: {}", e))?
+        .ok_or_else(|| format!("提示词 #{} 没有关联的 Git 记录", prompt_index))?;
+
+    // 检查点所在的仓库（项目仓库或影子仓库）
+    let repo = checkpoint::open_repo(&app, &project_path, record.backend)?;
+
+    // 提示词尚未完成时与当前工作区（含未跟踪文件）的快照比较
+    let to_ref = match &record.commit_after {
+        Some(commit) => commit.clone(),
+        None => simple_git::git_snapshot_worktree(&repo, "Workbench diff snapshot")?,
+    };
+
+    let files = collect_file_diffs(
+        &repo,
+        &record.commit_before,
+        &to_ref,
+        &options.unwrap_or_default(),
+    )?;
+
+    Ok(PromptFileDiffs {
+        prompt_index,
+        from_commit: record.commit_before,
+        to_commit: record.commit_after,
+        files,
+    })
+}
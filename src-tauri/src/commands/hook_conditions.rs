@@ -0,0 +1,607 @@
+//! Hook条件表达式引擎
+//!
+//! 条件在 `HookContext` 上求值，支持：
+//! - 变量：`event`、`session_id`、`project_path`，以及 `data` 下的点路径（`data.files[0].path`）
+//! - 比较：`==` `!=` `>` `>=` `<` `<=`
+//! - 逻辑：`&&` `||` `!` 与括号
+//! - 字符串/集合：`contains`、`matches`（正则）、`glob`（文件路径通配）
+//!
+//! 例如：`event == 'OnFileChange' && data.path glob 'src/**/*.rs'`
+
+use serde_json::Value;
+use std::fmt;
+
+use super::enhanced_hooks::HookContext;
+
+/// Parse or evaluation error with the character position it refers to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionError {
+    pub message: String,
+    /// 0-based character offset into the expression
+    pub position: usize,
+}
+
+impl ConditionError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+
+    /// Formats the error with the expression and a caret under the offending column
+    pub fn describe(&self, input: &str) -> String {
+        format!(
+            "{} at column {}\n  {}\n  {}^",
+            self.message,
+            self.position + 1,
+            input,
+            " ".repeat(self.position)
+        )
+    }
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.position + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+    Matches,
+    Glob,
+}
+
+impl CompareOp {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Contains => "contains",
+            CompareOp::Matches => "matches",
+            CompareOp::Glob => "glob",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    /// Variable path; the first segment is the root (event, session_id, project_path, data)
+    Path {
+        segments: Vec<PathSegment>,
+        position: usize,
+    },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare {
+        left: Box<Expr>,
+        op: CompareOp,
+        right: Box<Expr>,
+        position: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Dot,
+    Not,
+    And,
+    Or,
+    Op(CompareOp),
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(name) => format!("'{}'", name),
+            Token::Str(s) => format!("string '{}'", s),
+            Token::Num(n) => format!("number {}", n),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::LBracket => "'['".to_string(),
+            Token::RBracket => "']'".to_string(),
+            Token::Dot => "'.'".to_string(),
+            Token::Not => "'!'".to_string(),
+            Token::And => "'&&'".to_string(),
+            Token::Or => "'||'".to_string(),
+            Token::Op(op) => format!("'{}'", op.as_str()),
+            Token::End => "end of expression".to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ConditionError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let token = match (c, next) {
+            ('(', _) => Token::LParen,
+            (')', _) => Token::RParen,
+            ('[', _) => Token::LBracket,
+            (']', _) => Token::RBracket,
+            ('.', _) => Token::Dot,
+            ('&', Some('&')) => Token::And,
+            ('|', Some('|')) => Token::Or,
+            ('=', Some('=')) => Token::Op(CompareOp::Eq),
+            ('!', Some('=')) => Token::Op(CompareOp::Ne),
+            ('>', Some('=')) => Token::Op(CompareOp::Ge),
+            ('<', Some('=')) => Token::Op(CompareOp::Le),
+            ('!', _) => Token::Not,
+            ('>', _) => Token::Op(CompareOp::Gt),
+            ('<', _) => Token::Op(CompareOp::Lt),
+            ('=', _) => {
+                return Err(ConditionError::new(
+                    "Unexpected '=' (use '==' to compare)",
+                    start,
+                ))
+            }
+            ('&', _) => return Err(ConditionError::new("Unexpected '&' (use '&&')", start)),
+            ('|', _) => return Err(ConditionError::new("Unexpected '|' (use '||')", start)),
+            ('\'' | '"', _) => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(ConditionError::new("Unterminated string", start)),
+                        Some('\\') => {
+                            match chars.get(i + 1) {
+                                Some(escaped) => value.push(*escaped),
+                                None => {
+                                    return Err(ConditionError::new("Unterminated string", start))
+                                }
+                            }
+                            i += 2;
+                        }
+                        Some(ch) if *ch == quote => break,
+                        Some(ch) => {
+                            value.push(*ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push((Token::Str(value), start));
+                i += 1;
+                continue;
+            }
+            _ if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit())) => {
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let number = text.parse::<f64>().map_err(|_| {
+                    ConditionError::new(format!("Invalid number '{}'", text), start)
+                })?;
+                tokens.push((Token::Num(number), start));
+                continue;
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let token = match word.as_str() {
+                    "contains" => Token::Op(CompareOp::Contains),
+                    "matches" => Token::Op(CompareOp::Matches),
+                    "glob" => Token::Op(CompareOp::Glob),
+                    _ => Token::Ident(word),
+                };
+                tokens.push((token, start));
+                continue;
+            }
+            _ => {
+                return Err(ConditionError::new(
+                    format!("Unexpected character '{}'", c),
+                    start,
+                ))
+            }
+        };
+
+        i += match token {
+            Token::And | Token::Or | Token::Op(CompareOp::Eq | CompareOp::Ne) => 2,
+            Token::Op(CompareOp::Ge | CompareOp::Le) => 2,
+            _ => 1,
+        };
+        tokens.push((token, start));
+    }
+
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.pos].1
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.pos].clone();
+        if self.pos + 1 < self.tokens.len() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ConditionError> {
+        if *self.peek() == expected {
+            self.advance();
+            Ok(())
+        } else {
+            Err(ConditionError::new(
+                format!(
+                    "Expected {} but found {}",
+                    expected.describe(),
+                    self.peek().describe()
+                ),
+                self.position(),
+            ))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ConditionError> {
+        let mut left = self.parse_and()?;
+        while *self.peek() == Token::Or {
+            self.advance();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ConditionError> {
+        let mut left = self.parse_unary()?;
+        while *self.peek() == Token::And {
+            self.advance();
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ConditionError> {
+        if *self.peek() == Token::Not {
+            self.advance();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ConditionError> {
+        let left = self.parse_primary()?;
+        if let Token::Op(op) = *self.peek() {
+            let position = self.position();
+            self.advance();
+            let right = self.parse_primary()?;
+            return Ok(Expr::Compare {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+                position,
+            });
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ConditionError> {
+        let (token, position) = self.advance();
+        match token {
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+            }
+            Token::Str(s) => Ok(Expr::Literal(Value::String(s))),
+            Token::Num(n) => Ok(Expr::Literal(
+                serde_json::Number::from_f64(n)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+            )),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ => self.parse_path(name, position),
+            },
+            other => Err(ConditionError::new(
+                format!("Expected a value but found {}", other.describe()),
+                position,
+            )),
+        }
+    }
+
+    fn parse_path(&mut self, root: String, position: usize) -> Result<Expr, ConditionError> {
+        if !matches!(
+            root.as_str(),
+            "event" | "session_id" | "project_path" | "data"
+        ) {
+            return Err(ConditionError::new(
+                format!(
+                    "Unknown variable '{}' (expected event, session_id, project_path or data.*)",
+                    root
+                ),
+                position,
+            ));
+        }
+
+        let mut segments = vec![PathSegment::Key(root)];
+        loop {
+            match self.peek() {
+                Token::Dot => {
+                    self.advance();
+                    match self.advance() {
+                        (Token::Ident(key), _) => segments.push(PathSegment::Key(key)),
+                        // Keywords are valid field names after a dot (data.contains)
+                        (Token::Op(op), _) => {
+                            segments.push(PathSegment::Key(op.as_str().to_string()))
+                        }
+                        (other, pos) => {
+                            return Err(ConditionError::new(
+                                format!("Expected a field name but found {}", other.describe()),
+                                pos,
+                            ))
+                        }
+                    }
+                }
+                Token::LBracket => {
+                    self.advance();
+                    match self.advance() {
+                        (Token::Num(n), _) if n >= 0.0 && n.fract() == 0.0 => {
+                            segments.push(PathSegment::Index(n as usize))
+                        }
+                        (Token::Str(key), _) => segments.push(PathSegment::Key(key)),
+                        (other, pos) => {
+                            return Err(ConditionError::new(
+                                format!(
+                                    "Expected an index or quoted key but found {}",
+                                    other.describe()
+                                ),
+                                pos,
+                            ))
+                        }
+                    }
+                    self.expect(Token::RBracket)?;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(Expr::Path { segments, position })
+    }
+}
+
+/// Parses a condition expression
+pub fn parse(input: &str) -> Result<Expr, ConditionError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+    let expr = parser.parse_or()?;
+    if *parser.peek() != Token::End {
+        return Err(ConditionError::new(
+            format!("Unexpected {}", parser.peek().describe()),
+            parser.position(),
+        ));
+    }
+    Ok(expr)
+}
+
+fn resolve_path(segments: &[PathSegment], context: &HookContext) -> Value {
+    let mut current = match segments.first() {
+        Some(PathSegment::Key(root)) => match root.as_str() {
+            "event" => Value::String(context.event.clone()),
+            "session_id" => Value::String(context.session_id.clone()),
+            "project_path" => Value::String(context.project_path.clone()),
+            _ => context.data.clone(),
+        },
+        _ => return Value::Null,
+    };
+
+    for segment in &segments[1..] {
+        current = match (segment, &current) {
+            (PathSegment::Key(key), Value::Object(map)) => {
+                map.get(key).cloned().unwrap_or(Value::Null)
+            }
+            (PathSegment::Index(index), Value::Array(items)) => {
+                items.get(*index).cloned().unwrap_or(Value::Null)
+            }
+            _ => Value::Null,
+        };
+    }
+    current
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().map(|n| n != 0.0).unwrap_or(false),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => left == right,
+    }
+}
+
+fn eval_value(expr: &Expr, context: &HookContext) -> Result<Value, ConditionError> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Path { segments, .. } => Ok(resolve_path(segments, context)),
+        _ => Ok(Value::Bool(eval_bool(expr, context)?)),
+    }
+}
+
+fn compare(
+    left: &Value,
+    op: CompareOp,
+    right: &Value,
+    position: usize,
+) -> Result<bool, ConditionError> {
+    let type_error = |expected: &str| {
+        ConditionError::new(
+            format!("Operator '{}' expects {}", op.as_str(), expected),
+            position,
+        )
+    };
+
+    match op {
+        CompareOp::Eq => Ok(values_equal(left, right)),
+        CompareOp::Ne => Ok(!values_equal(left, right)),
+        CompareOp::Gt | CompareOp::Ge | CompareOp::Lt | CompareOp::Le => {
+            let ordering = match (left, right) {
+                (Value::Number(a), Value::Number(b)) => a
+                    .as_f64()
+                    .zip(b.as_f64())
+                    .and_then(|(a, b)| a.partial_cmp(&b)),
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                // Missing values never satisfy an ordering comparison
+                (Value::Null, _) | (_, Value::Null) => None,
+                _ => return Err(type_error("two numbers or two strings")),
+            };
+            Ok(match ordering {
+                Some(ordering) => match op {
+                    CompareOp::Gt => ordering.is_gt(),
+                    CompareOp::Ge => ordering.is_ge(),
+                    CompareOp::Lt => ordering.is_lt(),
+                    _ => ordering.is_le(),
+                },
+                None => false,
+            })
+        }
+        CompareOp::Contains => match (left, right) {
+            (Value::String(haystack), Value::String(needle)) => {
+                Ok(haystack.contains(needle.as_str()))
+            }
+            (Value::Array(items), needle) => {
+                Ok(items.iter().any(|item| values_equal(item, needle)))
+            }
+            (Value::Object(map), Value::String(key)) => Ok(map.contains_key(key)),
+            (Value::Null, _) => Ok(false),
+            _ => Err(type_error("a string, array or object on the left")),
+        },
+        CompareOp::Matches => {
+            let pattern = match right {
+                Value::String(pattern) => pattern,
+                _ => return Err(type_error("a regex string on the right")),
+            };
+            let regex = regex::Regex::new(pattern).map_err(|e| {
+                ConditionError::new(format!("Invalid regex '{}': {}", pattern, e), position)
+            })?;
+            Ok(match left {
+                Value::String(s) => regex.is_match(s),
+                Value::Array(items) => items
+                    .iter()
+                    .any(|item| item.as_str().is_some_and(|s| regex.is_match(s))),
+                _ => false,
+            })
+        }
+        CompareOp::Glob => {
+            let pattern = match right {
+                Value::String(pattern) => pattern,
+                _ => return Err(type_error("a glob string on the right")),
+            };
+            let glob = glob::Pattern::new(pattern).map_err(|e| {
+                ConditionError::new(format!("Invalid glob '{}': {}", pattern, e), position)
+            })?;
+            let options = glob::MatchOptions {
+                case_sensitive: true,
+                require_literal_separator: true,
+                require_literal_leading_dot: false,
+            };
+            let matches = |path: &str| glob.matches_with(&path.replace('\\', "/"), options);
+            Ok(match left {
+                Value::String(path) => matches(path),
+                // A list of files matches when any of them does
+                Value::Array(items) => items
+                    .iter()
+                    .any(|item| item.as_str().is_some_and(matches)),
+                _ => false,
+            })
+        }
+    }
+}
+
+fn eval_bool(expr: &Expr, context: &HookContext) -> Result<bool, ConditionError> {
+    match expr {
+        Expr::Literal(value) => Ok(truthy(value)),
+        Expr::Path { segments, .. } => Ok(truthy(&resolve_path(segments, context))),
+        Expr::Not(inner) => Ok(!eval_bool(inner, context)?),
+        Expr::And(left, right) => Ok(eval_bool(left, context)? && eval_bool(right, context)?),
+        Expr::Or(left, right) => Ok(eval_bool(left, context)? || eval_bool(right, context)?),
+        Expr::Compare {
+            left,
+            op,
+            right,
+            position,
+        } => compare(
+            &eval_value(left, context)?,
+            *op,
+            &eval_value(right, context)?,
+            *position,
+        ),
+    }
+}
+
+/// Parses and evaluates a condition against a hook context.
+/// An empty condition is always true. Errors include a caret pointing at the problem.
+pub fn evaluate_condition(input: &str, context: &HookContext) -> Result<bool, String> {
+    if input.trim().is_empty() {
+        return Ok(true);
+    }
+    let expr = parse(input).map_err(|e| e.describe(input))?;
+    eval_bool(&expr, context).map_err(|e| e.describe(input))
+}
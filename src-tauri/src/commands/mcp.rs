@@ -4,9 +4,22 @@ use log::{error, info};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use tauri::AppHandle;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+
+use super::mcp_client::{McpClient, McpTransport};
+
+/// Interval between background health probes of configured servers
+const PROBE_INTERVAL_SECS: u64 = 60;
+
+/// Timeout for each request (including the handshake) while probing a server
+const PROBE_TIMEOUT_SECS: u64 = 20;
+
+/// Timeout for a tool invocation from the explorer
+const TOOL_CALL_TIMEOUT_SECS: u64 = 120;
 
 /// Helper function to create a std::process::Command with proper environment variables
 /// This ensures commands like Claude can find Node.js and other dependencies
@@ -25,7 +38,7 @@ fn find_claude_binary(app_handle: &AppHandle) -> Result<String> {
 pub struct MCPServer {
     /// Server name/identifier
     pub name: String,
-    /// Transport type: "stdio" or "sse"
+    /// Transport type: "stdio", "sse" or "http"
     pub transport: String,
     /// Command to execute (for stdio)
     pub command: Option<String>,
@@ -33,7 +46,7 @@ pub struct MCPServer {
     pub args: Vec<String>,
     /// Environment variables
     pub env: HashMap<String, String>,
-    /// URL endpoint (for SSE)
+    /// URL endpoint (for SSE / HTTP)
     pub url: Option<String>,
     /// Configuration scope: "local", "project", or "user"
     pub scope: String,
@@ -44,7 +57,7 @@ pub struct MCPServer {
 }
 
 /// Server status information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerStatus {
     /// Whether the server is running
     pub running: bool,
@@ -52,6 +65,22 @@ pub struct ServerStatus {
     pub error: Option<String>,
     /// Last checked timestamp
     pub last_checked: Option<u64>,
+    /// Time to connect and complete the initialize handshake
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// Protocol version negotiated during initialize
+    #[serde(default)]
+    pub protocol_version: Option<String>,
+    #[serde(default)]
+    pub server_name: Option<String>,
+    #[serde(default)]
+    pub server_version: Option<String>,
+    #[serde(default)]
+    pub tools_count: Option<usize>,
+    #[serde(default)]
+    pub resources_count: Option<usize>,
+    #[serde(default)]
+    pub prompts_count: Option<usize>,
 }
 
 /// MCP configuration for project scope (.mcp.json)
@@ -59,16 +88,188 @@ pub struct ServerStatus {
 pub struct MCPProjectConfig {
     #[serde(rename = "mcpServers")]
     pub mcp_servers: HashMap<String, MCPServerConfig>,
+    /// Other top-level keys, written back unchanged
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-/// Individual server configuration in .mcp.json
+/// Individual server configuration in .mcp.json, selected by `type`.
+/// Entries without a `type` are stdio servers (or http when they only have a `url`).
+/// The `type` string itself stays in the entry's `extra`, so saving writes back exactly
+/// what was read (no added or normalized tag).
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum MCPServerConfig {
+    Stdio(StdioServerConfig),
+    Sse(RemoteServerConfig),
+    /// Streamable HTTP ("http" or "streamable-http")
+    Http(RemoteServerConfig),
+    /// Unknown `type` or malformed entry, kept verbatim
+    Other(serde_json::Value),
+}
+
+/// Launch configuration of a stdio server
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MCPServerConfig {
+pub struct StdioServerConfig {
     pub command: String,
     #[serde(default)]
     pub args: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Fields we do not model, kept so saving does not drop them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Endpoint of an SSE or streamable HTTP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteServerConfig {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Fields we do not model, kept so saving does not drop them
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<'de> Deserialize<'de> for MCPServerConfig {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let kind = match value.get("type").and_then(|t| t.as_str()) {
+            Some(kind) => kind,
+            None if value.get("url").is_some() && value.get("command").is_none() => "http",
+            None => "stdio",
+        };
+
+        let parsed = match kind {
+            "stdio" => serde_json::from_value(value.clone()).map(MCPServerConfig::Stdio),
+            "sse" => serde_json::from_value(value.clone()).map(MCPServerConfig::Sse),
+            "http" | "streamable-http" => {
+                serde_json::from_value(value.clone()).map(MCPServerConfig::Http)
+            }
+            _ => return Ok(MCPServerConfig::Other(value)),
+        };
+        Ok(parsed.unwrap_or_else(|e| {
+            log::warn!("Keeping unparsed MCP server entry as-is: {}", e);
+            MCPServerConfig::Other(value)
+        }))
+    }
+}
+
+static ENV_VAR_PATTERN: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+    regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap()
+});
+
+/// Expands `${VAR}` and `${VAR:-default}` from the workbench environment
+fn expand_env_vars(input: &str) -> String {
+    ENV_VAR_PATTERN
+        .replace_all(input, |caps: &regex::Captures| {
+            match std::env::var(&caps[1]) {
+                Ok(value) => value,
+                Err(_) => match caps.get(2) {
+                    Some(default) => default.as_str().to_string(),
+                    None => {
+                        log::warn!("Environment variable {} is not set", &caps[1]);
+                        String::new()
+                    }
+                },
+            }
+        })
+        .into_owned()
+}
+
+fn expand_env_map(map: &HashMap<String, String>) -> HashMap<String, String> {
+    map.iter()
+        .map(|(key, value)| (key.clone(), expand_env_vars(value)))
+        .collect()
+}
+
+impl MCPServerConfig {
+    /// Builds the transport used to connect, with environment variables interpolated
+    /// in command, args, env, url and headers. Stdio servers start in `cwd` when given.
+    pub fn to_transport(&self, cwd: Option<&Path>) -> std::result::Result<McpTransport, String> {
+        Ok(match self {
+            MCPServerConfig::Stdio(config) => McpTransport::Stdio {
+                command: expand_env_vars(&config.command),
+                args: config.args.iter().map(|arg| expand_env_vars(arg)).collect(),
+                env: expand_env_map(&config.env),
+                cwd: cwd.map(|p| p.to_path_buf()),
+            },
+            MCPServerConfig::Sse(config) => McpTransport::Sse {
+                url: expand_env_vars(&config.url),
+                headers: expand_env_map(&config.headers),
+            },
+            MCPServerConfig::Http(config) => McpTransport::Http {
+                url: expand_env_vars(&config.url),
+                headers: expand_env_map(&config.headers),
+            },
+            MCPServerConfig::Other(config) => {
+                return Err(format!(
+                    "Unsupported MCP server type: {}",
+                    config.get("type").unwrap_or(&serde_json::Value::Null)
+                ))
+            }
+        })
+    }
+}
+
+/// A tool offered by a server (`tools/list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPTool {
+    pub name: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON schema of the tool arguments
+    #[serde(default)]
+    pub input_schema: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<serde_json::Value>,
+}
+
+/// A resource offered by a server (`resources/list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPResource {
+    pub uri: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+/// A prompt template offered by a server (`prompts/list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MCPPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<serde_json::Value>,
+}
+
+/// Everything a server exposes, as shown in the tool explorer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPServerCapabilities {
+    pub name: String,
+    pub server_name: Option<String>,
+    pub server_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// Raw `capabilities` object from the initialize response
+    pub capabilities: serde_json::Value,
+    pub tools: Vec<MCPTool>,
+    pub resources: Vec<MCPResource>,
+    pub prompts: Vec<MCPPrompt>,
 }
 
 /// Result of adding a server
@@ -95,12 +296,340 @@ pub struct ImportServerResult {
     pub error: Option<String>,
 }
 
+/// A server entry found in Claude's configuration files
+#[derive(Debug, Clone)]
+struct ConfiguredServer {
+    name: String,
+    /// Raw entry, as written in ~/.claude.json or .mcp.json
+    config: serde_json::Value,
+    /// Working directory for stdio servers of a project
+    cwd: Option<PathBuf>,
+    /// False for .mcp.json servers the user has not approved; those are never started
+    approved: bool,
+}
+
+fn read_json_file(path: &Path) -> Option<serde_json::Value> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Approval of project (.mcp.json) servers, as gated by the Claude CLI:
+/// a server runs only if it is listed in `enabledMcpjsonServers` (or
+/// `enableAllProjectMcpServers` is set) and not listed in `disabledMcpjsonServers`.
+#[derive(Debug, Default)]
+struct ProjectServerApproval {
+    enable_all: bool,
+    enabled: Vec<String>,
+    disabled: Vec<String>,
+}
+
+impl ProjectServerApproval {
+    /// Approvals come from the user's own files (~/.claude.json project entry,
+    /// ~/.claude/settings.json, the project's settings.local.json); the shared
+    /// .claude/settings.json of a cloned repository may only disable servers.
+    fn load(claude_json: Option<&serde_json::Value>, project_path: &str) -> Self {
+        let project_dir = PathBuf::from(project_path);
+        let user_settings = dirs::home_dir()
+            .and_then(|home| read_json_file(&home.join(".claude").join("settings.json")));
+        let local_settings =
+            read_json_file(&project_dir.join(".claude").join("settings.local.json"));
+        let shared_settings = read_json_file(&project_dir.join(".claude").join("settings.json"));
+        let project_entry = claude_json
+            .and_then(|c| c.get("projects"))
+            .and_then(|p| p.get(project_path));
+
+        let trusted = [
+            project_entry,
+            user_settings.as_ref(),
+            local_settings.as_ref(),
+        ];
+        let names = |source: Option<&serde_json::Value>, key: &str| -> Vec<String> {
+            source
+                .and_then(|s| s.get(key))
+                .and_then(|v| v.as_array())
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(|s| s.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut approval = Self::default();
+        for source in trusted {
+            approval.enable_all |= source
+                .and_then(|s| s.get("enableAllProjectMcpServers"))
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            approval
+                .enabled
+                .extend(names(source, "enabledMcpjsonServers"));
+        }
+        for source in trusted.into_iter().chain([shared_settings.as_ref()]) {
+            approval
+                .disabled
+                .extend(names(source, "disabledMcpjsonServers"));
+        }
+        approval
+    }
+
+    fn allows(&self, name: &str) -> bool {
+        !self.disabled.iter().any(|n| n == name)
+            && (self.enable_all || self.enabled.iter().any(|n| n == name))
+    }
+}
+
+/// Collects the servers Claude would load: user scope from ~/.claude.json, then the
+/// project's .mcp.json, then local scope (~/.claude.json projects entry).
+/// Later scopes override earlier ones with the same name, as in the CLI.
+fn load_configured_servers(project_path: Option<&str>) -> Vec<ConfiguredServer> {
+    let mut servers: HashMap<String, ConfiguredServer> = HashMap::new();
+    let mut add_servers = |section: Option<&serde_json::Value>,
+                           cwd: Option<PathBuf>,
+                           approval: Option<&ProjectServerApproval>| {
+        if let Some(section) = section.and_then(|v| v.as_object()) {
+            for (name, config) in section {
+                servers.insert(
+                    name.clone(),
+                    ConfiguredServer {
+                        name: name.clone(),
+                        config: config.clone(),
+                        cwd: cwd.clone(),
+                        approved: approval.is_none_or(|a| a.allows(name)),
+                    },
+                );
+            }
+        }
+    };
+
+    let claude_json = dirs::home_dir().and_then(|home| read_json_file(&home.join(".claude.json")));
+    add_servers(
+        claude_json.as_ref().and_then(|c| c.get("mcpServers")),
+        None,
+        None,
+    );
+
+    if let Some(project_path) = project_path {
+        let project_dir = PathBuf::from(project_path);
+        let project_config = read_json_file(&project_dir.join(".mcp.json"));
+        let approval = ProjectServerApproval::load(claude_json.as_ref(), project_path);
+        add_servers(
+            project_config.as_ref().and_then(|c| c.get("mcpServers")),
+            Some(project_dir.clone()),
+            Some(&approval),
+        );
+        add_servers(
+            claude_json
+                .as_ref()
+                .and_then(|c| c.get("projects"))
+                .and_then(|p| p.get(project_path))
+                .and_then(|p| p.get("mcpServers")),
+            Some(project_dir),
+            None,
+        );
+    }
+
+    servers.into_values().collect()
+}
+
+fn not_approved_error(name: &str) -> String {
+    format!(
+        "Project MCP server '{}' has not been approved (enabledMcpjsonServers)",
+        name
+    )
+}
+
+/// Looks up a configured server by name
+fn find_configured_server(
+    name: &str,
+    project_path: Option<&str>,
+) -> Result<ConfiguredServer, String> {
+    load_configured_servers(project_path)
+        .into_iter()
+        .find(|server| server.name == name)
+        .ok_or_else(|| format!("MCP server '{}' is not configured", name))
+}
+
+/// Parses a configured server entry into a connectable transport
+fn server_transport(server: &ConfiguredServer) -> Result<McpTransport, String> {
+    let config: MCPServerConfig = serde_json::from_value(server.config.clone())
+        .map_err(|e| format!("Invalid configuration for '{}': {}", server.name, e))?;
+    config
+        .to_transport(server.cwd.as_deref())
+        .map_err(|e| format!("{} ({})", e, server.name))
+}
+
+/// Connects to a configured server and completes the initialize handshake
+/// Unapproved project servers are refused before anything is spawned.
+async fn connect_server(server: &ConfiguredServer, timeout_secs: u64) -> Result<McpClient, String> {
+    if !server.approved {
+        return Err(not_approved_error(&server.name));
+    }
+    let transport = server_transport(server)?;
+    McpClient::connect(&transport, Duration::from_secs(timeout_secs)).await
+}
+
+/// Parses list items, skipping entries that do not match the expected shape
+fn parse_items<T: serde::de::DeserializeOwned>(items: Vec<serde_json::Value>) -> Vec<T> {
+    items
+        .into_iter()
+        .filter_map(|item| match serde_json::from_value(item) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                log::warn!("Skipping malformed MCP list item: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Connects to a server, performs the initialize handshake and counts what it offers
+async fn probe_server(server: &ConfiguredServer) -> ServerStatus {
+    let mut status = ServerStatus {
+        last_checked: Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        ),
+        ..Default::default()
+    };
+
+    if !server.approved {
+        status.error = Some(not_approved_error(&server.name));
+        return status;
+    }
+
+    let transport = match server_transport(server) {
+        Ok(transport) => transport,
+        Err(e) => {
+            status.error = Some(e);
+            return status;
+        }
+    };
+
+    let started = Instant::now();
+    let mut client =
+        match McpClient::connect(&transport, Duration::from_secs(PROBE_TIMEOUT_SECS)).await {
+            Ok(client) => client,
+            Err(e) => {
+                status.error = Some(e);
+                return status;
+            }
+        };
+    status.running = true;
+    status.latency_ms = Some(started.elapsed().as_millis() as u64);
+    status.protocol_version = client.info.protocol_version.clone();
+    status.server_name = client.info.name.clone();
+    status.server_version = client.info.version.clone();
+
+    match client.list_tools().await {
+        Ok(tools) => status.tools_count = Some(tools.len()),
+        Err(e) => status.error = Some(e),
+    }
+    match client.list_resources().await {
+        Ok(resources) => status.resources_count = Some(resources.len()),
+        Err(e) => status.error = status.error.or(Some(e)),
+    }
+    match client.list_prompts().await {
+        Ok(prompts) => status.prompts_count = Some(prompts.len()),
+        Err(e) => status.error = status.error.or(Some(e)),
+    }
+
+    client.close().await;
+    status
+}
+
+/// Latest health of every configured server, refreshed by a background prober
+#[derive(Default)]
+pub struct McpStatusMonitor {
+    statuses: Mutex<HashMap<String, ServerStatus>>,
+    /// Project whose project/local scoped servers are probed alongside user servers
+    project_path: Mutex<Option<String>>,
+}
+
+pub struct McpStatusState(pub Arc<McpStatusMonitor>);
+
+impl McpStatusMonitor {
+    /// Switches the probed project; returns true when it changed
+    fn set_project(&self, project_path: Option<String>) -> bool {
+        match self.project_path.lock() {
+            Ok(mut current) if *current != project_path => {
+                *current = project_path;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn cached(&self) -> HashMap<String, ServerStatus> {
+        self.statuses
+            .lock()
+            .map(|statuses| statuses.clone())
+            .unwrap_or_default()
+    }
+
+    /// Probes all configured servers concurrently and emits `mcp-server-status-changed`
+    /// for each server whose running state or error changed
+    async fn probe_all(&self, app: &AppHandle) -> HashMap<String, ServerStatus> {
+        let project_path = self.project_path.lock().ok().and_then(|p| p.clone());
+        let servers = load_configured_servers(project_path.as_deref());
+        let results = futures::future::join_all(
+            servers
+                .iter()
+                .map(|server| async move { (server.name.clone(), probe_server(server).await) }),
+        )
+        .await;
+
+        let mut statuses = match self.statuses.lock() {
+            Ok(statuses) => statuses,
+            Err(_) => return results.into_iter().collect(),
+        };
+        for (name, status) in &results {
+            let changed = match statuses.get(name) {
+                Some(previous) => {
+                    previous.running != status.running || previous.error != status.error
+                }
+                None => true,
+            };
+            if changed {
+                info!(
+                    "MCP server '{}' status: running={}, error={:?}",
+                    name, status.running, status.error
+                );
+                let _ = app.emit(
+                    "mcp-server-status-changed",
+                    serde_json::json!({ "name": name, "status": status }),
+                );
+            }
+        }
+        *statuses = results.into_iter().collect();
+        statuses.clone()
+    }
+}
+
+/// Starts the background prober that keeps `McpStatusMonitor` up to date
+pub fn start_mcp_status_prober(app: AppHandle, monitor: Arc<McpStatusMonitor>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(PROBE_INTERVAL_SECS)).await;
+            monitor.probe_all(&app).await;
+        }
+    });
+}
+
 /// Executes a claude mcp command
 fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result<String> {
+    let claude_path = find_claude_binary(app_handle)?;
+    run_claude_mcp_command(&claude_path, args)
+}
+
+/// Executes a claude mcp command with an already resolved claude binary
+fn run_claude_mcp_command(claude_path: &str, args: Vec<&str>) -> Result<String> {
     info!("Executing claude mcp command with args: {:?}", args);
 
-    let claude_path = find_claude_binary(app_handle)?;
-    let mut cmd = create_command_with_env(&claude_path);
+    let mut cmd = create_command_with_env(claude_path);
     cmd.arg("mcp");
     for arg in args {
         cmd.arg(arg);
@@ -135,10 +664,38 @@ pub async fn mcp_add(
     url: Option<String>,
     scope: String,
 ) -> Result<AddServerResult, String> {
-    info!("Adding MCP server: {} with transport: {}", name, transport);
+    let server = MCPServer {
+        name,
+        transport,
+        command,
+        args,
+        env,
+        url,
+        scope,
+        is_active: false,
+        status: ServerStatus::default(),
+    };
+
+    Ok(match find_claude_binary(&app) {
+        Ok(claude_path) => add_server(&claude_path, &server),
+        Err(e) => AddServerResult {
+            success: false,
+            message: e.to_string(),
+            server_name: None,
+        },
+    })
+}
+
+/// Adds an MCP server via `claude mcp add` (shared with the headless CLI)
+pub fn add_server(claude_path: &str, server: &MCPServer) -> AddServerResult {
+    info!(
+        "Adding MCP server: {} with transport: {}",
+        server.name, server.transport
+    );
 
     // Prepare owned strings for environment variables
-    let env_args: Vec<String> = env
+    let env_args: Vec<String> = server
+        .env
         .iter()
         .map(|(key, value)| format!("{}={}", key, value))
         .collect();
@@ -147,70 +704,73 @@ pub async fn mcp_add(
 
     // Add scope flag
     cmd_args.push("-s");
-    cmd_args.push(&scope);
+    cmd_args.push(&server.scope);
 
-    // Add transport flag for SSE
-    if transport == "sse" {
+    // Add transport flag for SSE / streamable HTTP
+    if server.transport == "sse" || server.transport == "http" {
         cmd_args.push("--transport");
-        cmd_args.push("sse");
+        cmd_args.push(&server.transport);
     }
 
     // Add environment variables
-    for (i, _) in env.iter().enumerate() {
+    for (i, _) in server.env.iter().enumerate() {
         cmd_args.push("-e");
         cmd_args.push(&env_args[i]);
     }
 
     // Add name
-    cmd_args.push(&name);
+    cmd_args.push(&server.name);
 
     // Add command/URL based on transport
-    if transport == "stdio" {
-        if let Some(cmd) = &command {
+    if server.transport == "stdio" {
+        if let Some(cmd) = &server.command {
             // Add "--" separator before command to prevent argument parsing issues
-            if !args.is_empty() || cmd.contains('-') {
+            if !server.args.is_empty() || cmd.contains('-') {
                 cmd_args.push("--");
             }
             cmd_args.push(cmd);
             // Add arguments
-            for arg in &args {
+            for arg in &server.args {
                 cmd_args.push(arg);
             }
         } else {
-            return Ok(AddServerResult {
+            return AddServerResult {
                 success: false,
                 message: "Command is required for stdio transport".to_string(),
                 server_name: None,
-            });
+            };
         }
-    } else if transport == "sse" {
-        if let Some(url_str) = &url {
+    } else if server.transport == "sse" || server.transport == "http" {
+        if let Some(url_str) = &server.url {
             cmd_args.push(url_str);
         } else {
-            return Ok(AddServerResult {
+            return AddServerResult {
                 success: false,
-                message: "URL is required for SSE transport".to_string(),
+                message: format!(
+                    "URL is required for {} transport",
+                    server.transport.to_uppercase()
+                ),
                 server_name: None,
-            });
+            };
         }
     }
 
-    match execute_claude_mcp_command(&app, cmd_args) {
+    match run_claude_mcp_command(claude_path, cmd_args) {
         Ok(output) => {
-            info!("Successfully added MCP server: {}", name);
-            Ok(AddServerResult {
+            info!("Successfully added MCP server: {}", server.name);
+            AddServerResult {
                 success: true,
                 message: output.trim().to_string(),
-                server_name: Some(name),
-            })
+                server_name: Some(server.name.clone()),
+            }
         }
         Err(e) => {
             error!("Failed to add MCP server: {}", e);
-            Ok(AddServerResult {
+            AddServerResult {
                 success: false,
                 message: e.to_string(),
                 server_name: None,
-            })
+            }
         }
     }
 }
@@ -218,9 +778,15 @@ pub async fn mcp_add(
 /// Lists all configured MCP servers
 #[tauri::command]
 pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
+    let claude_path = find_claude_binary(&app).map_err(|e| e.to_string())?;
+    list_servers(&claude_path)
+}
+
+/// Lists MCP servers via `claude mcp list` (shared with the headless CLI)
+pub fn list_servers(claude_path: &str) -> Result<Vec<MCPServer>, String> {
     info!("Listing MCP servers");
 
-    match execute_claude_mcp_command(&app, vec!["list"]) {
+    match run_claude_mcp_command(claude_path, vec!["list"]) {
         Ok(output) => {
             info!("Raw output from 'claude mcp list': {:?}", output);
             let trimmed = output.trim();
@@ -302,11 +868,7 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
                             url: None,
                             scope: "local".to_string(), // Default assumption
                             is_active: false,
-                            status: ServerStatus {
-                                running: false,
-                                error: None,
-                                last_checked: None,
-                            },
+                            status: ServerStatus::default(),
                         });
                         info!("Added server: {:?}", name);
 
@@ -340,9 +902,15 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
 /// Gets details for a specific MCP server
 #[tauri::command]
 pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String> {
+    let claude_path = find_claude_binary(&app).map_err(|e| e.to_string())?;
+    get_server(&claude_path, name)
+}
+
+/// Reads one server via `claude mcp get` (shared with the headless CLI)
+pub fn get_server(claude_path: &str, name: String) -> Result<MCPServer, String> {
     info!("Getting MCP server details for: {}", name);
 
-    match execute_claude_mcp_command(&app, vec!["get", &name]) {
+    match run_claude_mcp_command(claude_path, vec!["get", &name]) {
         Ok(output) => {
             // Parse the structured text output
             let mut scope = "local".to_string();
@@ -392,11 +960,7 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
                 url,
                 scope,
                 is_active: false,
-                status: ServerStatus {
-                    running: false,
-                    error: None,
-                    last_checked: None,
-                },
+                status: ServerStatus::default(),
             })
         }
         Err(e) => {
@@ -409,9 +973,15 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
 /// Removes an MCP server
 #[tauri::command]
 pub async fn mcp_remove(app: AppHandle, name: String) -> Result<String, String> {
+    let claude_path = find_claude_binary(&app).map_err(|e| e.to_string())?;
+    remove_server(&claude_path, &name)
+}
+
+/// Removes a server via `claude mcp remove` (shared with the headless CLI)
+pub fn remove_server(claude_path: &str, name: &str) -> Result<String, String> {
     info!("Removing MCP server: {}", name);
 
-    match execute_claude_mcp_command(&app, vec!["remove", &name]) {
+    match run_claude_mcp_command(claude_path, vec!["remove", name]) {
         Ok(output) => {
             info!("Successfully removed MCP server: {}", name);
             Ok(output.trim().to_string())
@@ -431,35 +1001,52 @@ pub async fn mcp_add_json(
     json_config: String,
     scope: String,
 ) -> Result<AddServerResult, String> {
+    Ok(match find_claude_binary(&app) {
+        Ok(claude_path) => add_server_json(&claude_path, name, &json_config, &scope),
+        Err(e) => AddServerResult {
+            success: false,
+            message: e.to_string(),
+            server_name: None,
+        },
+    })
+}
+
+/// Adds a server via `claude mcp add-json` (shared with the headless CLI)
+pub fn add_server_json(
+    claude_path: &str,
+    name: String,
+    json_config: &str,
+    scope: &str,
+) -> AddServerResult {
     info!(
         "Adding MCP server from JSON: {} with scope: {}",
         name, scope
     );
 
     // Build command args
-    let mut cmd_args = vec!["add-json", &name, &json_config];
+    let mut cmd_args = vec!["add-json", &name, json_config];
 
     // Add scope flag
     let scope_flag = "-s";
     cmd_args.push(scope_flag);
-    cmd_args.push(&scope);
+    cmd_args.push(scope);
 
-    match execute_claude_mcp_command(&app, cmd_args) {
+    match run_claude_mcp_command(claude_path, cmd_args) {
         Ok(output) => {
             info!("Successfully added MCP server from JSON: {}", name);
-            Ok(AddServerResult {
+            AddServerResult {
                 success: true,
                 message: output.trim().to_string(),
                 server_name: Some(name),
-            })
+            }
         }
         Err(e) => {
             error!("Failed to add MCP server from JSON: {}", e);
-            Ok(AddServerResult {
+            AddServerResult {
                 success: false,
                 message: e.to_string(),
                 server_name: None,
-            })
+            }
         }
     }
 }
@@ -636,16 +1223,42 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
     }
 }
 
-/// Tests connection to an MCP server
+/// Tests connection to an MCP server by performing the initialize handshake
 #[tauri::command]
-pub async fn mcp_test_connection(app: AppHandle, name: String) -> Result<String, String> {
+pub async fn mcp_test_connection(
+    app: AppHandle,
+    name: String,
+    project_path: Option<String>,
+) -> Result<String, String> {
     info!("Testing connection to MCP server: {}", name);
 
-    // For now, we'll use the get command to test if the server exists
-    match execute_claude_mcp_command(&app, vec!["get", &name]) {
-        Ok(_) => Ok(format!("Connection to {} successful", name)),
-        Err(e) => Err(e.to_string()),
+    let server = load_configured_servers(project_path.as_deref())
+        .into_iter()
+        .find(|server| server.name == name);
+    let server = match server {
+        Some(server) => server,
+        None => {
+            // Not in the config files we know about; at least check the CLI knows it
+            return match execute_claude_mcp_command(&app, vec!["get", &name]) {
+                Ok(_) => Ok(format!("Connection to {} successful", name)),
+                Err(e) => Err(e.to_string()),
+            };
+        }
+    };
+
+    let status = probe_server(&server).await;
+    if !status.running {
+        return Err(status
+            .error
+            .unwrap_or_else(|| format!("Failed to connect to {}", name)));
     }
+    Ok(format!(
+        "Connected to {} in {} ms (protocol {}, {} tools)",
+        name,
+        status.latency_ms.unwrap_or(0),
+        status.protocol_version.as_deref().unwrap_or("unknown"),
+        status.tools_count.unwrap_or(0)
+    ))
 }
 
 /// Resets project-scoped server approval choices
@@ -666,13 +1279,89 @@ pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<String, String>
 }
 
 /// Gets the status of MCP servers
+/// Returns the prober's cached results unless `refresh` is set or the project changed
 #[tauri::command]
-pub async fn mcp_get_server_status() -> Result<HashMap<String, ServerStatus>, String> {
+pub async fn mcp_get_server_status(
+    app: AppHandle,
+    state: State<'_, McpStatusState>,
+    project_path: Option<String>,
+    refresh: Option<bool>,
+) -> Result<HashMap<String, ServerStatus>, String> {
     info!("Getting MCP server status");
 
-    // TODO: Implement actual status checking
-    // For now, return empty status
-    Ok(HashMap::new())
+    let monitor = state.0.clone();
+    let project_changed = monitor.set_project(project_path);
+    let cached = monitor.cached();
+    if refresh.unwrap_or(false) || project_changed || cached.is_empty() {
+        Ok(monitor.probe_all(&app).await)
+    } else {
+        Ok(cached)
+    }
+}
+
+/// Connects to a server and lists its tools (with JSON schemas), resources and prompts
+#[tauri::command]
+pub async fn mcp_explore_server(
+    name: String,
+    project_path: Option<String>,
+) -> Result<MCPServerCapabilities, String> {
+    info!("Exploring MCP server: {}", name);
+
+    let server = find_configured_server(&name, project_path.as_deref())?;
+    let mut client = connect_server(&server, PROBE_TIMEOUT_SECS).await?;
+
+    let listed = async {
+        let tools = client.list_tools().await?;
+        let resources = client.list_resources().await?;
+        let prompts = client.list_prompts().await?;
+        Ok::<_, String>((tools, resources, prompts))
+    }
+    .await;
+    let info = client.info.clone();
+    client.close().await;
+
+    let (tools, resources, prompts) = listed.map_err(|e| {
+        error!("Failed to list capabilities of {}: {}", name, e);
+        e
+    })?;
+    Ok(MCPServerCapabilities {
+        name,
+        server_name: info.name,
+        server_version: info.version,
+        protocol_version: info.protocol_version,
+        capabilities: info.capabilities,
+        tools: parse_items(tools),
+        resources: parse_items(resources),
+        prompts: parse_items(prompts),
+    })
+}
+
+/// Invokes a tool on a server with user-supplied JSON arguments.
+/// Returns the raw `tools/call` result (tool errors are reported via `isError`).
+#[tauri::command]
+pub async fn mcp_call_tool(
+    name: String,
+    tool_name: String,
+    arguments: Option<serde_json::Value>,
+    project_path: Option<String>,
+) -> Result<serde_json::Value, String> {
+    info!("Calling tool '{}' on MCP server: {}", tool_name, name);
+
+    let arguments = match arguments {
+        None | Some(serde_json::Value::Null) => serde_json::json!({}),
+        Some(arguments) if arguments.is_object() => arguments,
+        Some(_) => return Err("Tool arguments must be a JSON object".to_string()),
+    };
+
+    let server = find_configured_server(&name, project_path.as_deref())?;
+    let mut client = connect_server(&server, TOOL_CALL_TIMEOUT_SECS).await?;
+    let result = client.call_tool(&tool_name, arguments).await;
+    client.close().await;
+
+    result.map_err(|e| {
+        error!("Tool call {} on {} failed: {}", tool_name, name, e);
+        e
+    })
 }
 
 /// Exports MCP server configuration from .claude.json
@@ -725,6 +1414,7 @@ pub async fn mcp_read_project_config(project_path: String) -> Result<MCPProjectC
     if !mcp_json_path.exists() {
         return Ok(MCPProjectConfig {
             mcp_servers: HashMap::new(),
+            extra: serde_json::Map::new(),
         });
     }
 
@@ -0,0 +1,612 @@
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::mpsc;
+
+/// MCP protocol revision we announce in `initialize`
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Upper bound on `nextCursor` pages fetched by a single list call
+const MAX_LIST_PAGES: usize = 50;
+
+/// How much of a stdio server's stderr is kept for error messages
+const STDERR_TAIL_BYTES: usize = 2048;
+
+/// How to reach an MCP server
+#[derive(Debug, Clone)]
+pub enum McpTransport {
+    Stdio {
+        command: String,
+        args: Vec<String>,
+        env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+    },
+    /// Legacy HTTP+SSE transport (GET event stream, POST to the announced endpoint)
+    Sse {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+    /// Streamable HTTP transport (POST, JSON or SSE responses)
+    Http {
+        url: String,
+        headers: HashMap<String, String>,
+    },
+}
+
+/// What the server told us in its `initialize` response
+#[derive(Debug, Clone, Default)]
+pub struct ServerInfo {
+    pub protocol_version: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub capabilities: Value,
+}
+
+/// Incremental parser for `text/event-stream` bodies
+#[derive(Default)]
+struct SseParser {
+    buffer: String,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+struct SseEvent {
+    event: Option<String>,
+    data: String,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.push_str(&String::from_utf8_lossy(chunk));
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.find('\n') {
+            let line: String = self.buffer.drain(..=pos).collect();
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if line.is_empty() {
+                // Blank line dispatches the event
+                if !self.data.is_empty() {
+                    events.push(SseEvent {
+                        event: self.event.take(),
+                        data: self.data.join("\n"),
+                    });
+                }
+                self.event = None;
+                self.data.clear();
+            } else if let Some(value) = line.strip_prefix("data:") {
+                self.data
+                    .push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            } else if let Some(value) = line.strip_prefix("event:") {
+                self.event = Some(value.trim().to_string());
+            }
+            // Comments (":") and id/retry fields are ignored
+        }
+        events
+    }
+}
+
+struct StdioConnection {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    stderr_tail: Arc<Mutex<String>>,
+}
+
+struct HttpConnection {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    session_id: Option<String>,
+    protocol_version: Option<String>,
+    inbox: VecDeque<Value>,
+}
+
+struct SseConnection {
+    client: reqwest::Client,
+    endpoint: String,
+    headers: HashMap<String, String>,
+    incoming: mpsc::UnboundedReceiver<Value>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+enum Connection {
+    Stdio(StdioConnection),
+    Http(HttpConnection),
+    Sse(SseConnection),
+}
+
+fn apply_headers(
+    mut request: reqwest::RequestBuilder,
+    headers: &HashMap<String, String>,
+) -> reqwest::RequestBuilder {
+    for (key, value) in headers {
+        request = request.header(key.as_str(), value.as_str());
+    }
+    request
+}
+
+impl Connection {
+    async fn open(transport: &McpTransport) -> Result<Self, String> {
+        match transport {
+            McpTransport::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+            } => {
+                let mut std_cmd = crate::claude_binary::create_command_with_env(command);
+                std_cmd.args(args).envs(env);
+                if let Some(cwd) = cwd {
+                    std_cmd.current_dir(cwd);
+                }
+                let mut cmd = tokio::process::Command::from(std_cmd);
+                cmd.stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true);
+
+                // Add CREATE_NO_WINDOW flag on Windows to prevent terminal window popup
+                #[cfg(target_os = "windows")]
+                {
+                    cmd.creation_flags(0x08000000);
+                }
+
+                let mut child = cmd
+                    .spawn()
+                    .map_err(|e| format!("Failed to start '{}': {}", command, e))?;
+                let stdin = child.stdin.take().ok_or("Failed to open server stdin")?;
+                let stdout = child.stdout.take().ok_or("Failed to open server stdout")?;
+                let stderr = child.stderr.take().ok_or("Failed to open server stderr")?;
+
+                // Keep the end of stderr around to explain failed handshakes
+                let stderr_tail = Arc::new(Mutex::new(String::new()));
+                let tail = stderr_tail.clone();
+                tokio::spawn(async move {
+                    let mut lines = BufReader::new(stderr).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        log::debug!("MCP server stderr: {}", line);
+                        if let Ok(mut tail) = tail.lock() {
+                            tail.push_str(&line);
+                            tail.push('\n');
+                            if tail.len() > STDERR_TAIL_BYTES {
+                                let mut cut = tail.len() - STDERR_TAIL_BYTES;
+                                while !tail.is_char_boundary(cut) {
+                                    cut += 1;
+                                }
+                                tail.drain(..cut);
+                            }
+                        }
+                    }
+                });
+
+                Ok(Connection::Stdio(StdioConnection {
+                    child,
+                    stdin,
+                    stdout: BufReader::new(stdout).lines(),
+                    stderr_tail,
+                }))
+            }
+            McpTransport::Http { url, headers } => Ok(Connection::Http(HttpConnection {
+                client: reqwest::Client::new(),
+                url: url.clone(),
+                headers: headers.clone(),
+                session_id: None,
+                protocol_version: None,
+                inbox: VecDeque::new(),
+            })),
+            McpTransport::Sse { url, headers } => {
+                let client = reqwest::Client::new();
+                let mut response = apply_headers(client.get(url), headers)
+                    .header("Accept", "text/event-stream")
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+                if !response.status().is_success() {
+                    return Err(format!("SSE connection failed: HTTP {}", response.status()));
+                }
+
+                let (endpoint_tx, endpoint_rx) = tokio::sync::oneshot::channel::<String>();
+                let (message_tx, incoming) = mpsc::unbounded_channel::<Value>();
+                let reader = tokio::spawn(async move {
+                    let mut parser = SseParser::default();
+                    let mut endpoint_tx = Some(endpoint_tx);
+                    while let Ok(Some(chunk)) = response.chunk().await {
+                        for event in parser.push(&chunk) {
+                            match event.event.as_deref() {
+                                Some("endpoint") => {
+                                    if let Some(tx) = endpoint_tx.take() {
+                                        let _ = tx.send(event.data);
+                                    }
+                                }
+                                None | Some("message") => {
+                                    if let Ok(message) = serde_json::from_str::<Value>(&event.data)
+                                    {
+                                        let _ = message_tx.send(message);
+                                    }
+                                }
+                                Some(_) => {}
+                            }
+                        }
+                    }
+                });
+
+                let endpoint = endpoint_rx
+                    .await
+                    .map_err(|_| "SSE stream closed before announcing an endpoint".to_string())?;
+                let endpoint = reqwest::Url::parse(url)
+                    .and_then(|base| base.join(endpoint.trim()))
+                    .map_err(|e| format!("Invalid SSE endpoint '{}': {}", endpoint, e))?;
+
+                Ok(Connection::Sse(SseConnection {
+                    client,
+                    endpoint: endpoint.to_string(),
+                    headers: headers.clone(),
+                    incoming,
+                    reader,
+                }))
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<(), String> {
+        match self {
+            Connection::Stdio(conn) => {
+                let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+                line.push('\n');
+                conn.stdin
+                    .write_all(line.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write to server: {}", e))?;
+                conn.stdin
+                    .flush()
+                    .await
+                    .map_err(|e| format!("Failed to write to server: {}", e))
+            }
+            Connection::Http(conn) => conn.post(message).await,
+            Connection::Sse(conn) => {
+                let response = apply_headers(conn.client.post(&conn.endpoint), &conn.headers)
+                    .json(message)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to post to {}: {}", conn.endpoint, e))?;
+                if response.status().is_success() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "Server rejected message: HTTP {}",
+                        response.status()
+                    ))
+                }
+            }
+        }
+    }
+
+    async fn recv(&mut self) -> Result<Value, String> {
+        match self {
+            Connection::Stdio(conn) => loop {
+                match conn.stdout.next_line().await {
+                    Ok(Some(line)) => {
+                        // Some servers log to stdout; skip anything that is not JSON-RPC
+                        if let Ok(message) = serde_json::from_str::<Value>(&line) {
+                            return Ok(message);
+                        }
+                        log::debug!("MCP server stdout (ignored): {}", line);
+                    }
+                    Ok(None) | Err(_) => {
+                        let status = conn.child.try_wait().ok().flatten();
+                        let stderr = conn
+                            .stderr_tail
+                            .lock()
+                            .map(|tail| tail.trim().to_string())
+                            .unwrap_or_default();
+                        let mut error = match status {
+                            Some(status) => format!("Server exited ({})", status),
+                            None => "Server closed its output".to_string(),
+                        };
+                        if !stderr.is_empty() {
+                            error.push_str(": ");
+                            error.push_str(&stderr);
+                        }
+                        return Err(error);
+                    }
+                }
+            },
+            Connection::Http(conn) => conn
+                .inbox
+                .pop_front()
+                .ok_or_else(|| "Server sent no response".to_string()),
+            Connection::Sse(conn) => conn
+                .incoming
+                .recv()
+                .await
+                .ok_or_else(|| "SSE stream closed".to_string()),
+        }
+    }
+
+    async fn close(self) {
+        match self {
+            Connection::Stdio(mut conn) => {
+                // Closing stdin asks the server to exit; kill it if it lingers
+                drop(conn.stdin);
+                if tokio::time::timeout(Duration::from_secs(2), conn.child.wait())
+                    .await
+                    .is_err()
+                {
+                    let _ = conn.child.kill().await;
+                }
+            }
+            Connection::Http(conn) => {
+                if let Some(session_id) = &conn.session_id {
+                    let _ = apply_headers(conn.client.delete(&conn.url), &conn.headers)
+                        .header("Mcp-Session-Id", session_id)
+                        .send()
+                        .await;
+                }
+            }
+            Connection::Sse(conn) => conn.reader.abort(),
+        }
+    }
+}
+
+impl HttpConnection {
+    /// POSTs one message; responses (JSON or an SSE stream) are queued in `inbox`
+    async fn post(&mut self, message: &Value) -> Result<(), String> {
+        let mut request = apply_headers(self.client.post(&self.url), &self.headers)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        if let Some(session_id) = &self.session_id {
+            request = request.header("Mcp-Session-Id", session_id);
+        }
+        if let Some(version) = &self.protocol_version {
+            request = request.header("MCP-Protocol-Version", version);
+        }
+
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to connect to {}: {}", self.url, e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {}: {}", status, body.trim()));
+        }
+        if let Some(session_id) = response
+            .headers()
+            .get("mcp-session-id")
+            .and_then(|v| v.to_str().ok())
+        {
+            self.session_id = Some(session_id.to_string());
+        }
+
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.starts_with("text/event-stream"))
+            .unwrap_or(false);
+        let request_id = message.get("id").cloned();
+
+        if is_stream {
+            // Read events until the response to this request arrives
+            let mut parser = SseParser::default();
+            while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+                let mut answered = false;
+                for event in parser.push(&chunk) {
+                    if let Ok(incoming) = serde_json::from_str::<Value>(&event.data) {
+                        if request_id.is_some()
+                            && incoming.get("method").is_none()
+                            && incoming.get("id") == request_id.as_ref()
+                        {
+                            answered = true;
+                        }
+                        self.inbox.push_back(incoming);
+                    }
+                }
+                if answered {
+                    break;
+                }
+            }
+        } else {
+            let body = response.text().await.map_err(|e| e.to_string())?;
+            if !body.trim().is_empty() {
+                match serde_json::from_str::<Value>(&body)
+                    .map_err(|e| format!("Invalid JSON response: {}", e))?
+                {
+                    Value::Array(batch) => self.inbox.extend(batch),
+                    single => self.inbox.push_back(single),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A connected, initialized MCP session
+pub struct McpClient {
+    connection: Connection,
+    next_id: u64,
+    timeout: Duration,
+    pub info: ServerInfo,
+}
+
+impl McpClient {
+    /// Connects and performs the `initialize` handshake.
+    /// `timeout` bounds every individual request, including the handshake.
+    pub async fn connect(transport: &McpTransport, timeout: Duration) -> Result<Self, String> {
+        let connection = tokio::time::timeout(timeout, Connection::open(transport))
+            .await
+            .map_err(|_| "Timed out connecting to server".to_string())??;
+
+        let mut client = McpClient {
+            connection,
+            next_id: 1,
+            timeout,
+            info: ServerInfo::default(),
+        };
+
+        let result = match client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "claude-workbench",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            )
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                client.connection.close().await;
+                return Err(e);
+            }
+        };
+
+        client.info = ServerInfo {
+            protocol_version: result["protocolVersion"].as_str().map(|s| s.to_string()),
+            name: result["serverInfo"]["name"].as_str().map(|s| s.to_string()),
+            version: result["serverInfo"]["version"]
+                .as_str()
+                .map(|s| s.to_string()),
+            capabilities: result.get("capabilities").cloned().unwrap_or(Value::Null),
+        };
+        if let Connection::Http(conn) = &mut client.connection {
+            conn.protocol_version = client.info.protocol_version.clone();
+        }
+
+        client
+            .notify("notifications/initialized", Value::Null)
+            .await?;
+        Ok(client)
+    }
+
+    pub fn has_capability(&self, name: &str) -> bool {
+        self.info
+            .capabilities
+            .get(name)
+            .map(|v| !v.is_null())
+            .unwrap_or(false)
+    }
+
+    /// Sends a JSON-RPC request and waits for its response
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut message = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        if !params.is_null() {
+            message["params"] = params;
+        }
+
+        let timeout = self.timeout;
+        let exchange = async {
+            self.connection.send(&message).await?;
+            loop {
+                let incoming = self.connection.recv().await?;
+
+                // Requests and notifications from the server
+                if let Some(server_method) = incoming.get("method").and_then(|m| m.as_str()) {
+                    if let Some(request_id) = incoming.get("id") {
+                        let reply = if server_method == "ping" {
+                            json!({ "jsonrpc": "2.0", "id": request_id, "result": {} })
+                        } else {
+                            json!({
+                                "jsonrpc": "2.0",
+                                "id": request_id,
+                                "error": { "code": -32601, "message": "Method not found" },
+                            })
+                        };
+                        self.connection.send(&reply).await?;
+                    }
+                    continue;
+                }
+
+                if incoming.get("id").and_then(|v| v.as_u64()) != Some(id) {
+                    continue;
+                }
+                if let Some(error) = incoming.get("error") {
+                    let message = error["message"].as_str().unwrap_or("unknown error");
+                    return Err(format!("{} failed: {}", method, message));
+                }
+                return Ok(incoming.get("result").cloned().unwrap_or(Value::Null));
+            }
+        };
+
+        tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| format!("Timed out waiting for {} response", method))?
+    }
+
+    pub async fn notify(&mut self, method: &str, params: Value) -> Result<(), String> {
+        let mut message = json!({ "jsonrpc": "2.0", "method": method });
+        if !params.is_null() {
+            message["params"] = params;
+        }
+        self.connection.send(&message).await
+    }
+
+    /// Calls a paginated `*/list` method and concatenates `key` from every page
+    async fn list_all(&mut self, method: &str, key: &str) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => Value::Null,
+            };
+            let result = self.request(method, params).await?;
+            if let Some(page) = result.get(key).and_then(|v| v.as_array()) {
+                items.extend(page.iter().cloned());
+            }
+            cursor = result["nextCursor"].as_str().map(|s| s.to_string());
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// `tools/list`, or nothing when the server does not offer tools
+    pub async fn list_tools(&mut self) -> Result<Vec<Value>, String> {
+        if !self.has_capability("tools") {
+            return Ok(Vec::new());
+        }
+        self.list_all("tools/list", "tools").await
+    }
+
+    pub async fn list_resources(&mut self) -> Result<Vec<Value>, String> {
+        if !self.has_capability("resources") {
+            return Ok(Vec::new());
+        }
+        self.list_all("resources/list", "resources").await
+    }
+
+    pub async fn list_prompts(&mut self) -> Result<Vec<Value>, String> {
+        if !self.has_capability("prompts") {
+            return Ok(Vec::new());
+        }
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    /// Invokes a tool and returns the raw `tools/call` result
+    pub async fn call_tool(&mut self, name: &str, arguments: Value) -> Result<Value, String> {
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    /// Ends the session and stops stdio servers
+    pub async fn close(self) {
+        self.connection.close().await;
+    }
+}
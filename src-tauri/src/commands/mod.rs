@@ -1,15 +1,27 @@
+pub mod budget;
+pub mod checkpoint;
 pub mod claude;
 pub mod clipboard;
+pub mod code_attribution;
 pub mod context_commands;
 pub mod context_manager;
+pub mod context_meter;
 pub mod enhanced_hooks;
 pub mod extensions;
 pub mod file_operations;
+pub mod file_search;
 pub mod git_stats;
+pub mod hook_conditions;
 pub mod mcp;
+pub mod mcp_client;
+pub mod model_capabilities;
 pub mod permission_config;
+pub mod pre_commit_review;
+pub mod pricing;
 pub mod prompt_tracker;
 pub mod provider;
+pub mod session_export;
+pub mod session_search;
 pub mod simple_git;
 pub mod slash_commands;
 pub mod storage;
@@ -0,0 +1,299 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::command;
+
+use super::pricing::{compile_pattern, current_provider};
+
+/// Bundled context window / output limits
+const BUNDLED_CAPABILITIES: &str = include_str!("model_capabilities.json");
+
+/// User overrides live next to the other Claude config files
+const USER_CAPABILITIES_FILE: &str = "model_capabilities.json";
+
+/// Limits of one model family
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelCapability {
+    /// Stable identifier; a user entry with the same id replaces the bundled one
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Case-insensitive regex matched against the model name
+    pub pattern: String,
+    /// Exact names that select this entry before any pattern is tried (e.g. "sonnet1m")
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Restricts the entry to a provider (substring of ANTHROPIC_BASE_URL host)
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Context window in tokens
+    pub context_window: usize,
+    /// Maximum output tokens of a single response
+    pub max_output_tokens: usize,
+    /// Where the entry came from ("bundled" or "user"), filled in on load
+    #[serde(default, skip_deserializing)]
+    pub source: String,
+}
+
+/// On-disk capability file format (shared by the bundled and user files)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapabilityFile {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub models: Vec<ModelCapability>,
+}
+
+/// Merged view of bundled and user entries with compiled patterns
+pub struct CapabilityRegistry {
+    entries: Vec<(ModelCapability, Regex)>,
+}
+
+impl CapabilityRegistry {
+    /// Loads the bundled table and merges the user file from ~/.claude on top of it
+    pub fn load() -> Self {
+        let bundled: CapabilityFile =
+            serde_json::from_str(BUNDLED_CAPABILITIES).unwrap_or_else(|e| {
+                log::error!("Failed to parse bundled model capability table: {}", e);
+                CapabilityFile::default()
+            });
+        let user = load_user_capabilities().unwrap_or_else(|e| {
+            log::warn!("Ignoring user model capability file: {}", e);
+            CapabilityFile::default()
+        });
+        Self::merge(bundled, user)
+    }
+
+    fn merge(bundled: CapabilityFile, user: CapabilityFile) -> Self {
+        // User entries take precedence: they are matched first and replace bundled ids
+        let user_ids: Vec<String> = user.models.iter().map(|m| m.id.clone()).collect();
+        let models = user.models.into_iter().map(|m| (m, "user")).chain(
+            bundled
+                .models
+                .into_iter()
+                .filter(|m| !user_ids.contains(&m.id))
+                .map(|m| (m, "bundled")),
+        );
+
+        let mut entries = Vec::new();
+        for (mut capability, source) in models {
+            capability.source = source.to_string();
+            match compile_pattern(&capability.pattern) {
+                Ok(regex) => entries.push((capability, regex)),
+                Err(e) => log::warn!("Skipping capability entry '{}': {}", capability.id, e),
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Finds the entry for a model or alias. Aliases win over patterns,
+    /// provider-specific entries win over generic ones.
+    pub fn find(&self, model: &str, provider: Option<&str>) -> Option<&ModelCapability> {
+        let provider = provider.map(|p| p.to_lowercase());
+        let matches = |capability: &ModelCapability| match (&capability.provider, &provider) {
+            (None, _) => true,
+            (Some(wanted), Some(actual)) => actual.contains(&wanted.to_lowercase()),
+            (Some(_), None) => false,
+        };
+        let is_alias = |capability: &ModelCapability| {
+            capability
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(model))
+        };
+
+        let candidates = self
+            .entries
+            .iter()
+            .filter(|(capability, _)| matches(capability));
+        let aliased = candidates
+            .clone()
+            .filter(|(capability, _)| is_alias(capability));
+        let patterned = candidates.filter(|(_, regex)| regex.is_match(model));
+
+        let mut generic = None;
+        for (capability, _) in aliased.chain(patterned) {
+            if capability.provider.is_some() {
+                return Some(capability);
+            }
+            if generic.is_none() {
+                generic = Some(capability);
+            }
+        }
+        generic
+    }
+
+    pub fn entries(&self) -> Vec<ModelCapability> {
+        self.entries
+            .iter()
+            .map(|(capability, _)| capability.clone())
+            .collect()
+    }
+}
+
+static REGISTRY: once_cell::sync::Lazy<RwLock<CapabilityRegistry>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(CapabilityRegistry::load()));
+
+/// Active provider host and its ANTHROPIC_MODEL
+#[derive(Debug, Clone)]
+struct ProviderRouting {
+    provider: Option<String>,
+    model: Option<String>,
+}
+
+/// Read from settings.json on first use; cleared on provider switch and registry reload
+static PROVIDER_ROUTING: once_cell::sync::Lazy<RwLock<Option<ProviderRouting>>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(None));
+
+fn get_user_capabilities_path() -> Result<PathBuf, String> {
+    let claude_dir = super::claude::get_claude_dir().map_err(|e| e.to_string())?;
+    Ok(claude_dir.join(USER_CAPABILITIES_FILE))
+}
+
+fn load_user_capabilities() -> Result<CapabilityFile, String> {
+    let path = get_user_capabilities_path()?;
+    if !path.exists() {
+        return Ok(CapabilityFile::default());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read model capability file: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse model capability file: {}", e))
+}
+
+fn save_user_capabilities(file: &CapabilityFile) -> Result<(), String> {
+    let path = get_user_capabilities_path()?;
+    let content = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize model capability file: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write model capability file: {}", e))
+}
+
+fn reload_registry() -> Result<(), String> {
+    let mut registry = REGISTRY.write().map_err(|e| e.to_string())?;
+    *registry = CapabilityRegistry::load();
+    invalidate_provider_cache();
+    Ok(())
+}
+
+/// Forgets the cached provider routing so the next `resolve` re-reads settings.json
+pub fn invalidate_provider_cache() {
+    if let Ok(mut routing) = PROVIDER_ROUTING.write() {
+        *routing = None;
+    }
+}
+
+fn provider_routing() -> ProviderRouting {
+    if let Some(routing) = PROVIDER_ROUTING.read().ok().and_then(|r| r.clone()) {
+        return routing;
+    }
+    let routing = ProviderRouting {
+        provider: current_provider(),
+        model: provider_model(),
+    };
+    if let Ok(mut cached) = PROVIDER_ROUTING.write() {
+        *cached = Some(routing.clone());
+    }
+    routing
+}
+
+/// ANTHROPIC_MODEL configured by the active provider in settings.json
+fn provider_model() -> Option<String> {
+    let settings_path = super::claude::get_claude_dir().ok()?.join("settings.json");
+    let content = fs::read_to_string(settings_path).ok()?;
+    let settings: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let model = settings
+        .get("env")?
+        .get("ANTHROPIC_MODEL")?
+        .as_str()?
+        .trim();
+    (!model.is_empty()).then(|| model.to_string())
+}
+
+/// Capability of the model a session actually talks to.
+/// When a third-party provider is active, Claude aliases such as "sonnet" are routed
+/// to the provider's ANTHROPIC_MODEL, so that model's limits apply instead.
+pub fn resolve(model: &str) -> Option<ModelCapability> {
+    let ProviderRouting {
+        provider,
+        model: provider_model,
+    } = provider_routing();
+    let registry = REGISTRY.read().ok()?;
+
+    let routed = match (&provider, provider_model) {
+        (Some(_), Some(provider_model))
+            if registry
+                .entries
+                .iter()
+                .any(|(c, _)| c.aliases.iter().any(|a| a.eq_ignore_ascii_case(model))) =>
+        {
+            provider_model
+        }
+        _ => model.to_string(),
+    };
+
+    registry.find(&routed, provider.as_deref()).cloned()
+}
+
+/// Capability table as shown in the settings UI
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CapabilityTable {
+    pub models: Vec<ModelCapability>,
+    pub user_file: String,
+}
+
+/// Lists the merged capability table (user overrides first)
+#[command]
+pub fn list_model_capabilities() -> Result<CapabilityTable, String> {
+    let registry = REGISTRY.read().map_err(|e| e.to_string())?;
+    Ok(CapabilityTable {
+        models: registry.entries(),
+        user_file: get_user_capabilities_path()?.to_string_lossy().to_string(),
+    })
+}
+
+/// Adds or replaces a user capability entry
+#[command]
+pub fn save_model_capability(capability: ModelCapability) -> Result<(), String> {
+    if capability.id.trim().is_empty() {
+        return Err("Capability entry id cannot be empty".to_string());
+    }
+    if capability.context_window == 0 {
+        return Err("Context window must be greater than 0".to_string());
+    }
+    compile_pattern(&capability.pattern)?;
+
+    let mut user = load_user_capabilities()?;
+    match user.models.iter_mut().find(|m| m.id == capability.id) {
+        Some(existing) => *existing = capability,
+        None => user.models.push(capability),
+    }
+    if user.version == 0 {
+        user.version = 1;
+    }
+    save_user_capabilities(&user)?;
+
+    reload_registry()
+}
+
+/// Removes a user capability entry (falling back to the bundled entry if one exists)
+#[command]
+pub fn delete_model_capability(id: String) -> Result<(), String> {
+    let mut user = load_user_capabilities()?;
+    let before = user.models.len();
+    user.models.retain(|m| m.id != id);
+    if user.models.len() == before {
+        return Err(format!("No user capability entry with id '{}'", id));
+    }
+    save_user_capabilities(&user)?;
+
+    reload_registry()
+}
+
+/// Re-reads the capability files
+#[command]
+pub fn reload_model_capabilities() -> Result<(), String> {
+    reload_registry()
+}
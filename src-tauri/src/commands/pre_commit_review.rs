@@ -0,0 +1,729 @@
+//! 提交前代码审查
+//!
+//! 收集暂存区 diff（遵循 `exclude_patterns` 与 `max_files_to_review`），
+//! 通过无头 `claude --print` 按固定 JSON 契约执行审查，计算质量分数并
+//! 根据 `PreCommitCodeReviewConfig` 的阈值给出 `Allow` / `Block` 决策。
+//!
+//! 同一套逻辑既供 Tauri 命令使用，也供安装到仓库中的 git `pre-commit`
+//! shim 调用（shim 以 `--pre-commit-review` 参数无界面启动本程序）。
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+
+use super::enhanced_hooks::{CommitDecision, PreCommitCodeReviewConfig};
+
+/// 发送给审查模型的 diff 最大字节数
+const MAX_DIFF_BYTES: usize = 200_000;
+/// 单次审查的超时时间
+const REVIEW_TIMEOUT_SECS: u64 = 300;
+/// staged diff 是不可信输入：审查时禁用全部工具（包括只读工具），防止 diff 中注入的指令
+/// 读取任意文件；配合 `--max-turns 1`，任何工具调用都会让审查以 error_max_turns 结束
+const REVIEW_DISALLOWED_TOOLS: &str = "Bash,BashOutput,KillShell,Edit,MultiEdit,Write,\
+    NotebookEdit,Read,Grep,Glob,LS,WebFetch,WebSearch,Task,TodoWrite,SlashCommand";
+/// shim 脚本中的标记，用于识别由本程序安装的 hook
+const SHIM_MARKER: &str = "# claude-workbench pre-commit review shim";
+/// shim 旁边保存审查配置的文件名
+const SHIM_CONFIG_FILE: &str = "claude-workbench-review.json";
+/// 无界面审查模式的命令行参数
+pub const PRE_COMMIT_REVIEW_ARG: &str = "--pre-commit-review";
+
+/// 暂存区中的一个待审查文件
+#[derive(Debug, Clone)]
+struct StagedFile {
+    path: String,
+    added: usize,
+    deleted: usize,
+}
+
+/// 收集到的暂存区变更
+#[derive(Debug, Default)]
+struct StagedChanges {
+    files: Vec<StagedFile>,
+    excluded: usize,
+    skipped_binary: usize,
+    skipped_over_limit: usize,
+    diff: String,
+    truncated: bool,
+}
+
+/// 问题严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueSeverity {
+    Critical,
+    Major,
+    Minor,
+}
+
+impl IssueSeverity {
+    /// 宽松解析模型返回的严重程度
+    fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "critical" | "blocker" | "severe" => IssueSeverity::Critical,
+            "major" | "high" | "error" => IssueSeverity::Major,
+            _ => IssueSeverity::Minor,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            IssueSeverity::Critical => "critical",
+            IssueSeverity::Major => "major",
+            IssueSeverity::Minor => "minor",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for IssueSeverity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(IssueSeverity::parse(&value))
+    }
+}
+
+/// 审查发现的问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewIssue {
+    pub severity: IssueSeverity,
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub line: Option<u64>,
+    pub message: String,
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+/// 模型返回的结构化审查报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReviewReport {
+    #[serde(default)]
+    pub score: Option<f64>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub issues: Vec<ReviewIssue>,
+}
+
+impl ReviewReport {
+    fn count(&self, severity: IssueSeverity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+}
+
+/// 在仓库目录中运行 git 并返回 stdout
+async fn run_git(project_path: &str, args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args).current_dir(project_path);
+
+    #[cfg(target_os = "windows")]
+    {
+        cmd.creation_flags(0x08000000);
+    }
+
+    let output = cmd
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// 判断文件是否命中排除模式（模式不含 `/` 时同时匹配文件名）
+fn is_excluded(path: &str, patterns: &[glob::Pattern]) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    patterns.iter().any(|pattern| {
+        pattern.matches(path) || (!pattern.as_str().contains('/') && pattern.matches(file_name))
+    })
+}
+
+/// 收集暂存区 diff，遵循排除模式和最大文件数
+async fn collect_staged_changes(
+    project_path: &str,
+    config: &PreCommitCodeReviewConfig,
+) -> Result<StagedChanges, String> {
+    let patterns: Vec<glob::Pattern> = config
+        .exclude_patterns
+        .iter()
+        .filter_map(|p| match glob::Pattern::new(p) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                warn!("Ignoring invalid exclude pattern '{}': {}", p, e);
+                None
+            }
+        })
+        .collect();
+
+    // -z 配合 --no-renames 时每条记录为 "added\tdeleted\tpath\0"
+    let numstat = run_git(
+        project_path,
+        &[
+            "diff",
+            "--cached",
+            "--numstat",
+            "--no-renames",
+            "-z",
+            "--diff-filter=ACM",
+        ],
+    )
+    .await?;
+
+    let mut changes = StagedChanges::default();
+    for record in String::from_utf8_lossy(&numstat).split('\0') {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+
+        if is_excluded(path, &patterns) {
+            changes.excluded += 1;
+            continue;
+        }
+        // 二进制文件的 numstat 为 "-"
+        let (Ok(added), Ok(deleted)) = (added.parse::<usize>(), deleted.parse::<usize>()) else {
+            changes.skipped_binary += 1;
+            continue;
+        };
+        if changes.files.len() >= config.max_files_to_review {
+            changes.skipped_over_limit += 1;
+            continue;
+        }
+        changes.files.push(StagedFile {
+            path: path.to_string(),
+            added,
+            deleted,
+        });
+    }
+
+    if changes.files.is_empty() {
+        return Ok(changes);
+    }
+
+    let mut args = vec![
+        "diff",
+        "--cached",
+        "--no-renames",
+        "--no-color",
+        "--unified=3",
+        "--",
+    ];
+    args.extend(changes.files.iter().map(|f| f.path.as_str()));
+    let diff = run_git(project_path, &args).await?;
+
+    let mut diff = String::from_utf8_lossy(&diff).into_owned();
+    if diff.len() > MAX_DIFF_BYTES {
+        let mut cut = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        diff.truncate(cut);
+        changes.truncated = true;
+    }
+    changes.diff = diff;
+
+    Ok(changes)
+}
+
+/// 构造审查提示词（含 JSON 输出契约）
+fn build_review_prompt(config: &PreCommitCodeReviewConfig, changes: &StagedChanges) -> String {
+    let focus = match config.review_scope.as_str() {
+        "security" => "Focus on security: injection, authn/authz mistakes, secrets in code, unsafe deserialization, path traversal and similar vulnerabilities.",
+        "performance" => "Focus on performance: algorithmic complexity, needless allocations or copies, blocking calls on hot paths, N+1 queries and resource leaks.",
+        _ => "Review correctness, security, performance and maintainability.",
+    };
+
+    let file_list = changes
+        .files
+        .iter()
+        .map(|f| format!("- {} (+{} -{})", f.path, f.added, f.deleted))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let truncated_note = if changes.truncated {
+        "\nNote: the diff was truncated; only review what is shown.\n"
+    } else {
+        ""
+    };
+
+    format!(
+        "You are reviewing staged changes right before a git commit. {focus}\n\
+        Do not use any tools; review only the diff below.\n\
+        \n\
+        Respond with a single JSON object and nothing else, using exactly this shape:\n\
+        {{\n\
+          \"score\": <number from 0 to 10, overall quality of the change>,\n\
+          \"summary\": \"<one or two sentences>\",\n\
+          \"issues\": [\n\
+            {{\n\
+              \"severity\": \"critical\" | \"major\" | \"minor\",\n\
+              \"file\": \"<path>\",\n\
+              \"line\": <line number in the new file or null>,\n\
+              \"message\": \"<what is wrong>\",\n\
+              \"suggestion\": \"<how to fix it, or null>\"\n\
+            }}\n\
+          ]\n\
+        }}\n\
+        \n\
+        Use \"critical\" only for bugs or vulnerabilities that must not be committed, \
+        \"major\" for significant problems that should be fixed soon, and \"minor\" for style or small improvements.\n\
+        \n\
+        Files ({count}):\n{file_list}\n{truncated_note}\n\
+        Diff:\n{diff}\n",
+        focus = focus,
+        count = changes.files.len(),
+        file_list = file_list,
+        truncated_note = truncated_note,
+        diff = changes.diff,
+    )
+}
+
+/// 从模型输出中提取 JSON 审查报告
+fn parse_review_output(stdout: &str) -> Result<ReviewReport, String> {
+    // --output-format json 输出外层结果对象，审查文本位于 result 字段
+    let text = match serde_json::from_str::<serde_json::Value>(stdout.trim()) {
+        Ok(outer) if outer.get("result").is_some() => {
+            if outer.get("is_error").and_then(|v| v.as_bool()) == Some(true) {
+                return Err(format!(
+                    "Claude review failed: {}",
+                    outer["result"].as_str().unwrap_or("unknown error")
+                ));
+            }
+            outer["result"].as_str().unwrap_or_default().to_string()
+        }
+        _ => stdout.to_string(),
+    };
+
+    let start = text
+        .find('{')
+        .ok_or_else(|| "Review output did not contain a JSON object".to_string())?;
+    let end = text
+        .rfind('}')
+        .filter(|end| *end > start)
+        .ok_or_else(|| "Review output did not contain a JSON object".to_string())?;
+
+    serde_json::from_str::<ReviewReport>(&text[start..=end])
+        .map_err(|e| format!("Failed to parse review JSON: {}", e))
+}
+
+/// 计算质量分数：取模型给出的分数与按问题数量扣分后的分数中较低者
+pub fn score_review(report: &ReviewReport) -> f64 {
+    let penalty = report.count(IssueSeverity::Critical) as f64 * 3.0
+        + report.count(IssueSeverity::Major) as f64 * 1.5
+        + report.count(IssueSeverity::Minor) as f64 * 0.25;
+    let derived = (10.0 - penalty).max(0.0);
+
+    match report.score {
+        Some(score) if score.is_finite() => score.clamp(0.0, 10.0).min(derived),
+        _ => derived,
+    }
+}
+
+fn format_issue(issue: &ReviewIssue) -> String {
+    let location = match (&issue.file, issue.line) {
+        (Some(file), Some(line)) => format!("{}:{}", file, line),
+        (Some(file), None) => file.clone(),
+        _ => "-".to_string(),
+    };
+    format!(
+        "[{}] {} {}",
+        issue.severity.as_str(),
+        location,
+        issue.message
+    )
+}
+
+/// 根据配置阈值给出提交决策
+fn decide(
+    config: &PreCommitCodeReviewConfig,
+    changes: &StagedChanges,
+    report: &ReviewReport,
+) -> CommitDecision {
+    let score = score_review(report);
+    let critical = report.count(IssueSeverity::Critical);
+    let major = report.count(IssueSeverity::Major);
+
+    let mut reasons = Vec::new();
+    if config.block_critical_issues && critical > 0 {
+        reasons.push(format!("{} critical issue(s)", critical));
+    }
+    if config.block_major_issues && major > 0 {
+        reasons.push(format!("{} major issue(s)", major));
+    }
+    if score < config.quality_threshold {
+        reasons.push(format!(
+            "quality score {:.1} is below the threshold {:.1}",
+            score, config.quality_threshold
+        ));
+    }
+
+    let suggestions = if config.show_suggestions {
+        report
+            .issues
+            .iter()
+            .filter_map(|issue| {
+                let suggestion = issue.suggestion.as_deref()?.trim();
+                if suggestion.is_empty() {
+                    return None;
+                }
+                Some(match &issue.file {
+                    Some(file) => format!("{}: {}", file, suggestion),
+                    None => suggestion.to_string(),
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    if reasons.is_empty() {
+        return CommitDecision::Allow {
+            message: format!(
+                "Code review passed: score {:.1}/10, {} file(s) reviewed, {} issue(s). {}",
+                score,
+                changes.files.len(),
+                report.issues.len(),
+                report.summary
+            )
+            .trim_end()
+            .to_string(),
+            suggestions,
+        };
+    }
+
+    let mut details = vec![format!(
+        "Score: {:.1}/10 ({} file(s) reviewed)",
+        score,
+        changes.files.len()
+    )];
+    if !report.summary.is_empty() {
+        details.push(report.summary.clone());
+    }
+    details.extend(report.issues.iter().map(format_issue));
+
+    CommitDecision::Block {
+        reason: format!("Commit blocked: {}", reasons.join("; ")),
+        details: details.join("\n"),
+        suggestions,
+    }
+}
+
+/// 以无头模式运行 claude 审查并返回结构化报告
+async fn run_claude_review(
+    claude_path: &str,
+    project_path: &str,
+    prompt: &str,
+) -> Result<ReviewReport, String> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut cmd = Command::from(crate::claude_binary::create_command_with_env(claude_path));
+    cmd.args([
+        "--print",
+        "--output-format",
+        "json",
+        "--max-turns",
+        "1",
+        "--strict-mcp-config",
+        "--disallowedTools",
+        REVIEW_DISALLOWED_TOOLS,
+    ])
+    .current_dir(project_path)
+    .stdin(std::process::Stdio::piped())
+    .stdout(std::process::Stdio::piped())
+    .stderr(std::process::Stdio::piped())
+    .kill_on_drop(true);
+
+    #[cfg(target_os = "windows")]
+    {
+        cmd.creation_flags(0x08000000);
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start Claude CLI for review: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(prompt.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to send review prompt: {}", e))?;
+        stdin
+            .shutdown()
+            .await
+            .map_err(|e| format!("Failed to close review stdin: {}", e))?;
+    }
+
+    let output = tokio::time::timeout(
+        Duration::from_secs(REVIEW_TIMEOUT_SECS),
+        child.wait_with_output(),
+    )
+    .await
+    .map_err(|_| format!("Code review timed out after {}s", REVIEW_TIMEOUT_SECS))?
+    .map_err(|e| format!("Failed to wait for Claude review: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Claude review exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    parse_review_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 审查暂存区变更并给出提交决策
+pub async fn review_staged_changes(
+    claude_path: &str,
+    project_path: &str,
+    config: &PreCommitCodeReviewConfig,
+) -> Result<CommitDecision, String> {
+    if !config.enabled {
+        return Ok(CommitDecision::Allow {
+            message: "Pre-commit code review is disabled".to_string(),
+            suggestions: vec![],
+        });
+    }
+
+    let changes = collect_staged_changes(project_path, config).await?;
+    if changes.files.is_empty() {
+        return Ok(CommitDecision::Allow {
+            message: format!(
+                "No reviewable staged changes ({} excluded, {} binary)",
+                changes.excluded, changes.skipped_binary
+            ),
+            suggestions: vec![],
+        });
+    }
+
+    info!(
+        "Reviewing {} staged file(s) in {} ({} excluded, {} binary, {} over limit)",
+        changes.files.len(),
+        project_path,
+        changes.excluded,
+        changes.skipped_binary,
+        changes.skipped_over_limit
+    );
+
+    let prompt = build_review_prompt(config, &changes);
+    let report = run_claude_review(claude_path, project_path, &prompt).await?;
+    let decision = decide(config, &changes, &report);
+
+    if changes.skipped_over_limit > 0 {
+        warn!(
+            "{} staged file(s) were not reviewed (max_files_to_review = {})",
+            changes.skipped_over_limit, config.max_files_to_review
+        );
+    }
+    Ok(decision)
+}
+
+// ============ git pre-commit shim ============
+
+/// 解析仓库实际使用的 hooks 目录（支持 worktree 与 core.hooksPath）
+async fn resolve_hooks_dir(project_path: &str) -> Result<PathBuf, String> {
+    let output = run_git(project_path, &["rev-parse", "--git-path", "hooks"]).await?;
+    let hooks = PathBuf::from(String::from_utf8_lossy(&output).trim());
+    Ok(if hooks.is_absolute() {
+        hooks
+    } else {
+        Path::new(project_path).join(hooks)
+    })
+}
+
+/// 为 sh 双引号字符串转义
+fn shell_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// 安装 git pre-commit shim，返回 hook 文件路径
+#[tauri::command]
+pub async fn install_pre_commit_hook(
+    project_path: String,
+    config: Option<PreCommitCodeReviewConfig>,
+) -> Result<String, String> {
+    let hooks_dir = resolve_hooks_dir(&project_path).await?;
+    std::fs::create_dir_all(&hooks_dir)
+        .map_err(|e| format!("Failed to create hooks directory: {}", e))?;
+
+    let hook_path = hooks_dir.join("pre-commit");
+    if hook_path.exists() {
+        let existing = std::fs::read_to_string(&hook_path).unwrap_or_default();
+        if !existing.contains(SHIM_MARKER) {
+            return Err(format!(
+                "A pre-commit hook already exists at {}; remove it first",
+                hook_path.display()
+            ));
+        }
+    }
+
+    let config_path = hooks_dir.join(SHIM_CONFIG_FILE);
+    let config_json = serde_json::to_string_pretty(&config.unwrap_or_default())
+        .map_err(|e| format!("Failed to serialize review config: {}", e))?;
+    std::fs::write(&config_path, config_json)
+        .map_err(|e| format!("Failed to write review config: {}", e))?;
+
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate application executable: {}", e))?;
+    // Git for Windows 同样通过 sh 执行 hook，统一使用正斜杠
+    let exe = exe.to_string_lossy().replace('\\', "/");
+    let config_arg = config_path.to_string_lossy().replace('\\', "/");
+
+    let script = format!(
+        "#!/bin/sh\n\
+        {marker}\n\
+        # Set WORKBENCH_SKIP_REVIEW=1 or use `git commit --no-verify` to skip.\n\
+        [ -n \"$WORKBENCH_SKIP_REVIEW\" ] && exit 0\n\
+        exec {exe} {arg} \"$(git rev-parse --show-toplevel)\" --config {config}\n",
+        marker = SHIM_MARKER,
+        exe = shell_quote(&exe),
+        arg = PRE_COMMIT_REVIEW_ARG,
+        config = shell_quote(&config_arg),
+    );
+    std::fs::write(&hook_path, script)
+        .map_err(|e| format!("Failed to write pre-commit hook: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make pre-commit hook executable: {}", e))?;
+    }
+
+    info!(
+        "Installed pre-commit review shim at {}",
+        hook_path.display()
+    );
+    Ok(hook_path.to_string_lossy().to_string())
+}
+
+/// 移除由本程序安装的 git pre-commit shim
+#[tauri::command]
+pub async fn uninstall_pre_commit_hook(project_path: String) -> Result<(), String> {
+    let hooks_dir = resolve_hooks_dir(&project_path).await?;
+    let hook_path = hooks_dir.join("pre-commit");
+
+    if hook_path.exists() {
+        let existing = std::fs::read_to_string(&hook_path).unwrap_or_default();
+        if !existing.contains(SHIM_MARKER) {
+            return Err(format!(
+                "The pre-commit hook at {} was not installed by this application",
+                hook_path.display()
+            ));
+        }
+        std::fs::remove_file(&hook_path)
+            .map_err(|e| format!("Failed to remove pre-commit hook: {}", e))?;
+    }
+
+    let config_path = hooks_dir.join(SHIM_CONFIG_FILE);
+    if config_path.exists() {
+        std::fs::remove_file(&config_path)
+            .map_err(|e| format!("Failed to remove review config: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 处理 `--pre-commit-review <repo> [--config <file>]` 命令行入口
+///
+/// 返回 `Some(exit_code)` 表示以无界面模式运行完毕，`None` 表示正常启动应用。
+/// 审查本身出错（如未安装 Claude CLI）时不阻止提交，只打印警告。
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|a| a == PRE_COMMIT_REVIEW_ARG)?;
+
+    let project_path = match args.get(index + 1) {
+        Some(path) if !path.starts_with("--") => path.clone(),
+        _ => std::env::current_dir()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| ".".to_string()),
+    };
+
+    let config = match args
+        .iter()
+        .position(|a| a == "--config")
+        .and_then(|i| args.get(i + 1))
+    {
+        Some(path) => match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+        {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("claude-workbench: invalid review config {}: {}", path, e);
+                PreCommitCodeReviewConfig::default()
+            }
+        },
+        None => PreCommitCodeReviewConfig::default(),
+    };
+
+    // Same lookup as the app, so the user's selected installation is honored
+    let claude_path = crate::claude_binary::find_claude_binary_in(
+        crate::claude_binary::headless_app_data_dir().as_deref(),
+    )
+    .unwrap_or_else(|_| "claude".to_string());
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("claude-workbench: failed to start runtime: {}", e);
+            return Some(0);
+        }
+    };
+
+    eprintln!("claude-workbench: reviewing staged changes...");
+    let result = runtime.block_on(review_staged_changes(&claude_path, &project_path, &config));
+
+    Some(match result {
+        Ok(CommitDecision::Allow {
+            message,
+            suggestions,
+        }) => {
+            eprintln!("{}", message);
+            for suggestion in suggestions {
+                eprintln!("  - {}", suggestion);
+            }
+            0
+        }
+        Ok(CommitDecision::Block {
+            reason,
+            details,
+            suggestions,
+        }) => {
+            eprintln!("{}\n{}", reason, details);
+            for suggestion in suggestions {
+                eprintln!("  - {}", suggestion);
+            }
+            eprintln!("Use `git commit --no-verify` to bypass the review.");
+            1
+        }
+        Err(e) => {
+            eprintln!("claude-workbench: code review skipped: {}", e);
+            0
+        }
+    })
+}
@@ -0,0 +1,353 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{command, State};
+
+use super::storage::AgentDb;
+
+/// Bundled default prices (per million tokens)
+const BUNDLED_PRICING: &str = include_str!("model_pricing.json");
+
+/// User overrides live next to the other Claude config files
+const USER_PRICING_FILE: &str = "model_pricing.json";
+
+/// Higher price tier applied when a request's prompt exceeds a token threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LongContextPrice {
+    /// Prompt size (input + cache tokens) above which this tier applies
+    pub threshold_tokens: u64,
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+/// Price entry for one model family, prices are per million tokens in `currency`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Stable identifier; a user entry with the same id replaces the bundled one
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Case-insensitive regex matched against the model name
+    pub pattern: String,
+    /// Restricts the entry to a provider (substring of ANTHROPIC_BASE_URL host)
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub long_context: Option<LongContextPrice>,
+    /// Where the entry came from ("bundled" or "user"), filled in on load
+    #[serde(default, skip_deserializing)]
+    pub source: String,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+/// On-disk pricing file format (shared by the bundled and user files)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PricingFile {
+    #[serde(default)]
+    pub version: u32,
+    /// Conversion rates into USD, e.g. {"CNY": 0.14}
+    #[serde(default)]
+    pub usd_rates: HashMap<String, f64>,
+    #[serde(default)]
+    pub models: Vec<ModelPrice>,
+}
+
+/// Token counts of a single request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+}
+
+/// Merged view of bundled and user prices with compiled patterns
+pub struct PricingRegistry {
+    entries: Vec<(ModelPrice, Regex)>,
+    usd_rates: HashMap<String, f64>,
+}
+
+impl PricingRegistry {
+    /// Loads the bundled table and merges the user file from ~/.claude on top of it
+    pub fn load() -> Self {
+        let bundled: PricingFile = serde_json::from_str(BUNDLED_PRICING).unwrap_or_else(|e| {
+            log::error!("Failed to parse bundled pricing table: {}", e);
+            PricingFile::default()
+        });
+        let user = load_user_pricing().unwrap_or_else(|e| {
+            log::warn!("Ignoring user pricing file: {}", e);
+            PricingFile::default()
+        });
+        Self::merge(bundled, user)
+    }
+
+    fn merge(bundled: PricingFile, user: PricingFile) -> Self {
+        let mut usd_rates = bundled.usd_rates;
+        usd_rates.extend(user.usd_rates);
+
+        // User entries take precedence: they are matched first and replace bundled ids
+        let user_ids: Vec<String> = user.models.iter().map(|m| m.id.clone()).collect();
+        let models = user
+            .models
+            .into_iter()
+            .map(|m| (m, "user"))
+            .chain(
+                bundled
+                    .models
+                    .into_iter()
+                    .filter(|m| !user_ids.contains(&m.id))
+                    .map(|m| (m, "bundled")),
+            );
+
+        let mut entries = Vec::new();
+        for (mut price, source) in models {
+            price.source = source.to_string();
+            match compile_pattern(&price.pattern) {
+                Ok(regex) => entries.push((price, regex)),
+                Err(e) => log::warn!("Skipping pricing entry '{}': {}", price.id, e),
+            }
+        }
+
+        Self { entries, usd_rates }
+    }
+
+    /// Finds the price entry for a model. Provider-specific entries win over generic ones.
+    pub fn find(&self, model: &str, provider: Option<&str>) -> Option<&ModelPrice> {
+        let provider = provider.map(|p| p.to_lowercase());
+        let matches = |price: &ModelPrice| match (&price.provider, &provider) {
+            (None, _) => true,
+            (Some(wanted), Some(actual)) => actual.contains(&wanted.to_lowercase()),
+            (Some(_), None) => false,
+        };
+
+        let mut generic = None;
+        for (price, regex) in &self.entries {
+            if !regex.is_match(model) || !matches(price) {
+                continue;
+            }
+            if price.provider.is_some() {
+                return Some(price);
+            }
+            if generic.is_none() {
+                generic = Some(price);
+            }
+        }
+        generic
+    }
+
+    /// Calculates the cost of a request in USD. Unknown models cost 0.0.
+    pub fn calculate_cost(&self, model: &str, provider: Option<&str>, usage: &TokenUsage) -> f64 {
+        let price = match self.find(model, provider) {
+            Some(price) => price,
+            // Return 0 for unknown models to avoid incorrect cost estimations
+            None => return 0.0,
+        };
+
+        let prompt_tokens =
+            usage.input_tokens + usage.cache_creation_tokens + usage.cache_read_tokens;
+        let (input_price, output_price, cache_write_price, cache_read_price) =
+            match &price.long_context {
+                Some(tier) if prompt_tokens > tier.threshold_tokens => {
+                    (tier.input, tier.output, tier.cache_write, tier.cache_read)
+                }
+                _ => (price.input, price.output, price.cache_write, price.cache_read),
+            };
+
+        // Calculate cost (prices are per million tokens)
+        let cost = (usage.input_tokens as f64 * input_price
+            + usage.output_tokens as f64 * output_price
+            + usage.cache_creation_tokens as f64 * cache_write_price
+            + usage.cache_read_tokens as f64 * cache_read_price)
+            / 1_000_000.0;
+
+        let rate = match self.usd_rates.get(&price.currency.to_uppercase()) {
+            Some(rate) => *rate,
+            None if price.currency.eq_ignore_ascii_case("USD") => 1.0,
+            None => {
+                log::warn!("No USD rate for currency {}, pricing {} at 0", price.currency, model);
+                0.0
+            }
+        };
+        cost * rate
+    }
+
+    pub fn entries(&self) -> Vec<ModelPrice> {
+        self.entries.iter().map(|(price, _)| price.clone()).collect()
+    }
+
+    pub fn usd_rates(&self) -> HashMap<String, f64> {
+        self.usd_rates.clone()
+    }
+}
+
+static REGISTRY: once_cell::sync::Lazy<RwLock<PricingRegistry>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(PricingRegistry::load()));
+
+pub(crate) fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+fn get_user_pricing_path() -> Result<PathBuf, String> {
+    let claude_dir = super::claude::get_claude_dir().map_err(|e| e.to_string())?;
+    Ok(claude_dir.join(USER_PRICING_FILE))
+}
+
+fn load_user_pricing() -> Result<PricingFile, String> {
+    let path = get_user_pricing_path()?;
+    if !path.exists() {
+        return Ok(PricingFile::default());
+    }
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read pricing file: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse pricing file: {}", e))
+}
+
+fn save_user_pricing(file: &PricingFile) -> Result<(), String> {
+    let path = get_user_pricing_path()?;
+    let content = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize pricing file: {}", e))?;
+    fs::write(&path, content).map_err(|e| format!("Failed to write pricing file: {}", e))
+}
+
+/// Calculates the USD cost of a request with the current pricing table
+pub fn calculate_cost(model: &str, provider: Option<&str>, usage: &TokenUsage) -> f64 {
+    match REGISTRY.read() {
+        Ok(registry) => registry.calculate_cost(model, provider, usage),
+        Err(_) => 0.0,
+    }
+}
+
+/// Returns the active provider host (from ANTHROPIC_BASE_URL in settings.json),
+/// or None when Claude talks to the official API
+pub fn current_provider() -> Option<String> {
+    let settings_path = super::claude::get_claude_dir().ok()?.join("settings.json");
+    let content = fs::read_to_string(settings_path).ok()?;
+    let settings: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let base_url = settings
+        .get("env")?
+        .get("ANTHROPIC_BASE_URL")?
+        .as_str()?
+        .trim();
+
+    let host = base_url
+        .split("://")
+        .last()
+        .unwrap_or(base_url)
+        .split('/')
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+
+    if host.is_empty() || host == "api.anthropic.com" {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// Reloads the registry after the user file changed and recomputes historical costs
+fn reload_and_recalculate(db: &AgentDb) -> Result<usize, String> {
+    {
+        let mut registry = REGISTRY.write().map_err(|e| e.to_string())?;
+        *registry = PricingRegistry::load();
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    super::usage::recalculate_usage_costs(&conn)
+}
+
+/// Pricing table as shown in the settings UI
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PricingTable {
+    pub models: Vec<ModelPrice>,
+    pub usd_rates: HashMap<String, f64>,
+    pub user_file: String,
+}
+
+/// Lists the merged pricing table (user overrides first)
+#[command]
+pub fn list_model_prices() -> Result<PricingTable, String> {
+    let registry = REGISTRY.read().map_err(|e| e.to_string())?;
+    Ok(PricingTable {
+        models: registry.entries(),
+        usd_rates: registry.usd_rates(),
+        user_file: get_user_pricing_path()?.to_string_lossy().to_string(),
+    })
+}
+
+/// Adds or replaces a user price entry and recomputes stored costs.
+/// Returns the number of usage rows whose cost was recomputed.
+#[command]
+pub fn save_model_price(db: State<'_, AgentDb>, price: ModelPrice) -> Result<usize, String> {
+    if price.id.trim().is_empty() {
+        return Err("Pricing entry id cannot be empty".to_string());
+    }
+    compile_pattern(&price.pattern)?;
+
+    let mut user = load_user_pricing()?;
+    match user.models.iter_mut().find(|m| m.id == price.id) {
+        Some(existing) => *existing = price,
+        None => user.models.push(price),
+    }
+    if user.version == 0 {
+        user.version = 1;
+    }
+    save_user_pricing(&user)?;
+
+    reload_and_recalculate(&db)
+}
+
+/// Removes a user price entry (falling back to the bundled price if one exists)
+#[command]
+pub fn delete_model_price(db: State<'_, AgentDb>, id: String) -> Result<usize, String> {
+    let mut user = load_user_pricing()?;
+    let before = user.models.len();
+    user.models.retain(|m| m.id != id);
+    if user.models.len() == before {
+        return Err(format!("No user pricing entry with id '{}'", id));
+    }
+    save_user_pricing(&user)?;
+
+    reload_and_recalculate(&db)
+}
+
+/// Sets the USD conversion rate for a currency used by price entries
+#[command]
+pub fn set_currency_rate(
+    db: State<'_, AgentDb>,
+    currency: String,
+    usd_rate: f64,
+) -> Result<usize, String> {
+    if !(usd_rate.is_finite() && usd_rate >= 0.0) {
+        return Err("Exchange rate must be a non-negative number".to_string());
+    }
+    let mut user = load_user_pricing()?;
+    user.usd_rates.insert(currency.to_uppercase(), usd_rate);
+    save_user_pricing(&user)?;
+
+    reload_and_recalculate(&db)
+}
+
+/// Re-reads the pricing files and recomputes all calculated costs
+#[command]
+pub fn reload_model_prices(db: State<'_, AgentDb>) -> Result<usize, String> {
+    reload_and_recalculate(&db)
+}
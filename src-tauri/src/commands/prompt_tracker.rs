@@ -1,13 +1,16 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeSet, HashMap};
 use chrono::Utc;
 use log;
+use tauri::AppHandle;
 
-use super::simple_git;
+use super::simple_git::{self, GitRepo};
 use super::claude::get_claude_dir;
+use super::checkpoint::{self, CheckpointBackend};
+use super::git_stats::{self, FileChangeStatus};
 
 /// Rewind mode for reverting prompts
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -65,11 +68,40 @@ pub struct GitRecord {
     pub commit_after: Option<String>,
     /// Timestamp when prompt was sent
     pub timestamp: i64,
+    /// Repository the commits live in (records written before this field are project git)
+    #[serde(default)]
+    pub backend: CheckpointBackend,
+    /// Files/hunks of this prompt later restored to commit_before
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partial_reverts: Vec<PartialRevert>,
+}
+
+/// A file (optionally only some of its hunks) to restore to a prompt's commit_before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathRevertSelection {
+    /// Path relative to the repository root, as returned by get_prompt_file_diffs
+    pub path: String,
+    /// Indices into that file's hunks; None reverts the whole file
+    pub hunks: Option<Vec<usize>>,
+}
+
+/// A partial revert applied to a prompt's changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialRevert {
+    pub timestamp: i64,
+    pub selections: Vec<PathRevertSelection>,
+    /// Safety snapshot of the working tree taken just before the revert
+    pub snapshot_ref: Option<String>,
+    /// Lines of the prompt's change undone by this revert, per file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reverted_lines: Vec<git_stats::FileChangeStat>,
 }
 
 
 /// Get path to git records file
-fn get_git_records_path(session_id: &str, project_id: &str) -> Result<PathBuf> {
+pub(crate) fn get_git_records_path(session_id: &str, project_id: &str) -> Result<PathBuf> {
     let claude_dir = get_claude_dir().context("Failed to get claude dir")?;
     let records_path = claude_dir
         .join("projects")
@@ -79,7 +111,7 @@ fn get_git_records_path(session_id: &str, project_id: &str) -> Result<PathBuf> {
     Ok(records_path)
 }
 /// Load git records from .git-records.json (using prompt_index as key)
-fn load_git_records(session_id: &str, project_id: &str) -> Result<HashMap<usize, GitRecord>> {
+pub(crate) fn load_git_records(session_id: &str, project_id: &str) -> Result<HashMap<usize, GitRecord>> {
     let records_path = get_git_records_path(session_id, project_id)?;
 
     if !records_path.exists() {
@@ -134,7 +166,7 @@ fn save_git_record(session_id: &str, project_id: &str, prompt_index: usize, reco
 }
 
 /// Get a git record by prompt_index
-fn get_git_record(session_id: &str, project_id: &str, prompt_index: usize) -> Result<Option<GitRecord>> {
+pub(crate) fn get_git_record(session_id: &str, project_id: &str, prompt_index: usize) -> Result<Option<GitRecord>> {
     let records = load_git_records(session_id, project_id)?;
     Ok(records.get(&prompt_index).cloned())
 }
@@ -160,31 +192,9 @@ fn truncate_git_records(
     Ok(())
 }
 
-/// Truncate session JSONL file to before a specific prompt
-/// 🆕 Now supports multiple files (main session + agent files)
-fn truncate_session_to_prompt(
-    session_id: &str,
-    project_id: &str,
-    prompt_index: usize,
-) -> Result<()> {
-    let claude_dir = get_claude_dir().context("Failed to get claude dir")?;
-    let project_dir = claude_dir.join("projects").join(project_id);
-    let session_path = project_dir.join(format!("{}.jsonl", session_id));
-    
-    if !session_path.exists() {
-        return Ok(());  // No session file, nothing to truncate
-    }
-    
-    // ========================================================================
-    // Step 1: Process main session file
-    // ========================================================================
-    
-    // Read all lines
-    let content = fs::read_to_string(&session_path)
-        .context("Failed to read session file")?;
-    
-    let lines: Vec<&str> = content.lines().collect();
-    
+/// Find the JSONL line index of a prompt (real user input, excluding tool results,
+/// sidechain/subagent messages, Warmup and Skills messages)
+fn find_prompt_line(lines: &[&str], prompt_index: usize) -> Result<usize> {
     // Count user messages and find the line index to truncate at
     let mut user_message_count = 0;
     let mut truncate_at_line = 0;
@@ -302,8 +312,6 @@ fn truncate_session_to_prompt(
         }
     }
     
-    let total_lines = lines.len();
-    
     // 安全检查：如果没找到目标 prompt，返回错误而不是清空所有内容
     if !found_target {
         if user_message_count == 0 {
@@ -319,6 +327,37 @@ fn truncate_session_to_prompt(
             ));
         }
     }
+
+    Ok(truncate_at_line)
+}
+
+/// Truncate session JSONL file to before a specific prompt
+/// 🆕 Now supports multiple files (main session + agent files)
+fn truncate_session_to_prompt(
+    session_id: &str,
+    project_id: &str,
+    prompt_index: usize,
+) -> Result<()> {
+    let claude_dir = get_claude_dir().context("Failed to get claude dir")?;
+    let project_dir = claude_dir.join("projects").join(project_id);
+    let session_path = project_dir.join(format!("{}.jsonl", session_id));
+    
+    if !session_path.exists() {
+        return Ok(());  // No session file, nothing to truncate
+    }
+    
+    // ========================================================================
+    // Step 1: Process main session file
+    // ========================================================================
+    
+    // Read all lines
+    let content = fs::read_to_string(&session_path)
+        .context("Failed to read session file")?;
+    
+    let lines: Vec<&str> = content.lines().collect();
+    
+    let truncate_at_line = find_prompt_line(&lines, prompt_index)?;
+    let total_lines = lines.len();
     
     log::info!("Total lines: {}, will keep lines 0..{} (delete prompt #{} at line {} and after)", 
         total_lines, truncate_at_line, prompt_index, truncate_at_line);
@@ -380,6 +419,7 @@ fn truncate_session_to_prompt(
 /// Record a prompt being sent
 #[tauri::command]
 pub async fn record_prompt_sent(
+    app: AppHandle,
     session_id: String,
     project_id: String,
     project_path: String,
@@ -387,17 +427,12 @@ pub async fn record_prompt_sent(
 ) -> Result<usize, String> {
     log::info!("[Record Prompt] Recording prompt sent for session: {}", session_id);
 
-    // Ensure Git repository is initialized
-    simple_git::ensure_git_repo(&project_path)
+    // Ensure the checkpoint repository (project git or shadow) is initialized
+    let backend = checkpoint::load_backend(&app);
+    let repo = checkpoint::open_repo(&app, &project_path, backend)?;
+    checkpoint::ensure_repo(&repo)
         .map_err(|e| format!("Failed to ensure Git repo: {}", e))?;
 
-    // IMPORTANT: Always get the LATEST commit
-    // This ensures we start from the correct state even if previous prompt made no changes
-    let commit_before = simple_git::git_current_commit(&project_path)
-        .map_err(|e| format!("Failed to get current commit: {}", e))?;
-
-    log::info!("[Record Prompt] Current git commit: {}", commit_before);
-
     // 🔧 FIX: Get prompt_index FIRST (from current JSONL state)
     // The new prompt hasn't been written to JSONL yet, so prompts.len() will be the index of the new prompt
     let prompts = extract_prompts_from_jsonl(&session_id, &project_id)
@@ -407,11 +442,24 @@ pub async fn record_prompt_sent(
     
     log::info!("[Record Prompt] New prompt will be assigned index #{}", prompt_index);
 
+    // Snapshot the working tree as it is now (not just HEAD), so edits made between prompts
+    // are not attributed to this prompt and rewinding restores them
+    let commit_before = checkpoint::snapshot_commit(
+        &repo,
+        &format!("[Claude Code] Before prompt #{}", prompt_index),
+        &format!("{}{}/{}", PROMPT_BEFORE_REF_PREFIX, session_id, prompt_index),
+    )
+    .map_err(|e| format!("Failed to snapshot working tree: {}", e))?;
+
+    log::info!("[Record Prompt] Working tree checkpoint: {}", commit_before);
+
     // Create git record
     let git_record = GitRecord {
         commit_before: commit_before.clone(),
         commit_after: None,
         timestamp: Utc::now().timestamp(),
+        backend,
+        partial_reverts: Vec::new(),
     };
 
     // 🔧 FIX: Save git record using prompt_index as key (not hash!)
@@ -428,6 +476,7 @@ pub async fn record_prompt_sent(
 /// Mark a prompt as completed (after AI finishes)
 #[tauri::command]
 pub async fn mark_prompt_completed(
+    app: AppHandle,
     session_id: String,
     project_id: String,
     project_path: String,
@@ -435,10 +484,18 @@ pub async fn mark_prompt_completed(
 ) -> Result<(), String> {
     log::info!("Marking prompt #{} completed", prompt_index);
 
+    // 🔧 FIX: Load existing git record using prompt_index (not hash!)
+    let mut git_record = get_git_record(&session_id, &project_id, prompt_index)
+        .map_err(|e| format!("Failed to get git record: {}", e))?
+        .ok_or_else(|| format!("Git record not found for prompt #{}", prompt_index))?;
+
+    // Commit to the same repository commit_before was taken from
+    let repo = checkpoint::open_repo(&app, &project_path, git_record.backend)?;
+
     // Auto-commit any changes made by AI
     // This ensures each prompt has a distinct git state
     let commit_message = format!("[Claude Code] After prompt #{}", prompt_index);
-    match simple_git::git_commit_changes(&project_path, &commit_message) {
+    match checkpoint::commit_changes(&repo, &commit_message) {
         Ok(true) => {
             log::info!("Auto-committed changes after prompt #{}", prompt_index);
         },
@@ -452,14 +509,9 @@ pub async fn mark_prompt_completed(
     }
 
     // Get current commit (state after AI completion and auto-commit)
-    let commit_after = simple_git::git_current_commit(&project_path)
+    let commit_after = checkpoint::current_commit(&repo)
         .map_err(|e| format!("Failed to get current commit: {}", e))?;
 
-    // 🔧 FIX: Load existing git record using prompt_index (not hash!)
-    let mut git_record = get_git_record(&session_id, &project_id, prompt_index)
-        .map_err(|e| format!("Failed to get git record: {}", e))?
-        .ok_or_else(|| format!("Git record not found for prompt #{}", prompt_index))?;
-
     // Update commit_after
     git_record.commit_after = Some(commit_after.clone());
 
@@ -475,24 +527,39 @@ pub async fn mark_prompt_completed(
 /// Revert to a specific prompt with support for different rewind modes
 #[tauri::command]
 pub async fn revert_to_prompt(
+    app: AppHandle,
     session_id: String,
     project_id: String,
     project_path: String,
     prompt_index: usize,
     mode: RewindMode,
+) -> Result<String, String> {
+    let app_data_dir = checkpoint::app_data_dir(&app)?;
+    rewind_to_prompt(&app_data_dir, &session_id, &project_id, &project_path, prompt_index, mode)
+}
+
+/// Rewind implementation shared by the app and the headless CLI.
+/// `app_data_dir` locates the checkpoint backend setting and shadow repositories.
+pub fn rewind_to_prompt(
+    app_data_dir: &Path,
+    session_id: &str,
+    project_id: &str,
+    project_path: &str,
+    prompt_index: usize,
+    mode: RewindMode,
 ) -> Result<String, String> {
     log::info!("Reverting to prompt #{} in session: {} with mode: {:?}",
         prompt_index, session_id, mode);
 
     // Get prompts from JSONL (single source of truth)
-    let prompts = extract_prompts_from_jsonl(&session_id, &project_id)
+    let prompts = extract_prompts_from_jsonl(session_id, project_id)
         .map_err(|e| format!("Failed to extract prompts: {}", e))?;
 
     let prompt = prompts.get(prompt_index)
         .ok_or_else(|| format!("Prompt #{} not found", prompt_index))?;
 
     // 🔧 FIX: Get git record using prompt_index (not hash!)
-    let git_record = get_git_record(&session_id, &project_id, prompt_index)
+    let git_record = get_git_record(session_id, project_id, prompt_index)
         .map_err(|e| format!("Failed to get git record: {}", e))?;
 
     // Validate mode compatibility
@@ -508,17 +575,62 @@ pub async fn revert_to_prompt(
         _ => {}
     }
 
+    // Code is restored from the repository the prompt's checkpoint was recorded in
+    let backend = git_record
+        .as_ref()
+        .map(|r| r.backend)
+        .unwrap_or_else(|| checkpoint::load_backend_at(app_data_dir));
+    let repo = checkpoint::open_repo_at(app_data_dir, project_path, backend)?;
+    let repo_exists = checkpoint::repo_exists(&repo);
+    if mode != RewindMode::ConversationOnly && !repo_exists {
+        return Err(format!(
+            "无法回滚代码：检查点仓库不存在（{}），可能已被删除或不在项目根目录",
+            project_path
+        ));
+    }
+
+    // Back up the conversation before truncating so the rewind can be redone
+    let stamp = Utc::now().timestamp_millis().to_string();
+    let backup = if mode != RewindMode::CodeOnly {
+        backup_conversation(session_id, project_id, &stamp)
+            .map_err(|e| format!("Failed to back up conversation: {}", e))?;
+        Some(stamp.as_str())
+    } else {
+        None
+    };
+
+    // Record the pre-rewind state (working tree included) under refs/workbench/.
+    // The backup is only reachable through the snapshot ref, so drop it without one.
+    let snapshot = if repo_exists {
+        match record_rewind_snapshot(&repo, session_id, project_id, prompt_index, &mode, &stamp, backup) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) if mode == RewindMode::ConversationOnly => {
+                log::warn!("Failed to record rewind snapshot: {}", e);
+                None
+            }
+            Err(e) => {
+                remove_conversation_backup(session_id, project_id, &stamp);
+                return Err(format!("Failed to record rewind snapshot: {}", e));
+            }
+        }
+    } else {
+        None
+    };
+    if snapshot.is_none() && backup.is_some() {
+        remove_conversation_backup(session_id, project_id, &stamp);
+    }
+
     // Execute revert based on mode
     match mode {
         RewindMode::ConversationOnly => {
             log::info!("Reverting conversation only (deleting messages)");
 
             // Truncate session messages in JSONL
-            truncate_session_to_prompt(&session_id, &project_id, prompt_index)
+            truncate_session_to_prompt(session_id, project_id, prompt_index)
                 .map_err(|e| format!("Failed to truncate session: {}", e))?;
 
             // Truncate git records (remove records for prompts after this index)
-            truncate_git_records(&session_id, &project_id, &prompts, prompt_index)
+            truncate_git_records(session_id, project_id, &prompts, prompt_index)
                 .map_err(|e| format!("Failed to truncate git records: {}", e))?;
 
             log::info!("Successfully reverted conversation to prompt #{}", prompt_index);
@@ -528,15 +640,13 @@ pub async fn revert_to_prompt(
             log::info!("Reverting code only (keeping messages)");
 
             let record = git_record.unwrap(); // Safe because we validated above
+            let Some((_, snapshot_commit)) = &snapshot else {
+                return Err("无法回滚代码：未能记录回滚前的快照".to_string());
+            };
 
-            // 1. Stash any uncommitted changes
-            simple_git::git_stash_save(&project_path,
-                &format!("Auto-stash before code revert to prompt #{}", prompt_index))
-                .map_err(|e| format!("Failed to stash changes: {}", e))?;
-
-            // 2. Reset code to state before this prompt
-            simple_git::git_reset_hard(&project_path, &record.commit_before)
-                .map_err(|e| format!("Failed to reset code: {}", e))?;
+            // Check out the tree before this prompt; HEAD and the branch stay where they are
+            simple_git::git_restore_worktree(&repo, snapshot_commit, &record.commit_before)
+                .map_err(|e| format!("Failed to restore code: {}", e))?;
 
             log::info!("Successfully reverted code to prompt #{}", prompt_index);
         }
@@ -545,32 +655,695 @@ pub async fn revert_to_prompt(
             log::info!("Reverting both conversation and code");
 
             let record = git_record.unwrap(); // Safe because we validated above
+            let Some((_, snapshot_commit)) = &snapshot else {
+                return Err("无法回滚代码：未能记录回滚前的快照".to_string());
+            };
 
-            // 1. Stash any uncommitted changes
-            simple_git::git_stash_save(&project_path,
-                &format!("Auto-stash before full revert to prompt #{}", prompt_index))
-                .map_err(|e| format!("Failed to stash changes: {}", e))?;
-
-            // 2. Reset code to state before this prompt
-            simple_git::git_reset_hard(&project_path, &record.commit_before)
-                .map_err(|e| format!("Failed to reset code: {}", e))?;
+            // 1. Check out the tree before this prompt; HEAD and the branch stay where they are
+            simple_git::git_restore_worktree(&repo, snapshot_commit, &record.commit_before)
+                .map_err(|e| format!("Failed to restore code: {}", e))?;
 
-            // 3. Truncate session messages
-            truncate_session_to_prompt(&session_id, &project_id, prompt_index)
+            // 2. Truncate session messages
+            truncate_session_to_prompt(session_id, project_id, prompt_index)
                 .map_err(|e| format!("Failed to truncate session: {}", e))?;
 
-            // 4. Truncate git records
-            truncate_git_records(&session_id, &project_id, &prompts, prompt_index)
+            // 3. Truncate git records
+            truncate_git_records(session_id, project_id, &prompts, prompt_index)
                 .map_err(|e| format!("Failed to truncate git records: {}", e))?;
 
             log::info!("Successfully reverted both conversation and code to prompt #{}", prompt_index);
         }
     }
 
+    if let Some((ref_name, _)) = &snapshot {
+        log::info!("Pre-rewind state saved as {}", ref_name);
+    }
+
     // Return the prompt text for restoring to input
     Ok(prompt.text.clone())
 }
 
+/// Ref namespace for pre-rewind safety snapshots
+const REWIND_REF_PREFIX: &str = "refs/workbench/rewind/";
+/// Pins commit_before snapshots of the project repository: refs/workbench/prompts/<session>/<index>
+const PROMPT_BEFORE_REF_PREFIX: &str = "refs/workbench/prompts/";
+
+/// A safety snapshot recorded before a rewind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewindSnapshot {
+    /// Full ref name, e.g. refs/workbench/rewind/1730000000000
+    pub ref_name: String,
+    /// Snapshot commit holding the pre-rewind working tree
+    pub commit: String,
+    /// Unix timestamp (seconds) when the snapshot was recorded
+    pub created_at: i64,
+    pub session_id: Option<String>,
+    pub project_id: Option<String>,
+    pub prompt_index: Option<usize>,
+    pub mode: Option<RewindMode>,
+    /// Whether a conversation backup exists for this snapshot
+    pub has_conversation_backup: bool,
+}
+
+/// Paths of the conversation backup (session JSONL, git records) for a rewind stamp
+fn rewind_backup_paths(session_id: &str, project_id: &str, stamp: &str) -> Result<(PathBuf, PathBuf)> {
+    let claude_dir = get_claude_dir().context("Failed to get claude dir")?;
+    let sessions_dir = claude_dir.join("projects").join(project_id).join("sessions");
+    Ok((
+        sessions_dir.join(format!("{}.rewind-{}.jsonl", session_id, stamp)),
+        sessions_dir.join(format!("{}.rewind-{}.git-records.json", session_id, stamp)),
+    ))
+}
+
+/// Copy the session JSONL and its git records aside before they get truncated
+fn backup_conversation(session_id: &str, project_id: &str, stamp: &str) -> Result<()> {
+    let claude_dir = get_claude_dir().context("Failed to get claude dir")?;
+    let session_path = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
+    let (session_backup, records_backup) = rewind_backup_paths(session_id, project_id, stamp)?;
+
+    if let Some(parent) = session_backup.parent() {
+        fs::create_dir_all(parent).context("Failed to create sessions directory")?;
+    }
+    fs::copy(&session_path, &session_backup).context("Failed to back up session file")?;
+
+    let records_path = get_git_records_path(session_id, project_id)?;
+    if records_path.exists() {
+        fs::copy(&records_path, &records_backup).context("Failed to back up git records")?;
+    }
+
+    log::info!("Backed up conversation to {:?}", session_backup);
+    Ok(())
+}
+
+/// Put a conversation backup back in place and remove the backup files
+fn restore_conversation_backup(session_id: &str, project_id: &str, stamp: &str) -> Result<()> {
+    let claude_dir = get_claude_dir().context("Failed to get claude dir")?;
+    let session_path = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
+    let (session_backup, records_backup) = rewind_backup_paths(session_id, project_id, stamp)?;
+    let records_path = get_git_records_path(session_id, project_id)?;
+
+    fs::copy(&session_backup, &session_path).context("Failed to restore session file")?;
+    if records_backup.exists() {
+        fs::copy(&records_backup, &records_path).context("Failed to restore git records")?;
+    } else if records_path.exists() {
+        // There were no records before the rewind
+        fs::remove_file(&records_path).context("Failed to remove git records")?;
+    }
+
+    remove_conversation_backup(session_id, project_id, stamp);
+    Ok(())
+}
+
+fn remove_conversation_backup(session_id: &str, project_id: &str, stamp: &str) {
+    if let Ok((session_backup, records_backup)) = rewind_backup_paths(session_id, project_id, stamp) {
+        let _ = fs::remove_file(session_backup);
+        let _ = fs::remove_file(records_backup);
+    }
+}
+
+fn rewind_mode_name(mode: &RewindMode) -> &'static str {
+    match mode {
+        RewindMode::ConversationOnly => "conversation_only",
+        RewindMode::CodeOnly => "code_only",
+        RewindMode::Both => "both",
+    }
+}
+
+/// Snapshot the working tree and record it as refs/workbench/rewind/<stamp>.
+/// Metadata lives in the snapshot commit message as trailers. Returns (ref name, commit).
+fn record_rewind_snapshot(
+    repo: &GitRepo,
+    session_id: &str,
+    project_id: &str,
+    prompt_index: usize,
+    mode: &RewindMode,
+    stamp: &str,
+    backup: Option<&str>,
+) -> Result<(String, String), String> {
+    let mut message = format!(
+        "Workbench rewind snapshot\n\nWorkbench-Session: {}\nWorkbench-Project: {}\nWorkbench-Prompt: {}\nWorkbench-Mode: {}\n",
+        session_id, project_id, prompt_index, rewind_mode_name(mode)
+    );
+    if let Some(backup) = backup {
+        message.push_str(&format!("Workbench-Conversation-Backup: {}\n", backup));
+    }
+
+    let commit = simple_git::git_snapshot_worktree(repo, &message)?;
+    let ref_name = format!("{}{}", REWIND_REF_PREFIX, stamp);
+    simple_git::git_update_ref(repo, &ref_name, &commit)?;
+
+    Ok((ref_name, commit))
+}
+
+fn parse_rewind_snapshot(info: simple_git::GitRefInfo) -> (RewindSnapshot, Option<String>) {
+    let mut snapshot = RewindSnapshot {
+        ref_name: info.name,
+        commit: info.commit,
+        created_at: info.created_at,
+        session_id: None,
+        project_id: None,
+        prompt_index: None,
+        mode: None,
+        has_conversation_backup: false,
+    };
+    let mut backup = None;
+
+    for line in info.message.lines() {
+        let Some((key, value)) = line.split_once(": ") else { continue };
+        let value = value.trim().to_string();
+        match key {
+            "Workbench-Session" => snapshot.session_id = Some(value),
+            "Workbench-Project" => snapshot.project_id = Some(value),
+            "Workbench-Prompt" => snapshot.prompt_index = value.parse().ok(),
+            "Workbench-Mode" => {
+                snapshot.mode = serde_json::from_value(serde_json::Value::String(value)).ok()
+            }
+            "Workbench-Conversation-Backup" => backup = Some(value),
+            _ => {}
+        }
+    }
+
+    // The backup only counts if its files are still on disk
+    if let (Some(sid), Some(pid), Some(stamp)) = (&snapshot.session_id, &snapshot.project_id, &backup) {
+        snapshot.has_conversation_backup = rewind_backup_paths(sid, pid, stamp)
+            .map(|(session_backup, _)| session_backup.exists())
+            .unwrap_or(false);
+    }
+
+    (snapshot, backup)
+}
+
+fn load_rewind_snapshots(repo: &GitRepo) -> Result<Vec<(RewindSnapshot, Option<String>)>, String> {
+    Ok(simple_git::git_list_refs(repo, REWIND_REF_PREFIX)?
+        .into_iter()
+        .map(parse_rewind_snapshot)
+        .collect())
+}
+
+/// Checkpoint repository to look for snapshots in (the selected backend unless overridden)
+fn snapshot_repo(
+    app: &AppHandle,
+    project_path: &str,
+    backend: Option<CheckpointBackend>,
+) -> Result<GitRepo, String> {
+    let backend = backend.unwrap_or_else(|| checkpoint::load_backend(app));
+    checkpoint::open_repo(app, project_path, backend)
+}
+
+/// List pre-rewind safety snapshots of a project, newest first
+#[tauri::command]
+pub async fn list_rewind_snapshots(
+    app: AppHandle,
+    project_path: String,
+    session_id: Option<String>,
+    backend: Option<CheckpointBackend>,
+) -> Result<Vec<RewindSnapshot>, String> {
+    let repo = snapshot_repo(&app, &project_path, backend)?;
+    if !checkpoint::repo_exists(&repo) {
+        return Ok(Vec::new());
+    }
+
+    Ok(load_rewind_snapshots(&repo)?
+        .into_iter()
+        .map(|(snapshot, _)| snapshot)
+        .filter(|s| session_id.is_none() || s.session_id == session_id)
+        .collect())
+}
+
+/// Return to the state recorded before a rewind.
+/// The current state is itself recorded as a new snapshot first, so a redo can be undone too.
+#[tauri::command]
+pub async fn redo_rewind(
+    app: AppHandle,
+    project_path: String,
+    ref_name: String,
+    backend: Option<CheckpointBackend>,
+) -> Result<RewindSnapshot, String> {
+    log::info!("Redoing rewind from {}", ref_name);
+
+    let repo = snapshot_repo(&app, &project_path, backend)?;
+
+    let (target, backup) = load_rewind_snapshots(&repo)?
+        .into_iter()
+        .find(|(s, _)| s.ref_name == ref_name)
+        .ok_or_else(|| format!("Rewind snapshot not found: {}", ref_name))?;
+
+    let mode = target.mode.clone().unwrap_or(RewindMode::Both);
+    let (session_id, project_id) = match (&target.session_id, &target.project_id) {
+        (Some(sid), Some(pid)) => (sid.clone(), pid.clone()),
+        _ => return Err(format!("Rewind snapshot {} is missing session metadata", ref_name)),
+    };
+    let backup = backup.filter(|_| target.has_conversation_backup);
+
+    // Record where we are now before overwriting anything
+    let stamp = Utc::now().timestamp_millis().to_string();
+    let current_backup = if backup.is_some() {
+        backup_conversation(&session_id, &project_id, &stamp)
+            .map_err(|e| format!("Failed to back up conversation: {}", e))?;
+        Some(stamp.as_str())
+    } else {
+        None
+    };
+    let (current_ref, current_commit) = record_rewind_snapshot(
+        &repo,
+        &session_id,
+        &project_id,
+        target.prompt_index.unwrap_or(0),
+        &mode,
+        &stamp,
+        current_backup,
+    )?;
+
+    if mode != RewindMode::ConversationOnly {
+        simple_git::git_restore_worktree(&repo, &current_commit, &target.commit)
+            .map_err(|e| format!("Failed to restore code: {}", e))?;
+    }
+
+    if let Some(backup) = &backup {
+        restore_conversation_backup(&session_id, &project_id, backup)
+            .map_err(|e| format!("Failed to restore conversation: {}", e))?;
+    }
+
+    // The snapshot has been consumed
+    simple_git::git_delete_ref(&repo, &ref_name)?;
+
+    log::info!("Redo complete; previous state saved as {}", current_ref);
+    let (snapshot, _) = load_rewind_snapshots(&repo)?
+        .into_iter()
+        .find(|(s, _)| s.ref_name == current_ref)
+        .ok_or_else(|| format!("Rewind snapshot not found: {}", current_ref))?;
+    Ok(snapshot)
+}
+
+/// Delete rewind snapshots (and their conversation backups) older than `max_age_days`,
+/// always keeping the newest `keep_latest`. Returns the number of snapshots removed.
+#[tauri::command]
+pub async fn gc_rewind_snapshots(
+    app: AppHandle,
+    project_path: String,
+    max_age_days: Option<u32>,
+    keep_latest: Option<usize>,
+    backend: Option<CheckpointBackend>,
+) -> Result<usize, String> {
+    let repo = snapshot_repo(&app, &project_path, backend)?;
+    if !checkpoint::repo_exists(&repo) {
+        return Ok(0);
+    }
+
+    let cutoff = Utc::now().timestamp() - i64::from(max_age_days.unwrap_or(14)) * 86_400;
+    let mut removed = 0;
+
+    for (snapshot, backup) in load_rewind_snapshots(&repo)?
+        .into_iter()
+        .skip(keep_latest.unwrap_or(20))
+    {
+        if snapshot.created_at >= cutoff {
+            continue;
+        }
+        if let Err(e) = simple_git::git_delete_ref(&repo, &snapshot.ref_name) {
+            log::warn!("Failed to delete {}: {}", snapshot.ref_name, e);
+            continue;
+        }
+        if let (Some(sid), Some(pid), Some(stamp)) = (&snapshot.session_id, &snapshot.project_id, &backup) {
+            remove_conversation_backup(sid, pid, stamp);
+        }
+        removed += 1;
+    }
+
+    log::info!("Removed {} rewind snapshot(s) from {}", removed, project_path);
+    Ok(removed)
+}
+
+/// Result of reverting selected files/hunks of a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathRevertResult {
+    /// Files restored as a whole
+    pub reverted_files: Vec<String>,
+    /// Number of individual hunks reverted
+    pub reverted_hunks: usize,
+    /// Safety snapshot taken before the revert (usable with redo_rewind)
+    pub snapshot_ref: Option<String>,
+}
+
+/// Restore chosen files, or chosen hunks of files, to the prompt's commit_before.
+/// Everything else in the working tree and the conversation is left as it is.
+/// Hunk indices refer to get_prompt_file_diffs output with the same `context_lines`.
+#[tauri::command]
+pub async fn revert_prompt_paths(
+    app: AppHandle,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    prompt_index: usize,
+    selections: Vec<PathRevertSelection>,
+    context_lines: Option<u32>,
+) -> Result<PathRevertResult, String> {
+    log::info!("Reverting {} path(s) of prompt #{} in session {}",
+        selections.len(), prompt_index, session_id);
+
+    if selections.is_empty() {
+        return Err("No paths selected".to_string());
+    }
+
+    let mut record = get_git_record(&session_id, &project_id, prompt_index)
+        .map_err(|e| format!("Failed to get git record: {}", e))?
+        .ok_or_else(|| format!(
+            "无法回滚代码：提示词 #{} 没有关联的 Git 记录（可能来自 CLI 终端）",
+            prompt_index
+        ))?;
+
+    let repo = checkpoint::open_repo(&app, &project_path, record.backend)?;
+    // Diff paths are relative to the work tree root, so run everything from there
+    let repo = GitRepo {
+        work_tree: simple_git::git_toplevel(&repo)?,
+        git_dir: repo.git_dir,
+    };
+
+    // Same diff get_prompt_file_diffs shows
+    let to_ref = match &record.commit_after {
+        Some(commit) => commit.clone(),
+        None => simple_git::git_snapshot_worktree(&repo, "Workbench diff snapshot")?,
+    };
+    let files = git_stats::collect_file_diffs(
+        &repo,
+        &record.commit_before,
+        &to_ref,
+        &git_stats::FileDiffOptions {
+            context_lines,
+            ..Default::default()
+        },
+    )?;
+
+    let mut whole_files = Vec::new();
+    let mut patch = String::new();
+    let mut reverted_hunks = 0;
+    let mut reverted_lines = Vec::new();
+
+    for selection in &selections {
+        let file = files
+            .iter()
+            .find(|f| f.path == selection.path)
+            .ok_or_else(|| format!("文件 {} 不在提示词 #{} 的改动中", selection.path, prompt_index))?;
+
+        // Binary and deleted files can only be restored as a whole
+        let hunks = match &selection.hunks {
+            // git apply needs each hunk once and in file order
+            Some(hunks) if !file.binary && file.status != FileChangeStatus::Deleted => {
+                hunks.iter().copied().collect::<BTreeSet<usize>>()
+            }
+            _ => {
+                reverted_lines.push(git_stats::FileChangeStat {
+                    path: file.path.clone(),
+                    lines_added: file.lines_added,
+                    lines_removed: file.lines_removed,
+                    binary: file.binary,
+                });
+                whole_files.push(file);
+                continue;
+            }
+        };
+
+        let mut paths = vec![file.path.as_str()];
+        if let Some(old_path) = &file.old_path {
+            paths.push(old_path.as_str());
+        }
+        let raw = git_stats::raw_file_hunks(&repo, &record.commit_before, &to_ref, &paths, context_lines)?;
+
+        // Header names the current path on both sides so renames stay in place
+        patch.push_str(&format!(
+            "diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n",
+            file.path
+        ));
+        let mut stat = git_stats::FileChangeStat {
+            path: file.path.clone(),
+            lines_added: 0,
+            lines_removed: 0,
+            binary: false,
+        };
+        for index in hunks {
+            let hunk = raw.get(index)
+                .ok_or_else(|| format!("Hunk #{} not found in {}", index, file.path))?;
+            patch.push_str(hunk);
+            if !hunk.ends_with('\n') {
+                patch.push('\n');
+            }
+            reverted_hunks += 1;
+            // Skip the @@ header; the rest are context/+/- lines
+            for line in hunk.lines().skip(1) {
+                if line.starts_with('+') {
+                    stat.lines_added += 1;
+                } else if line.starts_with('-') {
+                    stat.lines_removed += 1;
+                }
+            }
+        }
+        reverted_lines.push(stat);
+    }
+
+    // Safety snapshot so the partial revert can be undone with redo_rewind
+    let stamp = Utc::now().timestamp_millis().to_string();
+    let (snapshot_ref, _) = record_rewind_snapshot(
+        &repo, &session_id, &project_id, prompt_index, &RewindMode::CodeOnly, &stamp, None)?;
+
+    // Hunks first: git apply is all-or-nothing, so a stale hunk leaves the tree untouched
+    if reverted_hunks > 0 {
+        let patch_file = std::env::temp_dir()
+            .join(format!("workbench-revert-{}.patch", uuid::Uuid::new_v4()));
+        fs::write(&patch_file, &patch)
+            .map_err(|e| format!("Failed to write patch: {}", e))?;
+        let applied = simple_git::git_apply_patch(&repo, &patch_file, true);
+        let _ = fs::remove_file(&patch_file);
+        applied.map_err(|e| format!("Failed to revert hunks (the file may have changed since): {}", e))?;
+    }
+
+    let mut restore = Vec::new();
+    for file in &whole_files {
+        match file.status {
+            // Did not exist before the prompt
+            FileChangeStatus::Added | FileChangeStatus::Copied => {
+                remove_work_tree_file(&repo.work_tree, &file.path)?;
+            }
+            FileChangeStatus::Renamed => {
+                remove_work_tree_file(&repo.work_tree, &file.path)?;
+                restore.extend(file.old_path.as_deref());
+            }
+            _ => restore.push(file.path.as_str()),
+        }
+    }
+    simple_git::git_restore_paths(&repo, &record.commit_before, &restore)
+        .map_err(|e| format!("Failed to restore files: {}", e))?;
+
+    // Remember what was reverted on the prompt's git record
+    record.partial_reverts.push(PartialRevert {
+        timestamp: Utc::now().timestamp(),
+        selections,
+        snapshot_ref: Some(snapshot_ref.clone()),
+        reverted_lines,
+    });
+    save_git_record(&session_id, &project_id, prompt_index, record)
+        .map_err(|e| format!("Failed to save git record: {}", e))?;
+
+    let reverted_files: Vec<String> = whole_files.iter().map(|f| f.path.clone()).collect();
+    log::info!("Reverted {} file(s) and {} hunk(s) of prompt #{}",
+        reverted_files.len(), reverted_hunks, prompt_index);
+
+    Ok(PathRevertResult {
+        reverted_files,
+        reverted_hunks,
+        snapshot_ref: Some(snapshot_ref),
+    })
+}
+
+fn remove_work_tree_file(work_tree: &str, path: &str) -> Result<(), String> {
+    let file = std::path::Path::new(work_tree).join(path);
+    match fs::remove_file(&file) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path, e)),
+    }
+}
+
+/// Result of forking a session at a prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkResult {
+    /// Session id of the new (forked) session
+    pub session_id: String,
+    /// Project id (encoded directory) the fork was written to
+    pub project_id: String,
+    /// Working directory of the fork (the worktree when one was created)
+    pub project_path: String,
+    /// The prompt the fork branches at (not included in the fork)
+    pub prompt_index: usize,
+    /// Text of that prompt, for restoring to the input box
+    pub prompt_text: String,
+    /// Number of JSONL lines copied into the fork
+    pub messages_copied: usize,
+    /// Number of git records copied into the fork
+    pub git_records_copied: usize,
+    /// Worktree path if a git worktree was created
+    pub worktree_path: Option<String>,
+    /// Branch of the worktree if one was created
+    pub branch: Option<String>,
+}
+
+/// Fork a session at a prompt into a new session id without touching the original.
+/// The fork holds everything before the prompt; optionally a git worktree is created
+/// on a new branch at the prompt's `commit_before` and the fork is attached to it.
+#[tauri::command]
+pub async fn fork_session_at_prompt(
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    prompt_index: usize,
+    create_worktree: Option<bool>,
+    worktree_path: Option<String>,
+    branch_name: Option<String>,
+) -> Result<ForkResult, String> {
+    log::info!("Forking session {} at prompt #{}", session_id, prompt_index);
+
+    let prompts = extract_prompts_from_jsonl(&session_id, &project_id)
+        .map_err(|e| format!("Failed to extract prompts: {}", e))?;
+    let prompt = prompts.get(prompt_index)
+        .ok_or_else(|| format!("Prompt #{} not found", prompt_index))?;
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let session_path = claude_dir
+        .join("projects")
+        .join(&project_id)
+        .join(format!("{}.jsonl", session_id));
+    let content = fs::read_to_string(&session_path)
+        .map_err(|e| format!("Failed to read session file: {}", e))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let cut = find_prompt_line(&lines, prompt_index)
+        .map_err(|e| e.to_string())?;
+    if cut == 0 {
+        return Err(format!("无法分叉：提示词 #{} 之前没有任何对话内容", prompt_index));
+    }
+
+    let new_session_id = uuid::Uuid::new_v4().to_string();
+    let short_id = &new_session_id[..8];
+
+    // Optionally create a worktree at the code state before this prompt
+    let (target_path, worktree, branch) = if create_worktree.unwrap_or(false) {
+        let record = get_git_record(&session_id, &project_id, prompt_index)
+            .map_err(|e| format!("Failed to get git record: {}", e))?
+            .ok_or_else(|| format!(
+                "无法创建工作树：提示词 #{} 没有关联的 Git 记录（可能来自 CLI 终端）",
+                prompt_index
+            ))?;
+
+        if record.backend == CheckpointBackend::Shadow {
+            return Err("无法创建工作树：该提示词的检查点保存在影子仓库中，不在项目的 Git 历史里".to_string());
+        }
+
+        let worktree_path = worktree_path.unwrap_or_else(|| {
+            let project = std::path::Path::new(&project_path);
+            let name = project
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "project".to_string());
+            project
+                .with_file_name(format!("{}-fork-{}", name, short_id))
+                .to_string_lossy()
+                .to_string()
+        });
+        let branch = branch_name.unwrap_or_else(|| format!("workbench/fork-{}", short_id));
+
+        simple_git::git_worktree_add(&project_path, &worktree_path, &branch, &record.commit_before)?;
+        (worktree_path.clone(), Some(worktree_path), Some(branch))
+    } else {
+        (project_path.clone(), None, None)
+    };
+
+    let target_project_id = if worktree.is_some() {
+        super::claude::encode_project_path(&target_path)
+    } else {
+        project_id.clone()
+    };
+
+    // Copy everything before the prompt, re-pointing the lines at the new session (and worktree)
+    let mut forked = String::new();
+    for line in &lines[..cut] {
+        match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(mut value) => {
+                if let Some(obj) = value.as_object_mut() {
+                    if obj.contains_key("sessionId") {
+                        obj.insert("sessionId".to_string(), serde_json::Value::String(new_session_id.clone()));
+                    }
+                    if worktree.is_some() {
+                        if let Some(cwd) = obj.get("cwd").and_then(|v| v.as_str()) {
+                            let cwd = super::session_export::rebase_path(cwd, Some(&project_path), &target_path);
+                            obj.insert("cwd".to_string(), serde_json::Value::String(cwd));
+                        }
+                    }
+                }
+                forked.push_str(&value.to_string());
+            }
+            Err(_) => forked.push_str(line),
+        }
+        forked.push('\n');
+    }
+
+    let target_dir = claude_dir.join("projects").join(&target_project_id);
+    let fork_path = target_dir.join(format!("{}.jsonl", new_session_id));
+    let written = (|| -> Result<HashMap<usize, GitRecord>, String> {
+        fs::create_dir_all(&target_dir)
+            .map_err(|e| format!("Failed to create project directory: {}", e))?;
+        fs::write(&fork_path, forked)
+            .map_err(|e| format!("Failed to write forked session: {}", e))?;
+
+        // Git records of the prompts kept in the fork
+        let records: HashMap<usize, GitRecord> = load_git_records(&session_id, &project_id)
+            .map_err(|e| format!("Failed to load git records: {}", e))?
+            .into_iter()
+            .filter(|(index, _)| *index < prompt_index)
+            .collect();
+        if !records.is_empty() {
+            save_git_records(&new_session_id, &target_project_id, &records)
+                .map_err(|e| format!("Failed to save git records: {}", e))?;
+        }
+        Ok(records)
+    })();
+
+    // Don't leave a half-written fork or an orphaned worktree/branch behind
+    let records = match written {
+        Ok(records) => records,
+        Err(e) => {
+            let _ = fs::remove_file(&fork_path);
+            if let (Some(worktree), Some(branch)) = (&worktree, &branch) {
+                if let Err(cleanup) = simple_git::git_worktree_remove(&project_path, worktree, branch) {
+                    log::warn!("Failed to clean up fork worktree {}: {}", worktree, cleanup);
+                }
+            }
+            return Err(e);
+        }
+    };
+
+    log::info!("Forked session {} at prompt #{} into {} ({} lines)",
+        session_id, prompt_index, new_session_id, cut);
+
+    Ok(ForkResult {
+        session_id: new_session_id,
+        project_id: target_project_id,
+        project_path: target_path,
+        prompt_index,
+        prompt_text: prompt.text.clone(),
+        messages_copied: cut,
+        git_records_copied: records.len(),
+        worktree_path: worktree,
+        branch,
+    })
+}
+
 /// Get all prompts for a session (for debugging)
 #[tauri::command]
 pub async fn get_prompt_list(
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::path::PathBuf;
-use tauri::{command, AppHandle};
+use tauri::command;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ProviderConfig {
@@ -246,10 +246,7 @@ pub fn get_current_provider_config() -> Result<CurrentConfig, String> {
 
 // 切换代理商配置（写入settings.json的env字段）
 #[command]
-pub async fn switch_provider_config(
-    _app: AppHandle,
-    config: ProviderConfig,
-) -> Result<String, String> {
+pub async fn switch_provider_config(config: ProviderConfig) -> Result<String, String> {
     log::info!(
         "开始切换代理商配置: {} - {}",
         config.name,
@@ -373,6 +370,7 @@ pub async fn switch_provider_config(
 
     // 保存设置
     save_settings(&settings)?;
+    super::model_capabilities::invalidate_provider_cache();
 
     log::info!("代理商配置切换完成: {}", config.name);
 
@@ -421,7 +419,7 @@ fn validate_third_party_config(config: &ProviderConfig) -> Result<(), String> {
 
 // 清理代理商配置（清理settings.json的env字段中的ANTHROPIC变量和apiKeyHelper字段）
 #[command]
-pub async fn clear_provider_config(_app: AppHandle) -> Result<String, String> {
+pub async fn clear_provider_config() -> Result<String, String> {
     log::info!("开始清理代理商配置");
 
     let mut settings = load_settings()?;
@@ -444,6 +442,7 @@ pub async fn clear_provider_config(_app: AppHandle) -> Result<String, String> {
 
     // 保存设置
     save_settings(&settings)?;
+    super::model_capabilities::invalidate_provider_cache();
 
     log::info!("代理商配置清理完成");
 
@@ -0,0 +1,983 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::claude::{encode_project_path, get_claude_dir, load_session_history};
+
+/// Version of the `.zip` bundle layout (bumped when `manifest.json` changes incompatibly)
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Largest decompressed entry accepted from an imported bundle (guards against zip bombs)
+const MAX_BUNDLE_ENTRY_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Tool output longer than this is truncated in Markdown/HTML exports (characters)
+const MAX_TOOL_OUTPUT_CHARS: usize = 20_000;
+
+/// Export target format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Zip,
+}
+
+/// Manifest stored as `manifest.json` inside a session bundle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub session_id: String,
+    pub project_id: String,
+    pub project_path: Option<String>,
+    pub exported_at: String,
+    pub message_count: usize,
+    pub app_version: String,
+    /// Whether `git-records.json` is present in the bundle
+    pub has_git_records: bool,
+}
+
+/// Result of an export
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportResult {
+    pub path: String,
+    pub format: ExportFormat,
+    pub bytes: u64,
+    pub message_count: usize,
+}
+
+/// Token usage reported for one assistant turn
+#[derive(Debug, Clone, Default)]
+struct TurnUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+}
+
+impl TurnUsage {
+    fn from_value(usage: &Value) -> Self {
+        let get = |key: &str| usage.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+        Self {
+            input_tokens: get("input_tokens"),
+            output_tokens: get("output_tokens"),
+            cache_creation_tokens: get("cache_creation_input_tokens"),
+            cache_read_tokens: get("cache_read_input_tokens"),
+        }
+    }
+
+    fn add(&mut self, other: &TurnUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "input {} · output {} · cache read {} · cache write {}",
+            self.input_tokens,
+            self.output_tokens,
+            self.cache_read_tokens,
+            self.cache_creation_tokens
+        )
+    }
+}
+
+/// A renderable piece of a turn
+#[derive(Debug, Clone)]
+enum Block {
+    Text(String),
+    Thinking(String),
+    ToolUse {
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_name: Option<String>,
+        content: String,
+        is_error: bool,
+    },
+    Image,
+}
+
+/// One transcript line rendered as a turn
+#[derive(Debug, Clone)]
+struct Turn {
+    role: String,
+    timestamp: Option<String>,
+    model: Option<String>,
+    usage: Option<TurnUsage>,
+    blocks: Vec<Block>,
+}
+
+/// A session prepared for rendering
+struct ExportDocument {
+    session_id: String,
+    project_path: Option<String>,
+    turns: Vec<Turn>,
+    total_usage: TurnUsage,
+}
+
+fn session_file_path(session_id: &str, project_id: &str) -> Result<PathBuf, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    Ok(claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id)))
+}
+
+/// Flattens tool_result content (string or array of text blocks) into text
+fn tool_result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item.get("type").and_then(|t| t.as_str()) {
+                Some("text") => item
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .map(str::to_string),
+                Some("image") => Some("[image]".to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max).collect();
+    truncated.push_str("\n… (truncated)");
+    truncated
+}
+
+/// Converts the messages returned by `load_session_history` into turns
+fn build_document(session_id: &str, messages: &[Value]) -> ExportDocument {
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+    let mut counted_usage: HashSet<String> = HashSet::new();
+    let mut project_path = None;
+    let mut total_usage = TurnUsage::default();
+    let mut turns = Vec::new();
+
+    for message in messages {
+        if project_path.is_none() {
+            project_path = message
+                .get("cwd")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+        }
+        if message.get("isMeta").and_then(|v| v.as_bool()) == Some(true) {
+            continue;
+        }
+
+        let role = match message.get("type").and_then(|t| t.as_str()) {
+            Some(role @ ("user" | "assistant")) => role.to_string(),
+            Some("summary") => {
+                if let Some(summary) = message.get("summary").and_then(|s| s.as_str()) {
+                    turns.push(Turn {
+                        role: "summary".to_string(),
+                        timestamp: None,
+                        model: None,
+                        usage: None,
+                        blocks: vec![Block::Text(summary.to_string())],
+                    });
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        let timestamp = ["timestamp", "sentAt", "receivedAt"]
+            .iter()
+            .find_map(|key| message.get(*key).and_then(|v| v.as_str()))
+            .map(str::to_string);
+        let inner = message.get("message");
+        let model = inner
+            .and_then(|m| m.get("model"))
+            .and_then(|m| m.as_str())
+            .map(str::to_string);
+
+        // Streamed assistant messages repeat the same usage on every content line; count it once
+        let usage = inner.and_then(|m| m.get("usage")).and_then(|usage| {
+            let message_id = inner
+                .and_then(|m| m.get("id"))
+                .and_then(|id| id.as_str())
+                .unwrap_or_default();
+            if !message_id.is_empty() && !counted_usage.insert(message_id.to_string()) {
+                return None;
+            }
+            let usage = TurnUsage::from_value(usage);
+            total_usage.add(&usage);
+            Some(usage)
+        });
+
+        let mut blocks = Vec::new();
+        match inner.and_then(|m| m.get("content")) {
+            Some(Value::String(text)) if !text.trim().is_empty() => {
+                blocks.push(Block::Text(text.clone()));
+            }
+            Some(Value::Array(items)) => {
+                for item in items {
+                    match item.get("type").and_then(|t| t.as_str()) {
+                        Some("text") => {
+                            if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+                                if !text.trim().is_empty() {
+                                    blocks.push(Block::Text(text.to_string()));
+                                }
+                            }
+                        }
+                        Some("thinking") => {
+                            if let Some(text) = item.get("thinking").and_then(|t| t.as_str()) {
+                                if !text.trim().is_empty() {
+                                    blocks.push(Block::Thinking(text.to_string()));
+                                }
+                            }
+                        }
+                        Some("tool_use") => {
+                            let name = item
+                                .get("name")
+                                .and_then(|n| n.as_str())
+                                .unwrap_or("tool")
+                                .to_string();
+                            if let Some(id) = item.get("id").and_then(|i| i.as_str()) {
+                                tool_names.insert(id.to_string(), name.clone());
+                            }
+                            blocks.push(Block::ToolUse {
+                                name,
+                                input: item.get("input").cloned().unwrap_or(Value::Null),
+                            });
+                        }
+                        Some("tool_result") => {
+                            let tool_name = item
+                                .get("tool_use_id")
+                                .and_then(|i| i.as_str())
+                                .and_then(|id| tool_names.get(id))
+                                .cloned();
+                            blocks.push(Block::ToolResult {
+                                tool_name,
+                                content: tool_result_text(item.get("content")),
+                                is_error: item.get("is_error").and_then(|e| e.as_bool())
+                                    == Some(true),
+                            });
+                        }
+                        Some("image") => blocks.push(Block::Image),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if blocks.is_empty() && usage.is_none() {
+            continue;
+        }
+        turns.push(Turn {
+            role,
+            timestamp,
+            model,
+            usage,
+            blocks,
+        });
+    }
+
+    ExportDocument {
+        session_id: session_id.to_string(),
+        project_path,
+        turns,
+        total_usage,
+    }
+}
+
+// ============ Markdown ============
+
+/// Picks a backtick fence longer than any run inside `content`
+fn fence_for(content: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in content.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+fn code_block(content: &str, lang: &str) -> String {
+    let fence = fence_for(content);
+    format!("{}{}\n{}\n{}\n", fence, lang, content.trim_end(), fence)
+}
+
+fn role_title(turn: &Turn) -> &'static str {
+    match turn.role.as_str() {
+        "user"
+            if turn
+                .blocks
+                .iter()
+                .all(|b| matches!(b, Block::ToolResult { .. })) =>
+        {
+            "Tool results"
+        }
+        "user" => "User",
+        "assistant" => "Assistant",
+        _ => "Summary",
+    }
+}
+
+fn render_markdown(doc: &ExportDocument) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# Session {}\n\n", doc.session_id));
+    if let Some(project_path) = &doc.project_path {
+        out.push_str(&format!("- **Project:** `{}`\n", project_path));
+    }
+    out.push_str(&format!("- **Turns:** {}\n", doc.turns.len()));
+    out.push_str(&format!("- **Tokens:** {}\n", doc.total_usage.describe()));
+    out.push_str(&format!(
+        "- **Exported:** {}\n\n---\n\n",
+        chrono::Local::now().to_rfc3339()
+    ));
+
+    for turn in &doc.turns {
+        out.push_str(&format!("## {}", role_title(turn)));
+        if let Some(timestamp) = &turn.timestamp {
+            out.push_str(&format!(" · {}", timestamp));
+        }
+        out.push_str("\n\n");
+
+        for block in &turn.blocks {
+            match block {
+                Block::Text(text) => {
+                    out.push_str(text.trim_end());
+                    out.push_str("\n\n");
+                }
+                Block::Thinking(text) => {
+                    out.push_str("<details>\n<summary>Thinking</summary>\n\n");
+                    out.push_str(text.trim_end());
+                    out.push_str("\n\n</details>\n\n");
+                }
+                Block::ToolUse { name, input } => {
+                    out.push_str(&format!("**Tool call:** `{}`\n\n", name));
+                    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                    out.push_str(&code_block(&input, "json"));
+                    out.push('\n');
+                }
+                Block::ToolResult {
+                    tool_name,
+                    content,
+                    is_error,
+                } => {
+                    out.push_str(&format!(
+                        "<details>\n<summary>{}{}</summary>\n\n",
+                        if *is_error {
+                            "Tool error"
+                        } else {
+                            "Tool result"
+                        },
+                        tool_name
+                            .as_ref()
+                            .map(|n| format!(": {}", n))
+                            .unwrap_or_default()
+                    ));
+                    out.push_str(&code_block(
+                        &truncate_chars(content, MAX_TOOL_OUTPUT_CHARS),
+                        "",
+                    ));
+                    out.push_str("\n</details>\n\n");
+                }
+                Block::Image => out.push_str("_[image]_\n\n"),
+            }
+        }
+
+        if let Some(usage) = &turn.usage {
+            out.push_str(&format!(
+                "> {}tokens: {}\n\n",
+                turn.model
+                    .as_ref()
+                    .map(|m| format!("{} · ", m))
+                    .unwrap_or_default(),
+                usage.describe()
+            ));
+        }
+    }
+
+    out
+}
+
+// ============ HTML ============
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, "PingFang SC", "Microsoft YaHei", sans-serif; max-width: 920px; margin: 2rem auto; padding: 0 1rem; color: #1f2328; background: #fff; line-height: 1.55; }
+header { border-bottom: 1px solid #d0d7de; margin-bottom: 1.5rem; }
+header h1 { font-size: 1.4rem; word-break: break-all; }
+header dl { display: grid; grid-template-columns: max-content 1fr; gap: .25rem 1rem; font-size: .9rem; }
+header dt { font-weight: 600; }
+.turn { border: 1px solid #d0d7de; border-radius: 8px; padding: .75rem 1rem; margin: 1rem 0; }
+.turn.user { background: #f6f8fa; }
+.turn.tool { background: #fbfbfb; }
+.turn.summary { background: #fff8c5; }
+.turn h2 { font-size: .95rem; margin: 0 0 .5rem; display: flex; justify-content: space-between; }
+.turn h2 time { font-weight: normal; color: #656d76; font-size: .8rem; }
+.text { white-space: pre-wrap; word-wrap: break-word; }
+pre { background: #f6f8fa; border: 1px solid #d0d7de; border-radius: 6px; padding: .6rem; overflow-x: auto; font-size: .82rem; white-space: pre-wrap; word-wrap: break-word; }
+details { margin: .5rem 0; }
+summary { cursor: pointer; color: #0969da; font-size: .9rem; }
+.tool-call { font-size: .9rem; margin: .5rem 0 .25rem; }
+.tool-call code { background: #eaeef2; padding: 0 .3rem; border-radius: 4px; }
+.error summary { color: #cf222e; }
+.usage { color: #656d76; font-size: .8rem; margin-top: .5rem; }
+@media (prefers-color-scheme: dark) {
+  body { background: #0d1117; color: #e6edf3; }
+  .turn, header, pre { border-color: #30363d; }
+  .turn.user, pre { background: #161b22; }
+  .turn.tool { background: #11161d; }
+  .turn.summary { background: #2e2a12; }
+  .tool-call code { background: #30363d; }
+}
+"#;
+
+fn render_html(doc: &ExportDocument) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    out.push_str(&format!(
+        "<title>Session {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(&doc.session_id),
+        HTML_STYLE
+    ));
+
+    out.push_str("<header>\n");
+    out.push_str(&format!(
+        "<h1>Session {}</h1>\n<dl>\n",
+        escape_html(&doc.session_id)
+    ));
+    if let Some(project_path) = &doc.project_path {
+        out.push_str(&format!(
+            "<dt>Project</dt><dd>{}</dd>\n",
+            escape_html(project_path)
+        ));
+    }
+    out.push_str(&format!("<dt>Turns</dt><dd>{}</dd>\n", doc.turns.len()));
+    out.push_str(&format!(
+        "<dt>Tokens</dt><dd>{}</dd>\n",
+        escape_html(&doc.total_usage.describe())
+    ));
+    out.push_str(&format!(
+        "<dt>Exported</dt><dd>{}</dd>\n</dl>\n</header>\n",
+        chrono::Local::now().to_rfc3339()
+    ));
+
+    for turn in &doc.turns {
+        let title = role_title(turn);
+        let class = match title {
+            "Tool results" => "tool",
+            "User" => "user",
+            "Assistant" => "assistant",
+            _ => "summary",
+        };
+        out.push_str(&format!(
+            "<section class=\"turn {}\">\n<h2><span>{}</span>",
+            class, title
+        ));
+        if let Some(timestamp) = &turn.timestamp {
+            out.push_str(&format!("<time>{}</time>", escape_html(timestamp)));
+        }
+        out.push_str("</h2>\n");
+
+        for block in &turn.blocks {
+            match block {
+                Block::Text(text) => out.push_str(&format!(
+                    "<div class=\"text\">{}</div>\n",
+                    escape_html(text.trim_end())
+                )),
+                Block::Thinking(text) => out.push_str(&format!(
+                    "<details><summary>Thinking</summary><div class=\"text\">{}</div></details>\n",
+                    escape_html(text.trim_end())
+                )),
+                Block::ToolUse { name, input } => {
+                    let input = serde_json::to_string_pretty(input).unwrap_or_default();
+                    out.push_str(&format!(
+                        "<div class=\"tool-call\">Tool call: <code>{}</code></div>\n<pre><code>{}</code></pre>\n",
+                        escape_html(name),
+                        escape_html(&input)
+                    ));
+                }
+                Block::ToolResult {
+                    tool_name,
+                    content,
+                    is_error,
+                } => out.push_str(&format!(
+                    "<details{}><summary>{}{}</summary><pre><code>{}</code></pre></details>\n",
+                    if *is_error { " class=\"error\"" } else { "" },
+                    if *is_error {
+                        "Tool error"
+                    } else {
+                        "Tool result"
+                    },
+                    tool_name
+                        .as_ref()
+                        .map(|n| format!(": {}", escape_html(n)))
+                        .unwrap_or_default(),
+                    escape_html(&truncate_chars(content, MAX_TOOL_OUTPUT_CHARS))
+                )),
+                Block::Image => out.push_str("<p><em>[image]</em></p>\n"),
+            }
+        }
+
+        if let Some(usage) = &turn.usage {
+            out.push_str(&format!(
+                "<div class=\"usage\">{}tokens: {}</div>\n",
+                turn.model
+                    .as_ref()
+                    .map(|m| format!("{} · ", escape_html(m)))
+                    .unwrap_or_default(),
+                usage.describe()
+            ));
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// ============ Bundle ============
+
+fn write_bundle(
+    output: &Path,
+    session_path: &Path,
+    git_records_path: &Path,
+    manifest: &BundleManifest,
+    markdown: &str,
+) -> Result<(), String> {
+    use zip::write::SimpleFileOptions;
+
+    let file =
+        fs::File::create(output).map_err(|e| format!("Failed to create bundle file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut add = |name: &str, bytes: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options)
+            .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))?;
+        zip.write_all(bytes)
+            .map_err(|e| format!("Failed to write {} to bundle: {}", name, e))
+    };
+
+    let manifest_json = serde_json::to_vec_pretty(manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    add("manifest.json", &manifest_json)?;
+
+    let raw = fs::read(session_path).map_err(|e| format!("Failed to read session file: {}", e))?;
+    add("session.jsonl", &raw)?;
+
+    if manifest.has_git_records {
+        let records =
+            fs::read(git_records_path).map_err(|e| format!("Failed to read git records: {}", e))?;
+        add("git-records.json", &records)?;
+    }
+
+    add("session.md", markdown.as_bytes())?;
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finalize bundle: {}", e))?;
+    Ok(())
+}
+
+/// Exports a session as Markdown, self-contained HTML or a portable `.zip` bundle.
+/// The bundle holds `manifest.json`, the raw `session.jsonl`, the prompt git records
+/// (when present) and a Markdown rendering for quick reading.
+#[tauri::command]
+pub async fn export_session(
+    session_id: String,
+    project_id: String,
+    format: ExportFormat,
+    output_path: String,
+) -> Result<ExportResult, String> {
+    log::info!(
+        "Exporting session {} of project {} as {:?} to {}",
+        session_id,
+        project_id,
+        format,
+        output_path
+    );
+
+    let messages = load_session_history(session_id.clone(), project_id.clone()).await?;
+    let doc = build_document(&session_id, &messages);
+    let output = PathBuf::from(&output_path);
+    if let Some(parent) = output.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create output directory: {}", e))?;
+        }
+    }
+
+    match format {
+        ExportFormat::Markdown => fs::write(&output, render_markdown(&doc))
+            .map_err(|e| format!("Failed to write Markdown export: {}", e))?,
+        ExportFormat::Html => fs::write(&output, render_html(&doc))
+            .map_err(|e| format!("Failed to write HTML export: {}", e))?,
+        ExportFormat::Zip => {
+            let session_path = session_file_path(&session_id, &project_id)?;
+            let git_records_path =
+                super::prompt_tracker::get_git_records_path(&session_id, &project_id)
+                    .map_err(|e| e.to_string())?;
+            let manifest = BundleManifest {
+                format_version: BUNDLE_FORMAT_VERSION,
+                session_id: session_id.clone(),
+                project_id: project_id.clone(),
+                project_path: doc.project_path.clone(),
+                exported_at: chrono::Utc::now().to_rfc3339(),
+                message_count: messages.len(),
+                app_version: env!("CARGO_PKG_VERSION").to_string(),
+                has_git_records: git_records_path.exists(),
+            };
+            write_bundle(
+                &output,
+                &session_path,
+                &git_records_path,
+                &manifest,
+                &render_markdown(&doc),
+            )?;
+        }
+    }
+
+    let bytes = fs::metadata(&output).map(|m| m.len()).unwrap_or(0);
+    Ok(ExportResult {
+        path: output.to_string_lossy().to_string(),
+        format,
+        bytes,
+        message_count: messages.len(),
+    })
+}
+
+// ============ Import ============
+
+/// Result of importing a session bundle
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    pub session_id: String,
+    pub project_id: String,
+    pub project_path: String,
+    pub message_count: usize,
+    /// True when the bundled session id already existed locally and a new one was generated
+    pub session_id_changed: bool,
+    pub git_records_imported: usize,
+    /// Git records whose commits do not exist in the local repository
+    pub git_records_dropped: usize,
+}
+
+/// Contents read from a bundle (or a bare `.jsonl` file)
+struct BundleContents {
+    manifest: Option<BundleManifest>,
+    session: Vec<u8>,
+    git_records: Option<Vec<u8>>,
+}
+
+fn read_bundle(path: &Path) -> Result<BundleContents, String> {
+    use std::io::Read;
+
+    if path.extension().and_then(|e| e.to_str()) == Some("jsonl") {
+        return Ok(BundleContents {
+            manifest: None,
+            session: fs::read(path).map_err(|e| format!("Failed to read session file: {}", e))?,
+            git_records: None,
+        });
+    }
+
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut archive =
+        zip::ZipArchive::new(file).map_err(|e| format!("Invalid session bundle: {}", e))?;
+
+    let mut read_entry = |name: &str| -> Result<Option<Vec<u8>>, String> {
+        let mut entry = match archive.by_name(name) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {} from bundle: {}", name, e)),
+        };
+        // The declared size can lie, so the limit is enforced while decompressing
+        let mut bytes = Vec::new();
+        entry
+            .by_ref()
+            .take(MAX_BUNDLE_ENTRY_BYTES + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {} from bundle: {}", name, e))?;
+        if bytes.len() as u64 > MAX_BUNDLE_ENTRY_BYTES {
+            return Err(format!(
+                "{} in bundle exceeds {} MB",
+                name,
+                MAX_BUNDLE_ENTRY_BYTES / (1024 * 1024)
+            ));
+        }
+        Ok(Some(bytes))
+    };
+
+    let manifest: BundleManifest = match read_entry("manifest.json")? {
+        Some(bytes) => {
+            serde_json::from_slice(&bytes).map_err(|e| format!("Invalid bundle manifest: {}", e))?
+        }
+        None => return Err("Bundle is missing manifest.json".to_string()),
+    };
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Bundle format version {} is newer than supported version {}",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        ));
+    }
+
+    let session = read_entry("session.jsonl")?
+        .ok_or_else(|| "Bundle is missing session.jsonl".to_string())?;
+    let git_records = read_entry("git-records.json")?;
+
+    Ok(BundleContents {
+        manifest: Some(manifest),
+        session,
+        git_records,
+    })
+}
+
+/// Maps a path from the exporter's machine onto the local project directory
+pub(crate) fn rebase_path(path: &str, old_root: Option<&str>, new_root: &str) -> String {
+    if let Some(old_root) = old_root {
+        if path == old_root {
+            return new_root.to_string();
+        }
+        for separator in ['/', '\\'] {
+            if let Some(rest) = path.strip_prefix(&format!("{}{}", old_root, separator)) {
+                let local_separator = std::path::MAIN_SEPARATOR.to_string();
+                return format!(
+                    "{}{}{}",
+                    new_root.trim_end_matches(['/', '\\']),
+                    local_separator,
+                    rest.replace(['/', '\\'], &local_separator)
+                );
+            }
+        }
+    }
+    // Paths outside the exported project fall back to the project root
+    new_root.to_string()
+}
+
+/// Rewrites `sessionId` and `cwd` on every transcript line; non-JSON lines are kept as is
+fn rewrite_session_lines(
+    raw: &[u8],
+    session_id: &str,
+    old_root: Option<&str>,
+    new_root: &str,
+) -> (String, usize) {
+    let mut output = String::with_capacity(raw.len());
+    let mut count = 0usize;
+
+    for line in String::from_utf8_lossy(raw).lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(line) {
+            Ok(mut value) => {
+                if let Some(obj) = value.as_object_mut() {
+                    if obj.contains_key("sessionId") {
+                        obj.insert(
+                            "sessionId".to_string(),
+                            Value::String(session_id.to_string()),
+                        );
+                    }
+                    if let Some(cwd) = obj.get("cwd").and_then(|v| v.as_str()) {
+                        let cwd = rebase_path(cwd, old_root, new_root);
+                        obj.insert("cwd".to_string(), Value::String(cwd));
+                    }
+                }
+                output.push_str(&value.to_string());
+                count += 1;
+            }
+            Err(_) => output.push_str(line),
+        }
+        output.push('\n');
+    }
+
+    (output, count)
+}
+
+/// Keeps only git records whose commits exist in the local repository
+fn filter_git_records(
+    bytes: &[u8],
+    project_path: &str,
+) -> Result<(HashMap<usize, super::prompt_tracker::GitRecord>, usize), String> {
+    let records: HashMap<usize, super::prompt_tracker::GitRecord> =
+        serde_json::from_slice(bytes).map_err(|e| format!("Invalid git records: {}", e))?;
+
+    let commit_exists = |commit: &str| {
+        let mut cmd = std::process::Command::new("git");
+        cmd.args(["cat-file", "-e", &format!("{}^{{commit}}", commit)])
+            .current_dir(project_path);
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            cmd.creation_flags(0x08000000);
+        }
+        cmd.output().map(|o| o.status.success()).unwrap_or(false)
+    };
+
+    let total = records.len();
+    let kept: HashMap<_, _> = records
+        .into_iter()
+        .filter(|(_, record)| commit_exists(&record.commit_before))
+        .map(|(index, mut record)| {
+            if let Some(after) = &record.commit_after {
+                if !commit_exists(after) {
+                    record.commit_after = None;
+                }
+            }
+            (index, record)
+        })
+        .collect();
+    let dropped = total - kept.len();
+    Ok((kept, dropped))
+}
+
+/// Imports a session bundle exported by `export_session` (or a bare `.jsonl` transcript)
+/// into a local project so it shows up in the session list and can be resumed.
+#[tauri::command]
+pub async fn import_session(
+    bundle_path: String,
+    project_path: String,
+) -> Result<ImportResult, String> {
+    log::info!(
+        "Importing session bundle {} into {}",
+        bundle_path,
+        project_path
+    );
+
+    if !Path::new(&project_path).is_dir() {
+        return Err(format!(
+            "Project directory does not exist: {}",
+            project_path
+        ));
+    }
+    let bundle = read_bundle(Path::new(&bundle_path))?;
+
+    // The original project root: manifest first, otherwise the first cwd in the transcript
+    let old_root = bundle
+        .manifest
+        .as_ref()
+        .and_then(|m| m.project_path.clone())
+        .or_else(|| {
+            String::from_utf8_lossy(&bundle.session)
+                .lines()
+                .find_map(|line| {
+                    serde_json::from_str::<Value>(line)
+                        .ok()?
+                        .get("cwd")?
+                        .as_str()
+                        .map(str::to_string)
+                })
+        });
+
+    let original_id = bundle
+        .manifest
+        .as_ref()
+        .map(|m| m.session_id.clone())
+        .or_else(|| {
+            Path::new(&bundle_path)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_string)
+        })
+        // The id becomes a file name: only accept a plain UUID, never a path
+        .and_then(|id| uuid::Uuid::parse_str(&id).ok())
+        .map(|id| id.hyphenated().to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let project_id = encode_project_path(&project_path);
+    let project_dir = get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects")
+        .join(&project_id);
+    fs::create_dir_all(&project_dir)
+        .map_err(|e| format!("Failed to create project directory: {}", e))?;
+
+    // Regenerate the session id when it would overwrite an existing session
+    let mut session_id = original_id.clone();
+    while project_dir.join(format!("{}.jsonl", session_id)).exists() {
+        session_id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let (content, message_count) = rewrite_session_lines(
+        &bundle.session,
+        &session_id,
+        old_root.as_deref(),
+        &project_path,
+    );
+    if message_count == 0 {
+        return Err("Bundle does not contain any session messages".to_string());
+    }
+    let session_file = project_dir.join(format!("{}.jsonl", session_id));
+    fs::write(&session_file, content)
+        .map_err(|e| format!("Failed to write session file: {}", e))?;
+
+    let (git_records_imported, git_records_dropped) = match &bundle.git_records {
+        Some(bytes) => match filter_git_records(bytes, &project_path) {
+            Ok((records, dropped)) => {
+                let records_path =
+                    super::prompt_tracker::get_git_records_path(&session_id, &project_id)
+                        .map_err(|e| e.to_string())?;
+                if !records.is_empty() {
+                    if let Some(parent) = records_path.parent() {
+                        fs::create_dir_all(parent)
+                            .map_err(|e| format!("Failed to create sessions directory: {}", e))?;
+                    }
+                    let json = serde_json::to_string_pretty(&records)
+                        .map_err(|e| format!("Failed to serialize git records: {}", e))?;
+                    fs::write(&records_path, json)
+                        .map_err(|e| format!("Failed to write git records: {}", e))?;
+                }
+                (records.len(), dropped)
+            }
+            Err(e) => {
+                log::warn!("Skipping git records from bundle: {}", e);
+                (0, 0)
+            }
+        },
+        None => (0, 0),
+    };
+
+    log::info!(
+        "Imported session {} ({} messages) into {:?}",
+        session_id,
+        message_count,
+        session_file
+    );
+
+    Ok(ImportResult {
+        session_id_changed: session_id != original_id,
+        session_id,
+        project_id,
+        project_path,
+        message_count,
+        git_records_imported,
+        git_records_dropped,
+    })
+}
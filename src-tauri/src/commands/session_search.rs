@@ -0,0 +1,635 @@
+use rusqlite::{params, Connection, ToSql};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+use super::session_export::escape_html;
+use super::storage::AgentDb;
+
+/// Interval between background indexing passes
+const INDEX_INTERVAL_SECS: u64 = 60;
+
+/// Longest message text stored in the index (characters)
+const MAX_INDEXED_CHARS: usize = 32_000;
+
+/// Default and maximum number of hits returned by `search_sessions`
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 500;
+
+/// Match delimiters requested from FTS5 `snippet()`; replaced with `<mark>` after escaping
+const FTS_MARK_START: char = '\u{1}';
+const FTS_MARK_END: char = '\u{2}';
+
+/// Context kept around a match when building snippets for short queries (characters)
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Bytes hashed from the start of a file to notice it was rewritten in place
+const HEAD_HASH_BYTES: u64 = 4096;
+
+/// Serializes indexing passes so two indexers never index the same byte range
+/// of a file twice
+static INDEX_LOCK: once_cell::sync::Lazy<std::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(()));
+
+/// A ranked search hit inside a session transcript
+#[derive(Debug, Serialize)]
+pub struct SessionSearchHit {
+    pub project_id: String,
+    pub project_path: String,
+    pub session_id: String,
+    /// Index of the message in `load_session_history` output
+    pub message_index: i64,
+    pub role: String,
+    pub timestamp: Option<String>,
+    /// Matching excerpt, HTML-escaped, with `<mark>` around the matched terms
+    pub snippet: String,
+    pub tool_names: Vec<String>,
+    /// Relevance score, higher is better
+    pub score: f64,
+}
+
+/// A user/assistant message extracted for indexing
+struct IndexedMessage {
+    session_id: String,
+    message_index: i64,
+    role: String,
+    timestamp: Option<String>,
+    content: String,
+    tool_names: String,
+}
+
+/// Indexing progress of a single JSONL file
+struct IndexState {
+    byte_offset: u64,
+    mtime: i64,
+    message_count: i64,
+    project_path: Option<String>,
+    /// Hash of the first min(byte_offset, HEAD_HASH_BYTES) bytes already indexed
+    head_hash: Option<String>,
+}
+
+fn file_mtime(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// Hash of the first `min(len, HEAD_HASH_BYTES)` bytes of a file
+fn head_hash(path: &Path, len: u64) -> std::io::Result<String> {
+    let mut head = Vec::new();
+    fs::File::open(path)?
+        .take(len.min(HEAD_HASH_BYTES))
+        .read_to_end(&mut head)?;
+    Ok(format!("{:x}", Sha256::digest(&head)))
+}
+
+/// Extracts searchable text and tool names from a message's content
+fn extract_content(content: &serde_json::Value) -> (String, Vec<String>) {
+    let mut text = String::new();
+    let mut tools = Vec::new();
+
+    if let Some(s) = content.as_str() {
+        text.push_str(s);
+    } else if let Some(blocks) = content.as_array() {
+        for block in blocks {
+            match block.get("type").and_then(|t| t.as_str()) {
+                Some("text") => {
+                    if let Some(t) = block.get("text").and_then(|t| t.as_str()) {
+                        if !text.is_empty() {
+                            text.push('\n');
+                        }
+                        text.push_str(t);
+                    }
+                }
+                Some("tool_use") => {
+                    if let Some(name) = block.get("name").and_then(|n| n.as_str()) {
+                        if !tools.iter().any(|t| t == name) {
+                            tools.push(name.to_string());
+                        }
+                    }
+                }
+                // tool_result / thinking / images are not indexed
+                _ => {}
+            }
+        }
+    }
+
+    if text.chars().count() > MAX_INDEXED_CHARS {
+        text = text.chars().take(MAX_INDEXED_CHARS).collect();
+    }
+    (text, tools)
+}
+
+/// Parses complete JSONL lines appended since the last indexing pass.
+/// Returns the messages to index, the bytes consumed, the number of JSON lines seen
+/// and the project path (cwd).
+fn parse_jsonl_increment(
+    path: &Path,
+    offset: u64,
+    first_index: i64,
+    known_project_path: Option<String>,
+) -> std::io::Result<(Vec<IndexedMessage>, u64, i64, Option<String>)> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
+    // Only consume complete lines; a partially written last line is picked up next time
+    let consumed = match buffer.iter().rposition(|b| *b == b'\n') {
+        Some(pos) => pos + 1,
+        None => return Ok((Vec::new(), 0, 0, known_project_path)),
+    };
+
+    let fallback_session_id = path
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let mut messages = Vec::new();
+    let mut project_path = known_project_path;
+    let mut seen = 0i64;
+
+    for line in String::from_utf8_lossy(&buffer[..consumed]).lines() {
+        // Same numbering as load_session_history: every line that parses as JSON
+        let value = match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let message_index = first_index + seen;
+        seen += 1;
+
+        if project_path.is_none() {
+            if let Some(cwd) = value.get("cwd").and_then(|v| v.as_str()) {
+                project_path = Some(cwd.to_string());
+            }
+        }
+
+        let role = match value.get("type").and_then(|t| t.as_str()) {
+            Some(role @ ("user" | "assistant")) => role.to_string(),
+            _ => continue,
+        };
+        if value.get("isMeta").and_then(|v| v.as_bool()) == Some(true) {
+            continue;
+        }
+        let content = match value.get("message").and_then(|m| m.get("content")) {
+            Some(content) => content,
+            None => continue,
+        };
+
+        let (text, tools) = extract_content(content);
+        if text.trim().is_empty() && tools.is_empty() {
+            continue;
+        }
+
+        messages.push(IndexedMessage {
+            session_id: value
+                .get("sessionId")
+                .and_then(|s| s.as_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| fallback_session_id.clone()),
+            message_index,
+            role,
+            timestamp: value
+                .get("timestamp")
+                .and_then(|t| t.as_str())
+                .map(|t| t.to_string()),
+            content: text,
+            tool_names: tools.join(" "),
+        });
+    }
+
+    Ok((messages, consumed as u64, seen, project_path))
+}
+
+/// Incrementally indexes ~/.claude/projects/**/*.jsonl into `session_search`.
+/// Files are tracked by byte offset and mtime like the usage ingester, plus a hash of
+/// their head to catch in-place rewrites; rows of files that no longer exist are dropped.
+/// Returns the number of messages added to the index.
+pub fn index_sessions(conn: &mut Connection, claude_path: &Path) -> Result<usize, String> {
+    let _guard = INDEX_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let mut states: HashMap<String, IndexState> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT file_path, byte_offset, mtime, message_count, project_path, head_hash
+                 FROM session_search_state",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    IndexState {
+                        byte_offset: row.get::<_, i64>(1)? as u64,
+                        mtime: row.get(2)?,
+                        message_count: row.get(3)?,
+                        project_path: row.get(4)?,
+                        head_hash: row.get(5)?,
+                    },
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows.flatten() {
+            states.insert(row.0, row.1);
+        }
+    }
+
+    let mut pending: Vec<(PathBuf, String, u64, i64)> = Vec::new();
+    let mut existing: HashSet<String> = HashSet::new();
+    if let Ok(projects) = fs::read_dir(claude_path.join("projects")) {
+        for project in projects.flatten() {
+            if !project.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let project_id = project.file_name().to_string_lossy().to_string();
+
+            for entry in walkdir::WalkDir::new(project.path())
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
+            {
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let size = metadata.len();
+                let mtime = file_mtime(&metadata);
+                let key = entry.path().to_string_lossy().to_string();
+                existing.insert(key.clone());
+
+                if let Some(state) = states.get(&key) {
+                    if state.byte_offset == size && state.mtime == mtime {
+                        continue;
+                    }
+                }
+                pending.push((entry.path().to_path_buf(), project_id.clone(), size, mtime));
+            }
+        }
+    }
+
+    let mut indexed = 0usize;
+    for (path, project_id, size, mtime) in pending {
+        let state = states.remove(&path.to_string_lossy().to_string());
+        indexed += index_file(conn, &path, &project_id, size, mtime, state)?;
+    }
+
+    let removed: Vec<String> = states
+        .into_keys()
+        .filter(|key| !existing.contains(key))
+        .collect();
+    if !removed.is_empty() {
+        prune_files(conn, &removed)?;
+        log::info!(
+            "Dropped {} deleted session files from the search index",
+            removed.len()
+        );
+    }
+
+    if indexed > 0 {
+        log::info!("Indexed {} new session messages for search", indexed);
+    }
+    Ok(indexed)
+}
+
+/// Indexes the unread part of one JSONL file in a single transaction.
+/// The caller must hold `INDEX_LOCK`.
+fn index_file(
+    conn: &mut Connection,
+    path: &Path,
+    project_id: &str,
+    size: u64,
+    mtime: i64,
+    state: Option<IndexState>,
+) -> Result<usize, String> {
+    let key = path.to_string_lossy().to_string();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let (mut offset, mut message_count, mut project_path, known_head) = match state {
+        Some(state) => (
+            state.byte_offset,
+            state.message_count,
+            state.project_path,
+            state.head_hash,
+        ),
+        None => (0, 0, None, None),
+    };
+
+    // The file shrank or its already indexed head changed (rewritten, possibly grown back
+    // past the old offset): drop its rows and start over
+    let rewritten = size < offset
+        || match &known_head {
+            Some(known) if offset > 0 => head_hash(path, offset).ok().as_ref() != Some(known),
+            _ => false,
+        };
+    if rewritten {
+        tx.execute(
+            "DELETE FROM session_search WHERE source_file = ?1",
+            params![key],
+        )
+        .map_err(|e| e.to_string())?;
+        offset = 0;
+        message_count = 0;
+        project_path = None;
+    }
+
+    let (messages, consumed, seen, project_path) =
+        match parse_jsonl_increment(path, offset, message_count, project_path) {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("Failed to index session file {:?}: {}", path, e);
+                return Ok(0);
+            }
+        };
+
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT INTO session_search (
+                    content, tool_names, project_id, project_path, session_id,
+                    message_index, role, timestamp, source_file
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )
+            .map_err(|e| e.to_string())?;
+
+        for message in &messages {
+            stmt.execute(params![
+                message.content,
+                message.tool_names,
+                project_id,
+                project_path,
+                message.session_id,
+                message.message_index,
+                message.role,
+                message.timestamp,
+                key,
+            ])
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let new_offset = offset + consumed;
+    let new_head = head_hash(path, new_offset).ok();
+
+    tx.execute(
+        "INSERT OR REPLACE INTO session_search_state
+            (file_path, byte_offset, mtime, message_count, project_path, head_hash, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, CURRENT_TIMESTAMP)",
+        params![
+            key,
+            new_offset as i64,
+            mtime,
+            message_count + seen,
+            project_path,
+            new_head
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(messages.len())
+}
+
+/// Removes the index rows and progress of files that were deleted.
+/// The caller must hold `INDEX_LOCK`.
+fn prune_files(conn: &mut Connection, keys: &[String]) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for key in keys {
+        tx.execute(
+            "DELETE FROM session_search WHERE source_file = ?1",
+            params![key],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "DELETE FROM session_search_state WHERE file_path = ?1",
+            params![key],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+fn get_claude_path() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
+        .ok_or("Failed to get home directory")?
+        .join(".claude"))
+}
+
+/// Starts the background session indexer on its own database connection.
+/// Emits `session-index-updated` with the number of new messages after each productive pass.
+pub fn start_session_indexer(app: AppHandle) {
+    std::thread::spawn(move || {
+        let db_path = match app.path().app_data_dir() {
+            Ok(dir) => dir.join("agents.db"),
+            Err(e) => {
+                log::error!("Session indexer could not resolve app data dir: {}", e);
+                return;
+            }
+        };
+
+        let mut conn = match Connection::open(&db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Session indexer failed to open database: {}", e);
+                return;
+            }
+        };
+        let _ = conn.busy_timeout(Duration::from_secs(5));
+
+        loop {
+            match get_claude_path().and_then(|path| index_sessions(&mut conn, &path)) {
+                Ok(indexed) if indexed > 0 => {
+                    let _ = app.emit("session-index-updated", indexed);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Background session indexing failed: {}", e),
+            }
+            std::thread::sleep(Duration::from_secs(INDEX_INTERVAL_SECS));
+        }
+    });
+}
+
+/// Builds an HTML-escaped, `<mark>`-highlighted excerpt around the first matching term.
+/// Used for queries the trigram index cannot match (terms shorter than 3 characters).
+fn build_snippet(text: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.to_lowercase().chars().collect();
+    // Lowercasing can change the length of some characters; fall back to no highlight then
+    if lower.len() != chars.len() {
+        return escape_html(
+            &chars
+                .iter()
+                .take(SNIPPET_CONTEXT_CHARS * 2)
+                .collect::<String>(),
+        );
+    }
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        let needle: Vec<char> = term.to_lowercase().chars().collect();
+        if needle.is_empty() || needle.len() > lower.len() {
+            continue;
+        }
+        let mut i = 0;
+        while i + needle.len() <= lower.len() {
+            if lower[i..i + needle.len()] == needle[..] {
+                ranges.push((i, i + needle.len()));
+                i += needle.len();
+            } else {
+                i += 1;
+            }
+        }
+    }
+    ranges.sort();
+
+    let first = ranges.first().map(|r| r.0).unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (first + SNIPPET_CONTEXT_CHARS * 2).min(chars.len());
+
+    let escaped = |range: &[char]| escape_html(&range.iter().collect::<String>());
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut pos = start;
+    for (range_start, range_end) in ranges {
+        if range_start < pos || range_start >= end {
+            continue;
+        }
+        snippet.push_str(&escaped(&chars[pos..range_start]));
+        snippet.push_str("<mark>");
+        snippet.push_str(&escaped(&chars[range_start..range_end.min(end)]));
+        snippet.push_str("</mark>");
+        pos = range_end.min(end);
+    }
+    snippet.push_str(&escaped(&chars[pos..end]));
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Escapes an FTS5 snippet and turns its match delimiters into `<mark>` tags
+fn highlight_fts_snippet(snippet: &str) -> String {
+    escape_html(snippet)
+        .replace(FTS_MARK_START, "<mark>")
+        .replace(FTS_MARK_END, "</mark>")
+}
+
+/// Full-text search across all session transcripts.
+/// Every whitespace-separated term must match (message text or tool names).
+/// Searches what the background indexer has indexed so far; it emits
+/// `session-index-updated` when new messages become searchable.
+#[command]
+pub async fn search_sessions(
+    db: State<'_, AgentDb>,
+    query: String,
+    project_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<SessionSearchHit>, String> {
+    let terms: Vec<String> = query.split_whitespace().map(|t| t.to_string()).collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT) as i64;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    // The trigram tokenizer only matches terms of 3+ characters; shorter ones need a scan
+    let use_fts = terms.iter().all(|t| t.chars().count() >= 3);
+
+    let mut filters: Vec<String> = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    if use_fts {
+        let fts_query = terms
+            .iter()
+            .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        filters.push(format!("session_search MATCH ?{}", values.len() + 1));
+        values.push(Box::new(fts_query));
+    } else {
+        for term in &terms {
+            let pattern = format!(
+                "%{}%",
+                term.replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+            let n = values.len() + 1;
+            filters.push(format!(
+                "(content LIKE ?{n} ESCAPE '\\' OR tool_names LIKE ?{n} ESCAPE '\\')"
+            ));
+            values.push(Box::new(pattern));
+        }
+    }
+    if let Some(project_id) = project_id {
+        filters.push(format!("project_id = ?{}", values.len() + 1));
+        values.push(Box::new(project_id));
+    }
+
+    let sql = format!(
+        "SELECT project_id, project_path, session_id, message_index, role, timestamp, {snippet},
+                tool_names, {rank} AS score
+         FROM session_search WHERE {filters} ORDER BY {order} LIMIT {limit}",
+        snippet = if use_fts {
+            "snippet(session_search, -1, char(1), char(2), '…', 24)"
+        } else {
+            "content"
+        },
+        rank = if use_fts {
+            "bm25(session_search, 1.0, 0.5)"
+        } else {
+            "0.0"
+        },
+        filters = filters.join(" AND "),
+        order = if use_fts { "score" } else { "timestamp DESC" },
+        limit = limit,
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let params: Vec<&dyn ToSql> = values.iter().map(|v| v.as_ref()).collect();
+    let hits = stmt
+        .query_map(params.as_slice(), |row| {
+            let project_id: String = row.get(0)?;
+            let project_path: Option<String> = row.get(1)?;
+            let text: String = row.get(6)?;
+            let tool_names: String = row.get(7)?;
+            let bm25: f64 = row.get(8)?;
+            Ok(SessionSearchHit {
+                project_path: project_path.unwrap_or_else(|| project_id.clone()),
+                project_id,
+                session_id: row.get(2)?,
+                message_index: row.get(3)?,
+                role: row.get(4)?,
+                timestamp: row.get(5)?,
+                snippet: if use_fts {
+                    highlight_fts_snippet(&text)
+                } else {
+                    build_snippet(&text, &terms)
+                },
+                tool_names: tool_names
+                    .split_whitespace()
+                    .map(|t| t.to_string())
+                    .collect(),
+                // bm25() is lower-is-better; flip it so callers can sort descending
+                score: -bm25,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Session search failed: {}", e))?;
+
+    Ok(hits)
+}
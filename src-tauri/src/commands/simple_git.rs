@@ -1,5 +1,5 @@
 use log;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 #[cfg(target_os = "windows")]
@@ -207,81 +207,393 @@ pub fn git_commit_changes(project_path: &str, message: &str) -> Result<bool, Str
     Ok(true)
 }
 
-/// Reset repository to a specific commit
-pub fn git_reset_hard(project_path: &str, commit: &str) -> Result<(), String> {
-    log::info!("Resetting repository to commit: {}", commit);
+/// A work tree plus the git directory that tracks it.
+/// `git_dir: None` is the project's own repository; `Some` is a separate (e.g. shadow)
+/// repository driven through GIT_DIR/GIT_WORK_TREE, so the project's `.git` is never touched.
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    pub work_tree: String,
+    pub git_dir: Option<PathBuf>,
+}
+
+impl GitRepo {
+    /// The repository the project itself lives in
+    pub fn project(work_tree: &str) -> Self {
+        Self {
+            work_tree: work_tree.to_string(),
+            git_dir: None,
+        }
+    }
+
+    /// A separate repository at `git_dir` using the project as its work tree
+    pub fn separate(work_tree: &str, git_dir: PathBuf) -> Self {
+        Self {
+            work_tree: work_tree.to_string(),
+            git_dir: Some(git_dir),
+        }
+    }
+}
+
+/// Run a git command and return trimmed stdout
+fn git_output(repo: &GitRepo, args: &[&str], envs: &[(&str, &str)]) -> Result<String, String> {
+    Ok(git_output_raw(repo, args, envs)?.trim().to_string())
+}
 
+/// Run a git command and return stdout as-is (for patches and -z output)
+pub(crate) fn git_output_raw(
+    repo: &GitRepo,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<String, String> {
     let mut cmd = Command::new("git");
-    cmd.args(["reset", "--hard", commit]);
-    cmd.current_dir(project_path);
+    cmd.args(args);
+    cmd.current_dir(&repo.work_tree);
+    if let Some(git_dir) = &repo.git_dir {
+        cmd.env("GIT_DIR", git_dir);
+        cmd.env("GIT_WORK_TREE", &repo.work_tree);
+    }
+    for (key, value) in envs {
+        cmd.env(key, value);
+    }
 
     #[cfg(target_os = "windows")]
     cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
     let output = cmd
         .output()
-        .map_err(|e| format!("Failed to reset: {}", e))?;
+        .map_err(|e| format!("Failed to run git {}: {}", args.join(" "), e))?;
 
     if !output.status.success() {
         return Err(format!(
-            "Git reset failed: {}",
+            "Git {} failed: {}",
+            args.first().unwrap_or(&""),
             String::from_utf8_lossy(&output.stderr)
         ));
     }
 
-    log::info!("Successfully reset to commit: {}", commit);
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
-/// Save uncommitted changes to stash
-pub fn git_stash_save(project_path: &str, message: &str) -> Result<(), String> {
-    // Check if there are uncommitted changes
-    let mut status_cmd = Command::new("git");
-    status_cmd.args(["status", "--porcelain"]);
-    status_cmd.current_dir(project_path);
+/// Snapshot the whole working tree (tracked, modified and untracked files, honoring
+/// .gitignore) into a commit on top of HEAD without touching HEAD, the index or the stash.
+/// Returns the snapshot commit hash.
+pub fn git_snapshot_worktree(repo: &GitRepo, message: &str) -> Result<String, String> {
+    // Build the tree in a throwaway index so the user's staging area is left alone
+    let index_path = git_output(
+        repo,
+        &["rev-parse", "--git-path", "workbench-snapshot.index"],
+        &[],
+    )?;
+    let index_path = if Path::new(&index_path).is_absolute() {
+        index_path
+    } else {
+        Path::new(&repo.work_tree)
+            .join(index_path)
+            .to_string_lossy()
+            .to_string()
+    };
+    let _ = std::fs::remove_file(&index_path);
+    let index_env = [("GIT_INDEX_FILE", index_path.as_str())];
+
+    let head = git_head(repo);
+    let result = (|| {
+        if head.is_some() {
+            git_output(repo, &["read-tree", "HEAD"], &index_env)?;
+        }
+        git_output(repo, &["add", "-A", "--", ":/"], &index_env)?;
+        let tree = git_output(repo, &["write-tree"], &index_env)?;
+
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+        if let Some(head) = &head {
+            args.push("-p");
+            args.push(head.as_str());
+        }
+        // Fixed identity so snapshots work even without user.name/user.email configured
+        git_output(
+            repo,
+            &args,
+            &[
+                ("GIT_AUTHOR_NAME", "Claude Workbench"),
+                ("GIT_AUTHOR_EMAIL", "workbench@localhost"),
+                ("GIT_COMMITTER_NAME", "Claude Workbench"),
+                ("GIT_COMMITTER_EMAIL", "workbench@localhost"),
+            ],
+        )
+    })();
+
+    let _ = std::fs::remove_file(&index_path);
+    result
+}
+
+/// HEAD commit of a repository, or None if nothing has been committed yet
+pub fn git_head(repo: &GitRepo) -> Option<String> {
+    git_output(repo, &["rev-parse", "--verify", "-q", "HEAD^{commit}"], &[])
+        .ok()
+        .filter(|head| !head.is_empty())
+}
+
+/// Create a bare repository (used as a separate git dir) if it does not exist yet.
+/// Returns true if it was created.
+pub fn git_init_bare(git_dir: &Path) -> Result<bool, String> {
+    if git_dir.join("HEAD").exists() {
+        return Ok(false);
+    }
+
+    std::fs::create_dir_all(git_dir)
+        .map_err(|e| format!("Failed to create {:?}: {}", git_dir, e))?;
+
+    let mut cmd = Command::new("git");
+    cmd.args(["init", "--bare", "-q"]);
+    cmd.arg(git_dir);
 
     #[cfg(target_os = "windows")]
-    status_cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let status_output = status_cmd
+    let output = cmd
         .output()
-        .map_err(|e| format!("Failed to check status: {}", e))?;
+        .map_err(|e| format!("Failed to init git: {}", e))?;
 
-    if status_output.stdout.is_empty() {
-        log::debug!("No uncommitted changes to stash");
-        return Ok(()); // No changes to stash
+    if !output.status.success() {
+        return Err(format!(
+            "Git init failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
     }
 
-    log::info!("Stashing uncommitted changes: {}", message);
+    log::info!("Initialized bare repository at {:?}", git_dir);
+    Ok(true)
+}
+
+/// Whether two commits point at the same tree
+fn git_same_tree(repo: &GitRepo, a: &str, b: &str) -> Result<bool, String> {
+    let tree_a = git_output(repo, &["rev-parse", &format!("{}^{{tree}}", a)], &[])?;
+    let tree_b = git_output(repo, &["rev-parse", &format!("{}^{{tree}}", b)], &[])?;
+    Ok(tree_a == tree_b)
+}
+
+/// Snapshot the working tree (see `git_snapshot_worktree`), or return HEAD itself
+/// when the working tree has no changes against it.
+pub fn git_snapshot_if_changed(repo: &GitRepo, message: &str) -> Result<String, String> {
+    let head = git_head(repo);
+    let commit = git_snapshot_worktree(repo, message)?;
+
+    match head {
+        Some(head) if git_same_tree(repo, &head, &commit)? => Ok(head),
+        _ => Ok(commit),
+    }
+}
 
-    let mut stash_cmd = Command::new("git");
-    stash_cmd.args(["stash", "save", "-u", message]);
-    stash_cmd.current_dir(project_path);
+/// Commit the whole working tree onto HEAD without using or changing the index.
+/// Returns: Ok(true) if committed, Ok(false) if the tree matches HEAD
+pub fn git_checkpoint_commit(repo: &GitRepo, message: &str) -> Result<bool, String> {
+    let head = git_head(repo);
+    let commit = git_snapshot_worktree(repo, message)?;
+
+    if let Some(head) = &head {
+        if git_same_tree(repo, head, &commit)? {
+            return Ok(false);
+        }
+    }
+
+    git_output(repo, &["update-ref", "HEAD", &commit], &[])?;
+    log::info!("Committed checkpoint: {}", message);
+    Ok(true)
+}
+
+/// Point a ref at a commit (creating it if needed)
+pub fn git_update_ref(repo: &GitRepo, ref_name: &str, commit: &str) -> Result<(), String> {
+    git_output(repo, &["update-ref", ref_name, commit], &[])?;
+    Ok(())
+}
+
+/// Delete a ref
+pub fn git_delete_ref(repo: &GitRepo, ref_name: &str) -> Result<(), String> {
+    git_output(repo, &["update-ref", "-d", ref_name], &[])?;
+    Ok(())
+}
+
+/// A ref with its commit, creation time and full commit message
+pub struct GitRefInfo {
+    pub name: String,
+    pub commit: String,
+    pub created_at: i64,
+    pub message: String,
+}
+
+/// List refs under a prefix, newest first
+pub fn git_list_refs(repo: &GitRepo, prefix: &str) -> Result<Vec<GitRefInfo>, String> {
+    let output = git_output(
+        repo,
+        &[
+            "for-each-ref",
+            "--sort=-creatordate",
+            "--format=%(refname)%1f%(objectname)%1f%(creatordate:unix)%1f%(contents)%1e",
+            prefix,
+        ],
+        &[],
+    )?;
+
+    Ok(output
+        .split('\u{1e}')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(4, '\u{1f}');
+            let name = fields.next()?.to_string();
+            if name.is_empty() {
+                return None;
+            }
+            Some(GitRefInfo {
+                name,
+                commit: fields.next()?.to_string(),
+                created_at: fields.next()?.parse().unwrap_or(0),
+                message: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Make the working tree match `target` without moving HEAD.
+/// `current` must be a snapshot of the working tree (see `git_snapshot_worktree`): files it
+/// contains that `target` lacks are deleted, everything else is checked out from `target`,
+/// and the index is reset to HEAD so nothing ends up staged.
+pub fn git_restore_worktree(repo: &GitRepo, current: &str, target: &str) -> Result<(), String> {
+    log::info!(
+        "Restoring working tree to {} (from snapshot {})",
+        target,
+        current
+    );
+
+    let toplevel = git_toplevel(repo)?;
+    let removed = git_output(
+        repo,
+        &[
+            "diff",
+            "--name-only",
+            "-z",
+            "--no-renames",
+            "--diff-filter=D",
+            current,
+            target,
+        ],
+        &[],
+    )?;
+    for path in removed.split('\0').filter(|p| !p.is_empty()) {
+        let file = Path::new(&toplevel).join(path);
+        if let Err(e) = std::fs::remove_file(&file) {
+            log::warn!("Failed to remove {:?}: {}", file, e);
+        }
+    }
+
+    git_output(
+        repo,
+        &["restore", "--source", target, "--worktree", "--", ":/"],
+        &[],
+    )?;
+    // Drop any staged state so the restored files show up as plain working-tree changes
+    if git_head(repo).is_some() {
+        git_output(repo, &["reset", "-q"], &[])?;
+    }
+
+    log::info!("Working tree restored to {}", target);
+    Ok(())
+}
+
+/// Root of the work tree (diff paths are relative to it)
+pub fn git_toplevel(repo: &GitRepo) -> Result<String, String> {
+    git_output(repo, &["rev-parse", "--show-toplevel"], &[])
+}
+
+/// Check out the given paths (relative to the work tree root) from `source` into the
+/// working tree only; the index and HEAD are left alone
+pub fn git_restore_paths(repo: &GitRepo, source: &str, paths: &[&str]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let pathspecs: Vec<String> = paths
+        .iter()
+        .map(|p| format!(":(top,literal){}", p))
+        .collect();
+    let mut args = vec!["restore", "--source", source, "--worktree", "--"];
+    args.extend(pathspecs.iter().map(|p| p.as_str()));
+    git_output(repo, &args, &[])?;
+    Ok(())
+}
+
+/// Apply a patch file to the working tree (not the index). All hunks apply or none do.
+pub fn git_apply_patch(repo: &GitRepo, patch_file: &Path, reverse: bool) -> Result<(), String> {
+    let patch_file = patch_file.to_string_lossy().to_string();
+    let mut args = vec!["apply", "--whitespace=nowarn"];
+    if reverse {
+        args.push("-R");
+    }
+    args.push(patch_file.as_str());
+    git_output(repo, &args, &[])?;
+    Ok(())
+}
+
+/// Create a worktree on a new branch checked out at a specific commit
+pub fn git_worktree_add(
+    project_path: &str,
+    worktree_path: &str,
+    branch: &str,
+    commit: &str,
+) -> Result<(), String> {
+    log::info!(
+        "Creating worktree {} on branch {} at commit {}",
+        worktree_path,
+        branch,
+        commit
+    );
+
+    let mut cmd = Command::new("git");
+    cmd.args(["worktree", "add", "-b", branch, worktree_path, commit]);
+    cmd.current_dir(project_path);
 
     #[cfg(target_os = "windows")]
-    stash_cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
 
-    let output = stash_cmd
+    let output = cmd
         .output()
-        .map_err(|e| format!("Failed to stash: {}", e))?;
+        .map_err(|e| format!("Failed to create worktree: {}", e))?;
 
     if !output.status.success() {
-        log::warn!(
-            "Git stash warning: {}",
+        return Err(format!(
+            "Git worktree add failed: {}",
             String::from_utf8_lossy(&output.stderr)
-        );
+        ));
     }
 
+    log::info!("Successfully created worktree: {}", worktree_path);
     Ok(())
 }
 
-/// Tauri command: Check and initialize Git repository
-#[tauri::command]
-pub fn check_and_init_git(project_path: String) -> Result<bool, String> {
-    let was_not_initialized = !is_git_repo(&project_path);
+/// Remove a worktree created by `git_worktree_add` together with its branch
+pub fn git_worktree_remove(
+    project_path: &str,
+    worktree_path: &str,
+    branch: &str,
+) -> Result<(), String> {
+    log::info!("Removing worktree {} and branch {}", worktree_path, branch);
+
+    let repo = GitRepo::project(project_path);
+    git_output(
+        &repo,
+        &["worktree", "remove", "--force", worktree_path],
+        &[],
+    )
+    .map_err(|e| format!("Git worktree remove failed: {}", e))?;
+    git_output(&repo, &["branch", "-D", branch], &[])
+        .map_err(|e| format!("Git branch delete failed: {}", e))?;
 
-    // Always call ensure_git_repo - it will check for commits too
-    ensure_git_repo(&project_path)?;
+    Ok(())
+}
+
+/// Tauri command: Check and initialize the checkpoint repository (project git or shadow)
+#[tauri::command]
+pub fn check_and_init_git(app: tauri::AppHandle, project_path: String) -> Result<bool, String> {
+    let backend = super::checkpoint::load_backend(&app);
+    let repo = super::checkpoint::open_repo(&app, &project_path, backend)?;
 
-    Ok(was_not_initialized)
+    // Returns true if the repository had to be created
+    super::checkpoint::ensure_repo(&repo)
 }
@@ -3,6 +3,7 @@ use rusqlite::{params, types::ValueRef, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value as JsonValue};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager, State};
 
@@ -15,7 +16,12 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         .path()
         .app_data_dir()
         .expect("Failed to get app data dir");
-    std::fs::create_dir_all(&app_dir).expect("Failed to create app data dir");
+    open_database(&app_dir)
+}
+
+/// Open (and migrate) `agents.db` in the given app data directory
+pub fn open_database(app_dir: &Path) -> SqliteResult<Connection> {
+    std::fs::create_dir_all(app_dir).expect("Failed to create app data dir");
 
     let db_path = app_dir.join("agents.db");
     let conn = Connection::open(db_path)?;
@@ -95,9 +101,117 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
 
     log::info!("✅ Database indexes created successfully (6 indexes)");
 
+    // ========== 增量用量导入：去重哈希与来源文件 ==========
+    // Older databases predate these columns, so add them in place
+    ensure_column(&conn, "usage_entries", "message_hash", "TEXT")?;
+    ensure_column(&conn, "usage_entries", "source_file", "TEXT")?;
+    ensure_column(&conn, "usage_entries", "provider", "TEXT")?;
+    ensure_column(&conn, "usage_entries", "cost_source", "TEXT DEFAULT 'calculated'")?;
+
+    // 7. 唯一索引：message_id:request_id 去重（无 ID 的条目不参与去重）
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_usage_message_hash
+         ON usage_entries(message_hash) WHERE message_hash IS NOT NULL",
+        [],
+    )?;
+
+    // 8. 来源文件索引（文件被截断时按文件清理）
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_usage_source_file
+         ON usage_entries(source_file)",
+        [],
+    )?;
+
+    // Per-file ingestion progress for incremental JSONL parsing
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS usage_ingest_state (
+            file_path TEXT PRIMARY KEY,
+            byte_offset INTEGER NOT NULL DEFAULT 0,
+            mtime INTEGER NOT NULL DEFAULT 0,
+            project_path TEXT,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // ========== 会话全文检索（FTS5） ==========
+    // trigram 分词器支持中英文混合子串匹配
+    conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS session_search USING fts5(
+            content,
+            tool_names,
+            project_id UNINDEXED,
+            project_path UNINDEXED,
+            session_id UNINDEXED,
+            message_index UNINDEXED,
+            role UNINDEXED,
+            timestamp UNINDEXED,
+            source_file UNINDEXED,
+            tokenize = 'trigram'
+        )",
+        [],
+    )?;
+
+    // Per-file indexing progress for incremental session search
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_search_state (
+            file_path TEXT PRIMARY KEY,
+            byte_offset INTEGER NOT NULL DEFAULT 0,
+            mtime INTEGER NOT NULL DEFAULT 0,
+            message_count INTEGER NOT NULL DEFAULT 0,
+            project_path TEXT,
+            head_hash TEXT,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    ensure_column(&conn, "session_search_state", "head_hash", "TEXT")?;
+
+    // ========== 上下文压缩历史 ==========
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS compaction_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            project_path TEXT,
+            timestamp TEXT NOT NULL,
+            trigger TEXT NOT NULL,
+            strategy TEXT NOT NULL,
+            tokens_before INTEGER NOT NULL DEFAULT 0,
+            tokens_after INTEGER,
+            success INTEGER NOT NULL,
+            error TEXT,
+            new_session_id TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_compaction_events_session
+         ON compaction_events(session_id, timestamp)",
+        [],
+    )?;
+
     Ok(conn)
 }
 
+/// Adds a column to an existing table if it is missing (lightweight migration)
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl),
+            [],
+        )?;
+        log::info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
+
 /// Represents metadata about a database table
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TableInfo {
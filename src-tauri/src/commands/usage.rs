@@ -2,15 +2,35 @@
 // Source: https://github.com/meistrari/opcode
 
 use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{params, Connection, ToSql};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tauri::command;
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct UsageEntry {
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{command, AppHandle, Emitter, Manager, State};
+
+use super::pricing::TokenUsage;
+use super::storage::AgentDb;
+
+/// Interval between background ingestion passes
+const INGEST_INTERVAL_SECS: u64 = 60;
+
+/// Only rows at most this old when ingested are attributed to the active provider.
+/// Older rows (first-run backfill, turns made while the app was closed) keep provider NULL,
+/// since the provider they actually used is unknown.
+const LIVE_PROVIDER_WINDOW_SECS: i64 = 15 * 60;
+
+/// Serializes ingestion passes so the background ingester and on-demand refreshes
+/// never read the same byte range of a file twice
+static INGEST_LOCK: once_cell::sync::Lazy<std::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(()));
+
+/// A single usage row parsed from a JSONL transcript, ready for `usage_entries`
+#[derive(Debug, Clone)]
+struct UsageEntry {
     timestamp: String,
     model: String,
     input_tokens: u64,
@@ -20,6 +40,10 @@ pub struct UsageEntry {
     cost: f64,
     session_id: String,
     project_path: String,
+    message_hash: Option<String>,
+    provider: Option<String>,
+    /// "reported" when the transcript carried costUSD, "calculated" when priced locally
+    cost_source: &'static str,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,23 +90,6 @@ pub struct ProjectUsage {
     last_used: String,
 }
 
-// Claude 4 pricing constants (per million tokens)
-const OPUS_4_INPUT_PRICE: f64 = 15.0;
-const OPUS_4_OUTPUT_PRICE: f64 = 75.0;
-const OPUS_4_CACHE_WRITE_PRICE: f64 = 18.75;
-const OPUS_4_CACHE_READ_PRICE: f64 = 1.50;
-
-const SONNET_4_INPUT_PRICE: f64 = 3.0;
-const SONNET_4_OUTPUT_PRICE: f64 = 15.0;
-const SONNET_4_CACHE_WRITE_PRICE: f64 = 3.75;
-const SONNET_4_CACHE_READ_PRICE: f64 = 0.30;
-
-// Claude 3.5 Sonnet pricing (for backward compatibility)
-const SONNET_35_INPUT_PRICE: f64 = 3.0;
-const SONNET_35_OUTPUT_PRICE: f64 = 15.0;
-const SONNET_35_CACHE_WRITE_PRICE: f64 = 3.75;
-const SONNET_35_CACHE_READ_PRICE: f64 = 0.30;
-
 #[derive(Debug, Deserialize)]
 struct JsonlEntry {
     timestamp: String,
@@ -110,377 +117,607 @@ struct UsageData {
     cache_read_input_tokens: Option<u64>,
 }
 
-fn calculate_cost(model: &str, usage: &UsageData) -> f64 {
-    let input_tokens = usage.input_tokens.unwrap_or(0) as f64;
-    let output_tokens = usage.output_tokens.unwrap_or(0) as f64;
-    let cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
-    let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0) as f64;
-
-    // Calculate cost based on model
-    let (input_price, output_price, cache_write_price, cache_read_price) =
-        if model.contains("opus-4") || model.contains("claude-opus-4") {
-            (
-                OPUS_4_INPUT_PRICE,
-                OPUS_4_OUTPUT_PRICE,
-                OPUS_4_CACHE_WRITE_PRICE,
-                OPUS_4_CACHE_READ_PRICE,
-            )
-        } else if model.contains("sonnet-4") || model.contains("claude-sonnet-4") {
-            (
-                SONNET_4_INPUT_PRICE,
-                SONNET_4_OUTPUT_PRICE,
-                SONNET_4_CACHE_WRITE_PRICE,
-                SONNET_4_CACHE_READ_PRICE,
-            )
-        } else if model.contains("3.5") || model.contains("35") || model.contains("sonnet") {
-            // Default to Sonnet 3.5 pricing for any sonnet variant
-            (
-                SONNET_35_INPUT_PRICE,
-                SONNET_35_OUTPUT_PRICE,
-                SONNET_35_CACHE_WRITE_PRICE,
-                SONNET_35_CACHE_READ_PRICE,
-            )
-        } else {
-            // Return 0 for unknown models to avoid incorrect cost estimations
-            (0.0, 0.0, 0.0, 0.0)
-        };
+impl UsageData {
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage {
+            input_tokens: self.input_tokens.unwrap_or(0),
+            output_tokens: self.output_tokens.unwrap_or(0),
+            cache_creation_tokens: self.cache_creation_input_tokens.unwrap_or(0),
+            cache_read_tokens: self.cache_read_input_tokens.unwrap_or(0),
+        }
+    }
+}
 
-    // Calculate cost (prices are per million tokens)
-    let cost = (input_tokens * input_price / 1_000_000.0)
-        + (output_tokens * output_price / 1_000_000.0)
-        + (cache_creation_tokens * cache_write_price / 1_000_000.0)
-        + (cache_read_tokens * cache_read_price / 1_000_000.0);
+/// Ingestion progress of a single JSONL file
+struct IngestState {
+    byte_offset: u64,
+    mtime: i64,
+    project_path: Option<String>,
+}
 
-    cost
+fn file_mtime(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
 }
 
-fn parse_jsonl_file(
-    path: &PathBuf,
+/// Parses complete JSONL lines appended since the last ingestion pass.
+/// Returns the parsed entries, the number of bytes consumed and the project path (cwd).
+fn parse_jsonl_increment(
+    path: &Path,
+    offset: u64,
     encoded_project_name: &str,
-    processed_hashes: &mut HashSet<String>,
-) -> Vec<UsageEntry> {
+    known_project_path: Option<String>,
+    provider: Option<&str>,
+) -> std::io::Result<(Vec<UsageEntry>, u64, Option<String>)> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
+    // Only consume complete lines; a partially written last line is picked up next time
+    let consumed = match buffer.iter().rposition(|b| *b == b'\n') {
+        Some(pos) => pos + 1,
+        None => return Ok((Vec::new(), 0, known_project_path)),
+    };
+
+    // Extract session ID from the file path
+    let fallback_session_id = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
     let mut entries = Vec::new();
-    let mut actual_project_path: Option<String> = None;
-
-    if let Ok(content) = fs::read_to_string(path) {
-        // Extract session ID from the file path
-        let session_id = path
-            .parent()
-            .and_then(|p| p.file_name())
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        for line in content.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
+    let mut actual_project_path = known_project_path;
 
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(line) {
-                // Extract the actual project path from cwd if we haven't already
-                if actual_project_path.is_none() {
-                    if let Some(cwd) = json_value.get("cwd").and_then(|v| v.as_str()) {
-                        actual_project_path = Some(cwd.to_string());
-                    }
-                }
+    for line in String::from_utf8_lossy(&buffer[..consumed]).lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
 
-                // Try to parse as JsonlEntry for usage data
-                if let Ok(entry) = serde_json::from_value::<JsonlEntry>(json_value) {
-                    if let Some(message) = &entry.message {
-                        // Deduplication based on message ID and request ID
-                        if let (Some(msg_id), Some(req_id)) = (&message.id, &entry.request_id) {
-                            let unique_hash = format!("{}:{}", msg_id, req_id);
-                            if processed_hashes.contains(&unique_hash) {
-                                continue; // Skip duplicate entry
-                            }
-                            processed_hashes.insert(unique_hash);
-                        }
-
-                        if let Some(usage) = &message.usage {
-                            // Skip entries without meaningful token usage
-                            if usage.input_tokens.unwrap_or(0) == 0
-                                && usage.output_tokens.unwrap_or(0) == 0
-                                && usage.cache_creation_input_tokens.unwrap_or(0) == 0
-                                && usage.cache_read_input_tokens.unwrap_or(0) == 0
-                            {
-                                continue;
-                            }
-
-                            let cost = entry.cost_usd.unwrap_or_else(|| {
-                                if let Some(model_str) = &message.model {
-                                    calculate_cost(model_str, usage)
-                                } else {
-                                    0.0
-                                }
-                            });
-
-                            // Use actual project path if found, otherwise use encoded name
-                            let project_path = actual_project_path
-                                .clone()
-                                .unwrap_or_else(|| encoded_project_name.to_string());
-
-                            entries.push(UsageEntry {
-                                timestamp: entry.timestamp,
-                                model: message
-                                    .model
-                                    .clone()
-                                    .unwrap_or_else(|| "unknown".to_string()),
-                                input_tokens: usage.input_tokens.unwrap_or(0),
-                                output_tokens: usage.output_tokens.unwrap_or(0),
-                                cache_creation_tokens: usage
-                                    .cache_creation_input_tokens
-                                    .unwrap_or(0),
-                                cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
-                                cost,
-                                session_id: entry.session_id.unwrap_or_else(|| session_id.clone()),
-                                project_path,
-                            });
-                        }
-                    }
-                }
+        let json_value = match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+
+        // Extract the actual project path from cwd if we haven't already
+        if actual_project_path.is_none() {
+            if let Some(cwd) = json_value.get("cwd").and_then(|v| v.as_str()) {
+                actual_project_path = Some(cwd.to_string());
             }
         }
+
+        // Try to parse as JsonlEntry for usage data
+        let entry = match serde_json::from_value::<JsonlEntry>(json_value) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let message = match &entry.message {
+            Some(message) => message,
+            None => continue,
+        };
+        let usage = match &message.usage {
+            Some(usage) => usage,
+            None => continue,
+        };
+
+        // Skip entries without meaningful token usage
+        if usage.input_tokens.unwrap_or(0) == 0
+            && usage.output_tokens.unwrap_or(0) == 0
+            && usage.cache_creation_input_tokens.unwrap_or(0) == 0
+            && usage.cache_read_input_tokens.unwrap_or(0) == 0
+        {
+            continue;
+        }
+
+        // Deduplication key based on message ID and request ID (enforced by a unique index)
+        let message_hash = match (&message.id, &entry.request_id) {
+            (Some(msg_id), Some(req_id)) => Some(format!("{}:{}", msg_id, req_id)),
+            _ => None,
+        };
+
+        let is_live = DateTime::parse_from_rfc3339(&entry.timestamp)
+            .map(|ts| (chrono::Utc::now() - ts.with_timezone(&chrono::Utc)).num_seconds())
+            .is_ok_and(|age| age <= LIVE_PROVIDER_WINDOW_SECS);
+        let entry_provider = provider.filter(|_| is_live);
+
+        let (cost, cost_source) = match entry.cost_usd {
+            Some(cost) => (cost, "reported"),
+            None => {
+                let cost = match &message.model {
+                    Some(model_str) => super::pricing::calculate_cost(
+                        model_str,
+                        entry_provider,
+                        &usage.token_usage(),
+                    ),
+                    None => 0.0,
+                };
+                (cost, "calculated")
+            }
+        };
+
+        // Use actual project path if found, otherwise use encoded name
+        let project_path = actual_project_path
+            .clone()
+            .unwrap_or_else(|| encoded_project_name.to_string());
+
+        entries.push(UsageEntry {
+            timestamp: entry.timestamp.clone(),
+            model: message
+                .model
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+            input_tokens: usage.input_tokens.unwrap_or(0),
+            output_tokens: usage.output_tokens.unwrap_or(0),
+            cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
+            cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+            cost,
+            session_id: entry
+                .session_id
+                .clone()
+                .unwrap_or_else(|| fallback_session_id.clone()),
+            project_path,
+            message_hash,
+            provider: entry_provider.map(|p| p.to_string()),
+            cost_source,
+        });
     }
 
-    entries
+    Ok((entries, consumed as u64, actual_project_path))
 }
 
-fn get_earliest_timestamp(path: &PathBuf) -> Option<String> {
-    if let Ok(content) = fs::read_to_string(path) {
-        let mut earliest_timestamp: Option<String> = None;
-        for line in content.lines() {
-            if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(line) {
-                if let Some(timestamp_str) = json_value.get("timestamp").and_then(|v| v.as_str()) {
-                    if let Some(current_earliest) = &earliest_timestamp {
-                        if timestamp_str < current_earliest.as_str() {
-                            earliest_timestamp = Some(timestamp_str.to_string());
-                        }
-                    } else {
-                        earliest_timestamp = Some(timestamp_str.to_string());
+/// Incrementally ingests new usage rows from ~/.claude/projects into `usage_entries`.
+/// Files are tracked by byte offset and mtime, so unchanged files are skipped and
+/// growing files are only parsed from where the previous pass stopped.
+/// Returns the number of rows inserted.
+pub fn ingest_usage_entries(conn: &mut Connection, claude_path: &Path) -> Result<usize, String> {
+    let _guard = INGEST_LOCK.lock().map_err(|e| e.to_string())?;
+
+    let mut states: HashMap<String, IngestState> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare("SELECT file_path, byte_offset, mtime, project_path FROM usage_ingest_state")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    IngestState {
+                        byte_offset: row.get::<_, i64>(1)? as u64,
+                        mtime: row.get(2)?,
+                        project_path: row.get(3)?,
+                    },
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows.flatten() {
+            states.insert(row.0, row.1);
+        }
+    }
+
+    // Collect files that changed since the last pass
+    let mut pending: Vec<(PathBuf, String, u64, i64)> = Vec::new();
+    if let Ok(projects) = fs::read_dir(claude_path.join("projects")) {
+        for project in projects.flatten() {
+            if !project.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let project_name = project.file_name().to_string_lossy().to_string();
+
+            for entry in walkdir::WalkDir::new(project.path())
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
+            {
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let size = metadata.len();
+                let mtime = file_mtime(&metadata);
+                let key = entry.path().to_string_lossy().to_string();
+
+                if let Some(state) = states.get(&key) {
+                    if state.byte_offset == size && state.mtime == mtime {
+                        continue;
                     }
                 }
+                pending.push((entry.path().to_path_buf(), project_name.clone(), size, mtime));
             }
         }
-        return earliest_timestamp;
     }
-    None
+
+    // Oldest files first so deduplication keeps the original occurrence
+    pending.sort_by_key(|(_, _, _, mtime)| *mtime);
+
+    // Rows written just now are attributed to the provider configured right now
+    let provider = if pending.is_empty() {
+        None
+    } else {
+        super::pricing::current_provider()
+    };
+
+    let mut inserted = 0usize;
+    for (path, project_name, size, mtime) in pending {
+        let state = states.remove(&path.to_string_lossy().to_string());
+        inserted += ingest_file(
+            conn,
+            &path,
+            &project_name,
+            size,
+            mtime,
+            state,
+            provider.as_deref(),
+        )?;
+    }
+
+    if inserted > 0 {
+        log::info!("Ingested {} new usage entries", inserted);
+    }
+    Ok(inserted)
 }
 
-fn get_all_usage_entries(claude_path: &PathBuf) -> Vec<UsageEntry> {
-    let mut all_entries = Vec::new();
-    let mut processed_hashes = HashSet::new();
-    let projects_dir = claude_path.join("projects");
+/// Ingests the unread part of one JSONL file in a single transaction.
+/// The caller must hold `INGEST_LOCK`.
+fn ingest_file(
+    conn: &mut Connection,
+    path: &Path,
+    project_name: &str,
+    size: u64,
+    mtime: i64,
+    state: Option<IngestState>,
+    provider: Option<&str>,
+) -> Result<usize, String> {
+    let key = path.to_string_lossy().to_string();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let (mut offset, mut project_path) = match state {
+        Some(state) => (state.byte_offset, state.project_path),
+        None => (0, None),
+    };
 
-    let mut files_to_process: Vec<(PathBuf, String)> = Vec::new();
+    // The file shrank (rewritten or truncated): drop its rows and start over
+    if size < offset {
+        tx.execute("DELETE FROM usage_entries WHERE source_file = ?1", params![key])
+            .map_err(|e| e.to_string())?;
+        offset = 0;
+        project_path = None;
+    }
 
-    if let Ok(projects) = fs::read_dir(&projects_dir) {
-        for project in projects.flatten() {
-            if project.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                let project_name = project.file_name().to_string_lossy().to_string();
-                let project_path = project.path();
-
-                walkdir::WalkDir::new(&project_path)
-                    .into_iter()
-                    .filter_map(Result::ok)
-                    .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
-                    .for_each(|entry| {
-                        files_to_process.push((entry.path().to_path_buf(), project_name.clone()));
-                    });
+    let (entries, consumed, project_path) =
+        match parse_jsonl_increment(path, offset, project_name, project_path, provider) {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("Failed to read usage from {:?}: {}", path, e);
+                return Ok(0);
             }
+        };
+
+    let mut inserted = 0usize;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT OR IGNORE INTO usage_entries (
+                    session_id, timestamp, model, input_tokens, output_tokens,
+                    cache_creation_tokens, cache_read_tokens, total_tokens, cost,
+                    project_path, message_hash, source_file, provider, cost_source
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )
+            .map_err(|e| e.to_string())?;
+
+        for entry in &entries {
+            let total_tokens = entry.input_tokens
+                + entry.output_tokens
+                + entry.cache_creation_tokens
+                + entry.cache_read_tokens;
+            inserted += stmt
+                .execute(params![
+                    entry.session_id,
+                    entry.timestamp,
+                    entry.model,
+                    entry.input_tokens as i64,
+                    entry.output_tokens as i64,
+                    entry.cache_creation_tokens as i64,
+                    entry.cache_read_tokens as i64,
+                    total_tokens as i64,
+                    entry.cost,
+                    entry.project_path,
+                    entry.message_hash,
+                    key,
+                    entry.provider,
+                    entry.cost_source,
+                ])
+                .map_err(|e| e.to_string())?;
         }
     }
 
-    // Sort files by their earliest timestamp to ensure chronological processing
-    // and deterministic deduplication
-    files_to_process.sort_by_cached_key(|(path, _)| get_earliest_timestamp(path));
+    tx.execute(
+        "INSERT OR REPLACE INTO usage_ingest_state (file_path, byte_offset, mtime, project_path, updated_at)
+         VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)",
+        params![key, (offset + consumed) as i64, mtime, project_path],
+    )
+    .map_err(|e| e.to_string())?;
 
-    for (path, project_name) in files_to_process {
-        let entries = parse_jsonl_file(&path, &project_name, &mut processed_hashes);
-        all_entries.extend(entries);
-    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(inserted)
+}
 
-    // Sort by timestamp
-    all_entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+/// Recomputes the cost of every locally priced usage row with the current pricing table.
+/// Rows whose cost was reported by the CLI (costUSD) are left untouched.
+pub fn recalculate_usage_costs(conn: &Connection) -> Result<usize, String> {
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let mut updated = 0usize;
+    {
+        let mut select = tx
+            .prepare(
+                "SELECT id, model, provider, input_tokens, output_tokens,
+                        cache_creation_tokens, cache_read_tokens
+                 FROM usage_entries WHERE cost_source = 'calculated'",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = select
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    TokenUsage {
+                        input_tokens: row.get::<_, i64>(3)? as u64,
+                        output_tokens: row.get::<_, i64>(4)? as u64,
+                        cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+                        cache_read_tokens: row.get::<_, i64>(6)? as u64,
+                    },
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let mut update = tx
+            .prepare("UPDATE usage_entries SET cost = ?1 WHERE id = ?2")
+            .map_err(|e| e.to_string())?;
+        for (id, model, provider, usage) in rows {
+            let cost = super::pricing::calculate_cost(&model, provider.as_deref(), &usage);
+            updated += update.execute(params![cost, id]).map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
 
-    all_entries
+    log::info!("Recalculated cost for {} usage entries", updated);
+    Ok(updated)
 }
 
-#[command]
-pub fn get_usage_stats(days: Option<u32>) -> Result<UsageStats, String> {
-    let claude_path = dirs::home_dir()
+fn get_claude_path() -> Result<PathBuf, String> {
+    Ok(dirs::home_dir()
         .ok_or("Failed to get home directory")?
-        .join(".claude");
-
-    let all_entries = get_all_usage_entries(&claude_path);
-
-    if all_entries.is_empty() {
-        return Ok(UsageStats {
-            total_cost: 0.0,
-            total_tokens: 0,
-            total_input_tokens: 0,
-            total_output_tokens: 0,
-            total_cache_creation_tokens: 0,
-            total_cache_read_tokens: 0,
-            total_sessions: 0,
-            by_model: vec![],
-            by_date: vec![],
-            by_project: vec![],
-        });
-    }
+        .join(".claude"))
+}
 
-    // Filter by days if specified
-    // 🚀 修复时区问题：使用本地时区进行日期比较
-    let filtered_entries = if let Some(days) = days {
-        let cutoff = Local::now().date_naive() - chrono::Duration::days(days as i64);
-        all_entries
-            .into_iter()
-            .filter(|e| {
-                if let Ok(dt) = DateTime::parse_from_rfc3339(&e.timestamp) {
-                    // 转换为本地时区后提取日期进行比较
-                    dt.with_timezone(&Local).date_naive() >= cutoff
-                } else {
-                    false
-                }
-            })
-            .collect()
-    } else {
-        all_entries
-    };
+/// Brings `usage_entries` up to date. The app relies on the background ingester instead;
+/// this is for callers without one (the headless CLI), on their own connection.
+pub fn refresh_usage_entries(conn: &mut Connection) -> Result<(), String> {
+    let claude_path = get_claude_path()?;
+    ingest_usage_entries(conn, &claude_path)?;
+    Ok(())
+}
 
-    // Calculate aggregated stats
-    let mut total_cost = 0.0;
-    let mut total_input_tokens = 0u64;
-    let mut total_output_tokens = 0u64;
-    let mut total_cache_creation_tokens = 0u64;
-    let mut total_cache_read_tokens = 0u64;
-
-    let mut model_stats: HashMap<String, ModelUsage> = HashMap::new();
-    let mut daily_stats: HashMap<String, DailyUsage> = HashMap::new();
-    let mut project_stats: HashMap<String, ProjectUsage> = HashMap::new();
-
-    for entry in &filtered_entries {
-        // Update totals
-        total_cost += entry.cost;
-        total_input_tokens += entry.input_tokens;
-        total_output_tokens += entry.output_tokens;
-        total_cache_creation_tokens += entry.cache_creation_tokens;
-        total_cache_read_tokens += entry.cache_read_tokens;
-
-        // Update model stats
-        let model_stat = model_stats
-            .entry(entry.model.clone())
-            .or_insert(ModelUsage {
-                model: entry.model.clone(),
-                total_cost: 0.0,
-                total_tokens: 0,
-                input_tokens: 0,
-                output_tokens: 0,
-                cache_creation_tokens: 0,
-                cache_read_tokens: 0,
-                session_count: 0,
-            });
-        model_stat.total_cost += entry.cost;
-        model_stat.input_tokens += entry.input_tokens;
-        model_stat.output_tokens += entry.output_tokens;
-        model_stat.cache_creation_tokens += entry.cache_creation_tokens;
-        model_stat.cache_read_tokens += entry.cache_read_tokens;
-        model_stat.total_tokens = model_stat.input_tokens + model_stat.output_tokens;
-        model_stat.session_count += 1;
-
-        // Update daily stats
-        // 🚀 修复时区问题：使用本地日期而不是 UTC 日期
-        let date = if let Ok(dt) = DateTime::parse_from_rfc3339(&entry.timestamp) {
-            // 转换为本地时间后提取日期
-            dt.with_timezone(&Local).format("%Y-%m-%d").to_string()
-        } else {
-            // 降级：直接从字符串提取（可能不准确）
-            entry.timestamp
-                .split('T')
-                .next()
-                .unwrap_or(&entry.timestamp)
-                .to_string()
+/// Starts the background usage ingester on its own database connection.
+/// Emits `usage-entries-updated` with the number of new rows after each productive pass.
+pub fn start_usage_ingester(app: AppHandle) {
+    std::thread::spawn(move || {
+        let db_path = match app.path().app_data_dir() {
+            Ok(dir) => dir.join("agents.db"),
+            Err(e) => {
+                log::error!("Usage ingester could not resolve app data dir: {}", e);
+                return;
+            }
         };
-        let daily_stat = daily_stats.entry(date.clone()).or_insert(DailyUsage {
-            date,
-            total_cost: 0.0,
-            total_tokens: 0,
-            models_used: vec![],
-        });
-        daily_stat.total_cost += entry.cost;
-        daily_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        if !daily_stat.models_used.contains(&entry.model) {
-            daily_stat.models_used.push(entry.model.clone());
-        }
 
-        // Update project stats
-        let project_stat =
-            project_stats
-                .entry(entry.project_path.clone())
-                .or_insert(ProjectUsage {
-                    project_path: entry.project_path.clone(),
-                    project_name: entry
-                        .project_path
-                        .split('/')
-                        .last()
-                        .unwrap_or(&entry.project_path)
-                        .to_string(),
-                    total_cost: 0.0,
-                    total_tokens: 0,
-                    session_count: 0,
-                    last_used: entry.timestamp.clone(),
-                });
-        project_stat.total_cost += entry.cost;
-        project_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        project_stat.session_count += 1;
-        if entry.timestamp > project_stat.last_used {
-            project_stat.last_used = entry.timestamp.clone();
+        let mut conn = match Connection::open(&db_path) {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Usage ingester failed to open database: {}", e);
+                return;
+            }
+        };
+        let _ = conn.busy_timeout(Duration::from_secs(5));
+
+        loop {
+            match get_claude_path().and_then(|path| ingest_usage_entries(&mut conn, &path)) {
+                Ok(inserted) if inserted > 0 => {
+                    let _ = app.emit("usage-entries-updated", inserted);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Background usage ingestion failed: {}", e),
+            }
+            std::thread::sleep(Duration::from_secs(INGEST_INTERVAL_SECS));
         }
-    }
+    });
+}
 
-    let total_tokens = total_input_tokens
-        + total_output_tokens
-        + total_cache_creation_tokens
-        + total_cache_read_tokens;
-    let total_sessions = filtered_entries.len() as u64;
+/// SQL expression for an entry's local calendar date
+/// 🚀 修复时区问题：使用本地时区进行日期比较
+pub(crate) const LOCAL_DATE_SQL: &str = "date(timestamp, 'localtime')";
+
+fn project_name_from_path(project_path: &str) -> String {
+    project_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(project_path)
+        .to_string()
+}
+
+/// Aggregates project usage from `usage_entries` for rows matching `filter`
+fn query_project_usage(
+    conn: &Connection,
+    filter: &str,
+    filter_params: &[&dyn ToSql],
+    ascending: bool,
+) -> Result<Vec<ProjectUsage>, String> {
+    let sql = format!(
+        "SELECT COALESCE(project_path, ''), SUM(cost), SUM(total_tokens), COUNT(DISTINCT session_id), MAX(timestamp)
+         FROM usage_entries WHERE {} GROUP BY project_path ORDER BY SUM(cost) {}",
+        filter,
+        if ascending { "ASC" } else { "DESC" }
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(filter_params, |row| {
+            let project_path: String = row.get(0)?;
+            Ok(ProjectUsage {
+                project_name: project_name_from_path(&project_path),
+                project_path,
+                total_cost: row.get(1)?,
+                total_tokens: row.get::<_, i64>(2)? as u64,
+                session_count: row.get::<_, i64>(3)? as u64,
+                last_used: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
 
-    // Convert hashmaps to sorted vectors
-    let mut by_model: Vec<ModelUsage> = model_stats.into_values().collect();
-    by_model.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
 
-    let mut by_date: Vec<DailyUsage> = daily_stats.into_values().collect();
-    by_date.sort_by(|a, b| b.date.cmp(&a.date));
+/// Aggregates usage statistics from `usage_entries` for rows matching `filter`.
+/// `distinct_sessions` counts unique session IDs instead of entries for `total_sessions`.
+fn query_usage_stats(
+    conn: &Connection,
+    filter: &str,
+    filter_params: &[&dyn ToSql],
+    distinct_sessions: bool,
+) -> Result<UsageStats, String> {
+    let (total_cost, total_input_tokens, total_output_tokens, total_cache_creation_tokens, total_cache_read_tokens, entry_count, session_count) = conn
+        .query_row(
+            &format!(
+                "SELECT COALESCE(SUM(cost), 0.0), COALESCE(SUM(input_tokens), 0),
+                        COALESCE(SUM(output_tokens), 0), COALESCE(SUM(cache_creation_tokens), 0),
+                        COALESCE(SUM(cache_read_tokens), 0), COUNT(*), COUNT(DISTINCT session_id)
+                 FROM usage_entries WHERE {}",
+                filter
+            ),
+            filter_params,
+            |row| {
+                Ok((
+                    row.get::<_, f64>(0)?,
+                    row.get::<_, i64>(1)? as u64,
+                    row.get::<_, i64>(2)? as u64,
+                    row.get::<_, i64>(3)? as u64,
+                    row.get::<_, i64>(4)? as u64,
+                    row.get::<_, i64>(5)? as u64,
+                    row.get::<_, i64>(6)? as u64,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+
+    // By model
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT model, SUM(cost), SUM(input_tokens), SUM(output_tokens),
+                    SUM(cache_creation_tokens), SUM(cache_read_tokens), COUNT(*)
+             FROM usage_entries WHERE {} GROUP BY model ORDER BY SUM(cost) DESC",
+            filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let by_model = stmt
+        .query_map(filter_params, |row| {
+            let input_tokens = row.get::<_, i64>(2)? as u64;
+            let output_tokens = row.get::<_, i64>(3)? as u64;
+            Ok(ModelUsage {
+                model: row.get(0)?,
+                total_cost: row.get(1)?,
+                total_tokens: input_tokens + output_tokens,
+                input_tokens,
+                output_tokens,
+                cache_creation_tokens: row.get::<_, i64>(4)? as u64,
+                cache_read_tokens: row.get::<_, i64>(5)? as u64,
+                session_count: row.get::<_, i64>(6)? as u64,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // By local date; fall back to the raw date prefix for unparseable timestamps
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT COALESCE({}, substr(timestamp, 1, 10)) AS day, SUM(cost), SUM(total_tokens),
+                    GROUP_CONCAT(DISTINCT model)
+             FROM usage_entries WHERE {} GROUP BY day ORDER BY day DESC",
+            LOCAL_DATE_SQL, filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let by_date = stmt
+        .query_map(filter_params, |row| {
+            let models: Option<String> = row.get(3)?;
+            Ok(DailyUsage {
+                date: row.get(0)?,
+                total_cost: row.get(1)?,
+                total_tokens: row.get::<_, i64>(2)? as u64,
+                models_used: models
+                    .map(|m| m.split(',').map(|s| s.to_string()).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
 
-    let mut by_project: Vec<ProjectUsage> = project_stats.into_values().collect();
-    by_project.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+    let by_project = query_project_usage(conn, filter, filter_params, false)?;
 
     Ok(UsageStats {
         total_cost,
-        total_tokens,
+        total_tokens: total_input_tokens
+            + total_output_tokens
+            + total_cache_creation_tokens
+            + total_cache_read_tokens,
         total_input_tokens,
         total_output_tokens,
         total_cache_creation_tokens,
         total_cache_read_tokens,
-        total_sessions,
+        total_sessions: if distinct_sessions { session_count } else { entry_count },
         by_model,
         by_date,
         by_project,
     })
 }
 
-#[command]
-pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<UsageStats, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
+/// Aggregates the last `days` days (all time when None)
+pub fn usage_stats(conn: &Connection, days: Option<u32>) -> Result<UsageStats, String> {
+    // Filter by days if specified
+    match days {
+        Some(days) => {
+            let cutoff = (Local::now().date_naive() - chrono::Duration::days(days as i64))
+                .format("%Y-%m-%d")
+                .to_string();
+            query_usage_stats(
+                conn,
+                &format!("{} >= ?1", LOCAL_DATE_SQL),
+                &[&cutoff],
+                false,
+            )
+        }
+        None => query_usage_stats(conn, "1 = 1", &[], false),
+    }
+}
 
-    let all_entries = get_all_usage_entries(&claude_path);
+#[command]
+pub fn get_usage_stats(db: State<'_, AgentDb>, days: Option<u32>) -> Result<UsageStats, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    usage_stats(&conn, days)
+}
 
+#[command]
+pub fn get_usage_by_date_range(
+    db: State<'_, AgentDb>,
+    start_date: String,
+    end_date: String,
+) -> Result<UsageStats, String> {
     // Parse dates
     let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d").or_else(|_| {
         DateTime::parse_from_rfc3339(&start_date)
@@ -493,223 +730,51 @@ pub fn get_usage_by_date_range(start_date: String, end_date: String) -> Result<U
             .map_err(|e| format!("Invalid end date: {}", e))
     })?;
 
-    // Filter entries by date range
-    // 🚀 修复时区问题：转换为本地时区后进行日期比较
-    let filtered_entries: Vec<_> = all_entries
-        .into_iter()
-        .filter(|e| {
-            if let Ok(dt) = DateTime::parse_from_rfc3339(&e.timestamp) {
-                // 先转换为本地时区，再提取日期进行比较
-                let date = dt.with_timezone(&Local).date_naive();
-                date >= start && date <= end
-            } else {
-                false
-            }
-        })
-        .collect();
-
-    if filtered_entries.is_empty() {
-        return Ok(UsageStats {
-            total_cost: 0.0,
-            total_tokens: 0,
-            total_input_tokens: 0,
-            total_output_tokens: 0,
-            total_cache_creation_tokens: 0,
-            total_cache_read_tokens: 0,
-            total_sessions: 0,
-            by_model: vec![],
-            by_date: vec![],
-            by_project: vec![],
-        });
-    }
-
-    // Calculate aggregated stats from filtered entries
-    let mut total_cost = 0.0;
-    let mut total_input_tokens = 0u64;
-    let mut total_output_tokens = 0u64;
-    let mut total_cache_creation_tokens = 0u64;
-    let mut total_cache_read_tokens = 0u64;
-
-    let mut model_stats: HashMap<String, ModelUsage> = HashMap::new();
-    let mut daily_stats: HashMap<String, DailyUsage> = HashMap::new();
-    let mut project_stats: HashMap<String, ProjectUsage> = HashMap::new();
-
-    for entry in &filtered_entries {
-        // Update totals
-        total_cost += entry.cost;
-        total_input_tokens += entry.input_tokens;
-        total_output_tokens += entry.output_tokens;
-        total_cache_creation_tokens += entry.cache_creation_tokens;
-        total_cache_read_tokens += entry.cache_read_tokens;
-
-        // Update model stats
-        let model_stat = model_stats
-            .entry(entry.model.clone())
-            .or_insert(ModelUsage {
-                model: entry.model.clone(),
-                total_cost: 0.0,
-                total_tokens: 0,
-                input_tokens: 0,
-                output_tokens: 0,
-                cache_creation_tokens: 0,
-                cache_read_tokens: 0,
-                session_count: 0,
-            });
-        model_stat.total_cost += entry.cost;
-        model_stat.input_tokens += entry.input_tokens;
-        model_stat.output_tokens += entry.output_tokens;
-        model_stat.cache_creation_tokens += entry.cache_creation_tokens;
-        model_stat.cache_read_tokens += entry.cache_read_tokens;
-        model_stat.total_tokens = model_stat.input_tokens + model_stat.output_tokens;
-        model_stat.session_count += 1;
-
-        // Update daily stats
-        // 🚀 修复时区问题：使用本地日期而不是 UTC 日期
-        let date = if let Ok(dt) = DateTime::parse_from_rfc3339(&entry.timestamp) {
-            // 转换为本地时间后提取日期
-            dt.with_timezone(&Local).format("%Y-%m-%d").to_string()
-        } else {
-            // 降级：直接从字符串提取（可能不准确）
-            entry.timestamp
-                .split('T')
-                .next()
-                .unwrap_or(&entry.timestamp)
-                .to_string()
-        };
-        let daily_stat = daily_stats.entry(date.clone()).or_insert(DailyUsage {
-            date,
-            total_cost: 0.0,
-            total_tokens: 0,
-            models_used: vec![],
-        });
-        daily_stat.total_cost += entry.cost;
-        daily_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        if !daily_stat.models_used.contains(&entry.model) {
-            daily_stat.models_used.push(entry.model.clone());
-        }
-
-        // Update project stats
-        let project_stat =
-            project_stats
-                .entry(entry.project_path.clone())
-                .or_insert(ProjectUsage {
-                    project_path: entry.project_path.clone(),
-                    project_name: entry
-                        .project_path
-                        .split('/')
-                        .last()
-                        .unwrap_or(&entry.project_path)
-                        .to_string(),
-                    total_cost: 0.0,
-                    total_tokens: 0,
-                    session_count: 0,
-                    last_used: entry.timestamp.clone(),
-                });
-        project_stat.total_cost += entry.cost;
-        project_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        project_stat.session_count += 1;
-        if entry.timestamp > project_stat.last_used {
-            project_stat.last_used = entry.timestamp.clone();
-        }
-    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    let unique_sessions: HashSet<_> = filtered_entries.iter().map(|e| &e.session_id).collect();
-
-    Ok(UsageStats {
-        total_cost,
-        total_tokens: total_input_tokens
-            + total_output_tokens
-            + total_cache_creation_tokens
-            + total_cache_read_tokens,
-        total_input_tokens,
-        total_output_tokens,
-        total_cache_creation_tokens,
-        total_cache_read_tokens,
-        total_sessions: unique_sessions.len() as u64,
-        by_model: model_stats.into_values().collect(),
-        by_date: daily_stats.into_values().collect(),
-        by_project: project_stats.into_values().collect(),
-    })
+    let start = start.format("%Y-%m-%d").to_string();
+    let end = end.format("%Y-%m-%d").to_string();
+    query_usage_stats(
+        &conn,
+        &format!("{} BETWEEN ?1 AND ?2", LOCAL_DATE_SQL),
+        &[&start, &end],
+        true,
+    )
 }
 
 #[command]
 pub fn get_session_stats(
+    db: State<'_, AgentDb>,
     since: Option<String>,
     until: Option<String>,
     order: Option<String>,
 ) -> Result<Vec<ProjectUsage>, String> {
-    let claude_path = dirs::home_dir()
-        .ok_or("Failed to get home directory")?
-        .join(".claude");
-
-    let all_entries = get_all_usage_entries(&claude_path);
-
-    // Filter by date range if provided
-    // 🚀 修复时区问题：转换为本地时区后进行日期比较
-    let filtered_entries: Vec<_> = all_entries
-        .into_iter()
-        .filter(|e| {
-            if let (Some(since_str), Some(until_str)) = (&since, &until) {
-                if let (Ok(since_date), Ok(until_date)) = (
-                    NaiveDate::parse_from_str(since_str, "%Y%m%d"),
-                    NaiveDate::parse_from_str(until_str, "%Y%m%d"),
-                ) {
-                    if let Ok(dt) = DateTime::parse_from_rfc3339(&e.timestamp) {
-                        // 先转换为本地时区，再提取日期进行比较
-                        let date = dt.with_timezone(&Local).date_naive();
-                        return date >= since_date && date <= until_date;
-                    }
-                }
-            }
-            true
-        })
-        .collect();
-
-    // Group by project
-    let mut project_stats: HashMap<String, ProjectUsage> = HashMap::new();
-    for entry in filtered_entries {
-        let project_stat =
-            project_stats
-                .entry(entry.project_path.clone())
-                .or_insert(ProjectUsage {
-                    project_path: entry.project_path.clone(),
-                    project_name: entry
-                        .project_path
-                        .split('/')
-                        .last()
-                        .unwrap_or(&entry.project_path)
-                        .to_string(),
-                    total_cost: 0.0,
-                    total_tokens: 0,
-                    session_count: 0,
-                    last_used: entry.timestamp.clone(),
-                });
-        project_stat.total_cost += entry.cost;
-        project_stat.total_tokens += entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        project_stat.session_count += 1;
-        if entry.timestamp > project_stat.last_used {
-            project_stat.last_used = entry.timestamp.clone();
-        }
-    }
-
-    let mut by_session: Vec<ProjectUsage> = project_stats.into_values().collect();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let ascending = order.as_deref() == Some("asc");
+
+    // Filter by date range only if both bounds are valid
+    let range = match (&since, &until) {
+        (Some(since_str), Some(until_str)) => match (
+            NaiveDate::parse_from_str(since_str, "%Y%m%d"),
+            NaiveDate::parse_from_str(until_str, "%Y%m%d"),
+        ) {
+            (Ok(since_date), Ok(until_date)) => Some((
+                since_date.format("%Y-%m-%d").to_string(),
+                until_date.format("%Y-%m-%d").to_string(),
+            )),
+            _ => None,
+        },
+        _ => None,
+    };
 
-    // Sort by order
-    let order_str = order.unwrap_or_else(|| "desc".to_string());
-    if order_str == "asc" {
-        by_session.sort_by(|a, b| a.total_cost.partial_cmp(&b.total_cost).unwrap());
-    } else {
-        by_session.sort_by(|a, b| b.total_cost.partial_cmp(&a.total_cost).unwrap());
+    match range {
+        Some((since_date, until_date)) => query_project_usage(
+            &conn,
+            &format!("{} BETWEEN ?1 AND ?2", LOCAL_DATE_SQL),
+            &[&since_date, &until_date],
+            ascending,
+        ),
+        None => query_project_usage(&conn, "1 = 1", &[], ascending),
     }
-
-    Ok(by_session)
 }
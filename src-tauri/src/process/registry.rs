@@ -30,6 +30,7 @@ pub struct ProcessHandle {
     pub info: ProcessInfo,
     pub child: Arc<Mutex<Option<Child>>>,
     pub live_output: Arc<Mutex<String>>,
+    pub tab_id: Option<String>, // Frontend tab that owns the process (Claude sessions only)
     #[cfg(windows)]
     pub job_object: Option<Arc<JobObject>>, // Job object for automatic cleanup on Windows
 }
@@ -82,10 +83,52 @@ impl ProcessRegistry {
             model,
         };
 
-        self.register_process_internal(run_id, process_info, child)
+        self.register_process_internal(run_id, process_info, child, None)
+    }
+
+    /// Register a freshly spawned Claude process together with its child handle.
+    /// The session ID is unknown until Claude emits its init message, so the entry
+    /// starts out pending and is completed later via `set_claude_session_id`.
+    pub fn register_claude_process(
+        &self,
+        tab_id: Option<String>,
+        pid: u32,
+        project_path: String,
+        task: String,
+        model: String,
+        child: Child,
+    ) -> Result<i64, String> {
+        let run_id = self.generate_id()?;
+
+        let process_info = ProcessInfo {
+            run_id,
+            process_type: ProcessType::ClaudeSession {
+                session_id: String::new(),
+            },
+            pid,
+            started_at: Utc::now(),
+            project_path,
+            task,
+            model,
+        };
+
+        self.register_process_internal(run_id, process_info, child, tab_id)?;
+        Ok(run_id)
+    }
+
+    /// Attach the Claude session ID (from the init message) to a pending process
+    pub fn set_claude_session_id(&self, run_id: i64, session_id: String) -> Result<(), String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get_mut(&run_id) {
+            handle.info.process_type = ProcessType::ClaudeSession { session_id };
+            Ok(())
+        } else {
+            Err(format!("Process {} not found in registry", run_id))
+        }
     }
 
     /// Register a new Claude session (without child process - handled separately)
+    #[allow(dead_code)]
     pub fn register_claude_session(
         &self,
         session_id: String,
@@ -106,7 +149,7 @@ impl ProcessRegistry {
             model,
         };
 
-        // Register without child - Claude sessions use ClaudeProcessState for process management
+        // Register without child - the caller keeps ownership of the process
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
         // Create Job Object on Windows for automatic process cleanup
@@ -140,6 +183,7 @@ impl ProcessRegistry {
             info: process_info,
             child: Arc::new(Mutex::new(None)), // No child handle for Claude sessions
             live_output: Arc::new(Mutex::new(String::new())),
+            tab_id: None,
             #[cfg(windows)]
             job_object,
         };
@@ -149,12 +193,12 @@ impl ProcessRegistry {
     }
 
     /// Internal method to register any process
-    #[allow(dead_code)]
     fn register_process_internal(
         &self,
         run_id: i64,
         process_info: ProcessInfo,
         child: Child,
+        tab_id: Option<String>,
     ) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
@@ -190,6 +234,7 @@ impl ProcessRegistry {
             info: process_info,
             child: Arc::new(Mutex::new(Some(child))),
             live_output: Arc::new(Mutex::new(String::new())),
+            tab_id,
             #[cfg(windows)]
             job_object,
         };
@@ -204,7 +249,10 @@ impl ProcessRegistry {
         Ok(processes
             .values()
             .filter_map(|handle| match &handle.info.process_type {
-                ProcessType::ClaudeSession { .. } => Some(handle.info.clone()),
+                // Skip processes that have not reported their session ID yet
+                ProcessType::ClaudeSession { session_id } if !session_id.is_empty() => {
+                    Some(handle.info.clone())
+                }
                 _ => None,
             })
             .collect())
@@ -225,8 +273,55 @@ impl ProcessRegistry {
             .map(|handle| handle.info.clone()))
     }
 
+    /// Get the most recent Claude process started from a specific frontend tab
+    pub fn get_claude_process_by_tab(&self, tab_id: &str) -> Result<Option<ProcessInfo>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes
+            .values()
+            .filter(|handle| {
+                matches!(handle.info.process_type, ProcessType::ClaudeSession { .. })
+                    && handle.tab_id.as_deref() == Some(tab_id)
+            })
+            .max_by_key(|handle| handle.info.run_id)
+            .map(|handle| handle.info.clone()))
+    }
+
+    /// Wait for a registered process to exit.
+    /// Returns `None` if the process was killed or removed from the registry meanwhile.
+    pub async fn wait_for_exit(&self, run_id: i64) -> Option<std::process::ExitStatus> {
+        loop {
+            let child_arc = {
+                let processes = self.processes.lock().ok()?;
+                processes.get(&run_id)?.child.clone()
+            };
+
+            let status = {
+                let mut child_guard = child_arc.lock().ok()?;
+                match child_guard.as_mut() {
+                    Some(child) => match child.try_wait() {
+                        Ok(Some(status)) => {
+                            *child_guard = None;
+                            Some(Some(status))
+                        }
+                        Ok(None) => None,
+                        Err(e) => {
+                            log::error!("Error checking status of process {}: {}", run_id, e);
+                            Some(None)
+                        }
+                    },
+                    // Handle already cleared by kill_process
+                    None => Some(None),
+                }
+            };
+
+            match status {
+                Some(result) => return result,
+                None => tokio::time::sleep(tokio::time::Duration::from_millis(100)).await,
+            }
+        }
+    }
+
     /// Unregister a process (called when it completes)
-    #[allow(dead_code)]
     pub fn unregister_process(&self, run_id: i64) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
         processes.remove(&run_id);